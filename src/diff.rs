@@ -0,0 +1,65 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+pub const PREFIX_SAME: &str = "  ";
+pub const PREFIX_REMOVED: &str = "- ";
+pub const PREFIX_ADDED: &str = "+ ";
+
+// Creates a line based diff between old and new which is derived from a longest common subsequence. Each line of the
+// result is prefixed by PREFIX_SAME, PREFIX_REMOVED or PREFIX_ADDED.
+pub fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                std::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut res: Vec<String> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while (i < a.len()) && (j < b.len()) {
+        if a[i] == b[j] {
+            res.push(format!("{}{}", PREFIX_SAME, a[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            res.push(format!("{}{}", PREFIX_REMOVED, a[i]));
+            i += 1;
+        } else {
+            res.push(format!("{}{}", PREFIX_ADDED, b[j]));
+            j += 1;
+        }
+    }
+
+    for k in i..a.len() {
+        res.push(format!("{}{}", PREFIX_REMOVED, a[k]));
+    }
+
+    for k in j..b.len() {
+        res.push(format!("{}{}", PREFIX_ADDED, b[k]));
+    }
+
+    return res;
+}
@@ -17,6 +17,23 @@ limitations under the License. */
 mod rijndael;
 mod chacha20;
 mod derivers;
+mod keyslots;
+mod recipients;
+mod calibrate;
+mod recovery;
+mod records;
+mod shamir;
+
+pub use keyslots::KeySlots;
+use keyslots::KeySlot;
+pub use recipients::{RecipientCryptor, RecipientKey, PrivateKey};
+pub use recipients::{generate_key_pair, public_key_file_name, read_public_key, read_private_key, recipients_from_header};
+use recipients::Recipient;
+pub use calibrate::{Calibration, calibrate};
+#[cfg(test)]
+pub use calibrate::calibrate_with;
+pub use records::RecordCryptor;
+pub use recovery::{RECOVERY_KEY_SLOT, generate_recovery_secret, recovery_password, split_recovery_secret, combine_shares, share_to_qr};
 
 use std::fs::File;
 use std::io::BufReader;
@@ -27,21 +44,44 @@ use std::io::{Error, ErrorKind};
 use rand::RngCore;
 
 use serde::{Serialize, Deserialize};
-use cipher::consts::{U12, U16};
+use cipher::consts::U16;
+use cipher::typenum::Unsigned;
 use cipher::generic_array::GenericArray;
 use base64::prelude::*;
 use crate::persist::SendSyncPersister;
-use aead::{Aead, KeyInit, AeadInPlace, AeadCore, KeySizeUser};
+use aead::{Aead, KeyInit, AeadInPlace, AeadCore, KeySizeUser, Payload};
+use crate::secret::{SecretBytes, SecretKey, SecretString};
+use sha2::{Sha256, Digest};
+use zeroize::Zeroize;
 
 
 const DEFAULT_TAG_SIZE: usize = 16;
 const DEFAULT_NONCE_SIZE: usize = 12;
+// XChaCha20 Poly-1305 uses 192 bit nonces which can safely be chosen at random
+const EXTENDED_NONCE_SIZE: usize = 24;
 const DEFAULT_SALT_SIZE: usize = 16;
+const KEY_FILE_SIZE: usize = 64;
+// Version 2 authenticates the header
+const CONTAINER_VERSION: u32 = 2;
+// Version 3 encrypts the payload with a data key which is stored in key slots
+const KEY_SLOTS_VERSION: u32 = 3;
+// Version 4 encrypts the payload with a data key which is wrapped for public key recipients
+const RECIPIENTS_VERSION: u32 = 4;
 // bcrypt has an input length limitation.
 // It does not seem to be clear what this limitation is though.
 // One recommendation is that 50 is a safe choice for all sensible
-// bcrypt implementations.
+// bcrypt implementations. bcrypt is not offered anymore but the limit
+// is kept for the legacy sha256 KDF.
 const MAX_PW_SIZE_IN_BYTES: usize = 50;  
+// Argon2 and scrypt can process passwords of any length. This limit is large enough for
+// long diceware passphrases.
+const MAX_PASSPHRASE_SIZE_IN_BYTES: usize = 1024;
+// Files in the binary format begin with these bytes. Files in the JSON format begin with '{'.
+const BINARY_MAGIC: &[u8; 4] = b"RPWM";
+// Version of the binary encoding. It is independent of the container version in the header.
+const BINARY_ENCODING_VERSION: u8 = 1;
+const FORMAT_JSON: &str = "json";
+const FORMAT_BINARY: &str = "binary";
 
 const KDF_SCRYPT: &str = "scrypt";
 const KDF_ARGON2: &str = "argon2";
@@ -49,18 +89,89 @@ const KDF_SHA256: &str = "sha256";
 const CIP_AES256: &str = "aes256";
 const CIP_AES192: &str = "aes192";
 const CIP_CHACHA20: &str = "chacha20";
+const CIP_XCHACHA20: &str = "xchacha20";
+const CIP_AES256SIV: &str = "aes256siv";
+const ALGO_AES256: &str = "AES-256 GCM";
+const ALGO_AES192: &str = "AES-192 GCM";
+const ALGO_CHACHA20: &str = "ChaCha20 Poly-1305";
+const ALGO_XCHACHA20: &str = "XChaCha20 Poly-1305";
+const ALGO_AES256SIV: &str = "AES-256 GCM-SIV";
 
 pub const DEFAULT_KDF_ID: KdfId = KdfId::Argon2;
 
 
 // This trait describes a "thing" which knows how to en- and decrypt a byte vector and to serialize, deserialize,
 // load and save the encrypted data structure.
+// A cipher only has to implement en- and decryption. All other state is kept in the AeadContext which is returned
+// by ctx and ctx_mut.
 pub trait Cryptor {
     fn encrypt(&mut self, pw: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>>;
-    fn decrypt(&mut self, pw: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>>;
-    fn to_dyn_writer(&self, writer: &mut dyn Write, data: &Vec<u8>) -> std::io::Result<()>;
-    fn from_dyn_reader(&mut self, reader: &mut dyn Read)-> std::io::Result<Vec<u8>>;
-    fn algo_name(&self) -> &'static str;
+    fn decrypt(&mut self, pw: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes>;
+    fn ctx(&self) -> &AeadContext;
+    fn ctx_mut(&mut self) -> &mut AeadContext;
+
+    fn to_dyn_writer(&self, writer: &mut dyn Write, data: &Vec<u8>) -> std::io::Result<()> {
+        return self.ctx().to_writer(writer, data, self.cipher_id());
+    }
+
+    fn from_dyn_reader(&mut self, reader: &mut dyn Read)-> std::io::Result<Vec<u8>> {
+        let cipher_id = self.cipher_id();
+        return self.ctx_mut().from_reader(reader, cipher_id);
+    }
+
+    fn algo_name(&self) -> &'static str {
+        return self.cipher_id().algo_name();
+    }
+
+    fn cipher_id(&self) -> CipherId {
+        return self.ctx().cipher_id;
+    }
+
+    fn kdf_params(&self) -> KdfParams {
+        return self.ctx().kdf_params;
+    }
+
+    fn set_kdf_params(&mut self, params: KdfParams) {
+        self.ctx_mut().kdf_params = params;
+    }
+
+    // SHA-256 hash of the key file which is used in addition to the password. See key_file_hash.
+    fn set_key_file(&mut self, key_file: Option<SecretBytes>) {
+        self.ctx_mut().key_file = key_file;
+    }
+
+    // The KDF which was found in the header of the last file that was read
+    fn kdf(&self) -> (KeyDeriver, KdfId) {
+        return (self.ctx().kdf, self.ctx().kdf_id);
+    }
+
+    // Key slots of the last file that was read or None if the file does not use key slots
+    fn key_slots(&self) -> Option<KeySlots> {
+        return self.ctx().key_slots.clone();
+    }
+
+    fn set_key_slots(&mut self, key_slots: Option<KeySlots>) {
+        self.ctx_mut().key_slots = key_slots;
+    }
+
+    // If set the payload is en- and decrypted with this key instead of a key which is derived from the password
+    fn set_data_key(&mut self, data_key: Option<SecretBytes>) {
+        self.ctx_mut().data_key = data_key;
+    }
+
+    // The format of the last file that was read. It is also used when writing.
+    fn container_format(&self) -> ContainerFormat {
+        return self.ctx().format;
+    }
+
+    fn set_container_format(&mut self, format: ContainerFormat) {
+        self.ctx_mut().format = format;
+    }
+
+    // Public keys for which the last file that was read is encrypted
+    fn recipients(&self) -> Vec<RecipientKey> {
+        return Vec::new();
+    }
 
     fn to_file(&self, data: &Vec<u8>, file_name: &str) -> std::io::Result<()> {
         let file = File::create(file_name)?;
@@ -120,6 +231,14 @@ impl KdfId {
         return KdfId::from_string(&String::from(name));
     }
 
+    pub fn default_params(self) -> KdfParams {
+        match self {
+            KdfId::Scrypt => KdfParams::new(derivers::SCRYPT_DEFAULT_LOG_N, derivers::SCRYPT_DEFAULT_R, derivers::SCRYPT_DEFAULT_P),
+            KdfId::Argon2 => KdfParams::new(derivers::ARGON2_DEFAULT_M_COST, derivers::ARGON2_DEFAULT_T_COST, derivers::ARGON2_DEFAULT_P_COST),
+            KdfId::Sha256 => KdfParams::new(0, 0, 0)
+        }
+    }
+
     pub fn to_named_func(self) -> (KeyDeriver, KdfId) {
         match self {
             KdfId::Scrypt => (derivers::scrypt_deriver, self),
@@ -128,6 +247,20 @@ impl KdfId {
         }
     }
 
+    // Deprecated KDFs can still be used to open existing files but they should not be used for new files
+    pub fn is_deprecated(self) -> bool {
+        return self == KdfId::Sha256;
+    }
+
+    // Maximum length of a password in bytes which is accepted for new passwords
+    pub fn max_password_len(self) -> usize {
+        match self {
+            KdfId::Scrypt => MAX_PASSPHRASE_SIZE_IN_BYTES,
+            KdfId::Argon2 => MAX_PASSPHRASE_SIZE_IN_BYTES,
+            KdfId::Sha256 => MAX_PW_SIZE_IN_BYTES
+        }
+    }
+
     pub fn from_string(name: &String) -> Option<Self> {
         match &name[..] {
             KDF_SHA256 => Some(KdfId::Sha256),
@@ -138,18 +271,100 @@ impl KdfId {
     }
 }
 
+// Cost parameters of a key derivation function. The meaning of the values depends on the KDF:
+// Argon2 uses them as memory in KiB, number of passes and degree of parallelism. scrypt uses them
+// as log2 of N, block size r and parallelism p. The sha256 KDF has no parameters.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+pub struct KdfParams {
+    #[serde(rename(deserialize = "M"))]
+    #[serde(rename(serialize = "M"))]
+    pub m: u32,
+    #[serde(rename(deserialize = "T"))]
+    #[serde(rename(serialize = "T"))]
+    pub t: u32,
+    #[serde(rename(deserialize = "P"))]
+    #[serde(rename(serialize = "P"))]
+    pub p: u32
+}
+
+impl KdfParams {
+    pub fn new(m: u32, t: u32, p: u32) -> KdfParams {
+        return KdfParams { m: m, t: t, p: p };
+    }
+
+    // Parses parameters of the form "m,t,p"
+    pub fn from_str(s: &str) -> Option<KdfParams> {
+        let values: Vec<u32> = match s.split(',').map(|v| v.trim().parse::<u32>()).collect() {
+            Ok(v) => v,
+            Err(_) => return None
+        };
+
+        if values.len() != 3 {
+            return None;
+        }
+
+        return Some(KdfParams::new(values[0], values[1], values[2]));
+    }
+
+    pub fn to_string(&self) -> String {
+        return format!("{},{},{}", self.m, self.t, self.p);
+    }
+
+    // Checks whether the parameters can be used with the given KDF. Parameters which are weaker than the defaults
+    // or which would need an unreasonable amount of memory are rejected.
+    pub fn check(&self, id: KdfId) -> Option<Error> {
+        let defaults = id.default_params();
+
+        let is_ok = match id {
+            KdfId::Sha256 => true,
+            KdfId::Argon2 => (self.m >= defaults.m) && (self.m <= derivers::ARGON2_MAX_M_COST) && (self.t >= defaults.t) &&
+                             (self.t <= derivers::ARGON2_MAX_T_COST) && (self.p >= defaults.p) && (self.p <= derivers::MAX_PARALLELISM) &&
+                             argon2::Params::new(self.m, self.t, self.p, Some(32)).is_ok(),
+            KdfId::Scrypt => (self.m >= defaults.m) && (self.m <= derivers::SCRYPT_MAX_LOG_N) && (self.t >= defaults.t) &&
+                             (self.t <= derivers::SCRYPT_MAX_R) && (self.p >= defaults.p) && (self.p <= derivers::MAX_PARALLELISM) &&
+                             scrypt::Params::new(self.m as u8, self.t, self.p, 32).is_ok()
+        };
+
+        if !is_ok {
+            return Some(Error::new(ErrorKind::Other, format!("Unsupported parameters {} for KDF {}", self.to_string(), id.to_str())));
+        }
+
+        return None;
+    }
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 pub enum CipherId {
     Aes256Gcm,
     Aes192Gcm,
-    ChaCha20Poly1305
+    ChaCha20Poly1305,
+    XChaCha20Poly1305,
+    Aes256GcmSiv
 }
 
 impl CipherId {
+    pub fn to_string(self) -> String {
+        return String::from(self.to_str())
+    }
+
     pub fn to_str(self) -> &'static str {
         match self {
             CipherId::Aes192Gcm => CIP_AES192,
             CipherId::Aes256Gcm => CIP_AES256,
             CipherId::ChaCha20Poly1305 => CIP_CHACHA20,
+            CipherId::XChaCha20Poly1305 => CIP_XCHACHA20,
+            CipherId::Aes256GcmSiv => CIP_AES256SIV,
+        }
+    }
+
+    // Name which is shown to the user
+    pub fn algo_name(self) -> &'static str {
+        match self {
+            CipherId::Aes192Gcm => ALGO_AES192,
+            CipherId::Aes256Gcm => ALGO_AES256,
+            CipherId::ChaCha20Poly1305 => ALGO_CHACHA20,
+            CipherId::XChaCha20Poly1305 => ALGO_XCHACHA20,
+            CipherId::Aes256GcmSiv => ALGO_AES256SIV,
         }
     }
 
@@ -158,6 +373,8 @@ impl CipherId {
             CIP_AES192 => Some(CipherId::Aes192Gcm),
             CIP_AES256 => Some(CipherId::Aes256Gcm),
             CIP_CHACHA20 => Some(CipherId::ChaCha20Poly1305),
+            CIP_XCHACHA20 => Some(CipherId::XChaCha20Poly1305),
+            CIP_AES256SIV => Some(CipherId::Aes256GcmSiv),
             _ => None
         }
     }
@@ -166,41 +383,293 @@ impl CipherId {
         #[cfg(not(feature = "chacha20"))]
         return vec![CipherId::Aes256Gcm];
         #[cfg(feature = "chacha20")]
-        return vec![CipherId::Aes192Gcm, CipherId::Aes256Gcm, CipherId::ChaCha20Poly1305];
+        return vec![CipherId::Aes192Gcm, CipherId::Aes256Gcm, CipherId::ChaCha20Poly1305, CipherId::XChaCha20Poly1305, CipherId::Aes256GcmSiv];
     }        
 
+    // Size of the nonce in bytes which is stored in the header of files that are encrypted with this cipher
+    pub fn nonce_size(self) -> usize {
+        match self {
+            CipherId::XChaCha20Poly1305 => EXTENDED_NONCE_SIZE,
+            _ => DEFAULT_NONCE_SIZE
+        }
+    }
+
     pub fn make(self, d: KeyDeriver, i: KdfId) -> Box<dyn Cryptor> {
         match self {
             CipherId::Aes192Gcm => return Box::new(rijndael::Gcm192Context::new_with_kdf(d, i)),
             CipherId::Aes256Gcm => return Box::new(rijndael::Gcm256Context::new_with_kdf(d, i)),
-            CipherId::ChaCha20Poly1305 => return Box::new(chacha20::ChaCha20Poly1305Context::new_with_kdf(d, i))
+            CipherId::ChaCha20Poly1305 => return Box::new(chacha20::ChaCha20Poly1305Context::new_with_kdf(d, i)),
+            CipherId::XChaCha20Poly1305 => return Box::new(chacha20::XChaCha20Poly1305Context::new_with_kdf(d, i)),
+            CipherId::Aes256GcmSiv => return Box::new(rijndael::GcmSiv256Context::new_with_kdf(d, i))
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct CryptedJson {
+// Determines how header and ciphertext are stored. The JSON format stores the ciphertext in base64 and can be inspected
+// with any text editor. The binary format consists of BINARY_MAGIC, BINARY_ENCODING_VERSION, the length of the header as
+// a 32 bit big endian value, the header in compact JSON and the raw ciphertext.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum ContainerFormat {
+    Json,
+    Binary
+}
+
+impl ContainerFormat {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            ContainerFormat::Json => FORMAT_JSON,
+            ContainerFormat::Binary => FORMAT_BINARY
+        }
+    }
+
+    pub fn from_str(name: &str) -> Option<Self> {
+        return match name {
+            FORMAT_JSON => Some(ContainerFormat::Json),
+            FORMAT_BINARY => Some(ContainerFormat::Binary),
+            _ => None
+        }
+    }
+
+    pub fn get_known_ids() -> Vec<ContainerFormat> {
+        return vec![ContainerFormat::Json, ContainerFormat::Binary];
+    }
+}
+
+// Everything in front of the ciphertext. Beginning with version 2 the serialized header is used as associated data when
+// encrypting, i.e. no field can be changed without causing the decryption to fail.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct CryptedHeader {
+    // Files which were written before the format was versioned do not contain this field and are of version 1
+    #[serde(rename(deserialize = "Version"))]
+    #[serde(rename(serialize = "Version"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<u32>,
     #[serde(rename(deserialize = "PbKdf"))]
     #[serde(rename(serialize = "PbKdf"))]
     pbkdf: String,    
+    // Files which were written before the parameters were stored use the default parameters of their KDF
+    #[serde(rename(deserialize = "KdfParams"))]
+    #[serde(rename(serialize = "KdfParams"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kdf_params: Option<KdfParams>,
+    // Files which were written before the cipher was stored do not contain this field. In this case the cipher
+    // has to be specified by the user.
+    #[serde(rename(deserialize = "Cipher"))]
+    #[serde(rename(serialize = "Cipher"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cipher: Option<String>,
+    // Present and true if the key was derived from the password and the contents of a key file
+    #[serde(rename(deserialize = "KeyFile"))]
+    #[serde(rename(serialize = "KeyFile"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_file: Option<bool>,
+    // Present in files of version 3. The key slots are not part of the associated data of the payload. Each slot
+    // is authenticated on its own when it is unlocked.
+    #[serde(rename(deserialize = "KeySlots"))]
+    #[serde(rename(serialize = "KeySlots"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_slots: Option<Vec<KeySlot>>,
+    // Present in files of version 4. Like key slots the recipients are not part of the associated data of the payload.
+    #[serde(rename(deserialize = "Recipients"))]
+    #[serde(rename(serialize = "Recipients"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recipients: Option<Vec<Recipient>>,
     #[serde(rename(deserialize = "Salt"))]
     #[serde(rename(serialize = "Salt"))]
     salt: String,
     #[serde(rename(deserialize = "Nonce"))]
     #[serde(rename(serialize = "Nonce"))]
-    nonce: String,
+    nonce: String
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CryptedJson {
+    #[serde(flatten)]
+    header: CryptedHeader,
     #[serde(rename(deserialize = "Data"))]
     #[serde(rename(serialize = "Data"))]
     data: String
 }
 
-pub type KeyDeriver = fn(&Vec<u8>, &str) -> Vec<u8>;
+pub type KeyDeriver = fn(&Vec<u8>, &str, &KdfParams) -> SecretKey;
+
+// Splits an encrypted file into its header and the raw ciphertext. The format of the file is detected automatically.
+fn read_container(raw: &[u8]) -> std::io::Result<(CryptedHeader, Vec<u8>, ContainerFormat)> {
+    if !raw.starts_with(BINARY_MAGIC) {
+        let json_struct: CryptedJson = serde_json::from_slice(raw)?;
+
+        let data = match BASE64_STANDARD.decode(&json_struct.data) {
+            Ok(s) => s,
+            Err(_) => return Err(Error::new(ErrorKind::Other, "Base64 decode error"))
+        };
+
+        return Ok((json_struct.header, data, ContainerFormat::Json));
+    }
+
+    let prefix_len = BINARY_MAGIC.len() + 5;
+
+    if raw.len() < prefix_len {
+        return Err(Error::new(ErrorKind::Other, "Binary container too short"));
+    }
+
+    let encoding_version = raw[BINARY_MAGIC.len()];
+
+    if encoding_version != BINARY_ENCODING_VERSION {
+        return Err(Error::new(ErrorKind::Other, format!("Unsupported binary encoding version {}", encoding_version)));
+    }
+
+    let mut len_bytes: [u8; 4] = [0; 4];
+    len_bytes.copy_from_slice(&raw[BINARY_MAGIC.len() + 1..prefix_len]);
+    let header_len = u32::from_be_bytes(len_bytes) as usize;
+
+    if raw.len() - prefix_len < header_len {
+        return Err(Error::new(ErrorKind::Other, "Binary container too short"));
+    }
+
+    let header: CryptedHeader = serde_json::from_slice(&raw[prefix_len..prefix_len + header_len])?;
+
+    return Ok((header, raw[prefix_len + header_len..].to_vec(), ContainerFormat::Binary));
+}
+
+fn write_container<T: Write>(mut writer: T, header: CryptedHeader, data: &[u8], format: ContainerFormat) -> std::io::Result<()> {
+    match format {
+        ContainerFormat::Json => {
+            let j = CryptedJson {
+                header: header,
+                data: BASE64_STANDARD.encode(data)
+            };
+
+            serde_json::to_writer_pretty(writer, &j)?;
+        },
+        ContainerFormat::Binary => {
+            let header_bytes = serde_json::to_vec(&header)?;
+
+            writer.write_all(BINARY_MAGIC)?;
+            writer.write_all(&[BINARY_ENCODING_VERSION])?;
+            writer.write_all(&(header_bytes.len() as u32).to_be_bytes())?;
+            writer.write_all(&header_bytes)?;
+            writer.write_all(data)?;
+        }
+    }
+
+    return Ok(());
+}
+
+// Returns the cipher which is named in the header of an encrypted file. None is returned for files which were written
+// before the cipher was stored in the header or which can not be parsed at all.
+pub fn cipher_from_header(raw: &Vec<u8>) -> Option<CipherId> {
+    let header = match read_container(raw.as_slice()) {
+        Ok((h, _, _)) => h,
+        Err(_) => return None
+    };
+
+    return match header.cipher {
+        Some(c) => CipherId::from_str(&c),
+        None => None
+    };
+}
+
+// Everything which can be learned about an encrypted file without opening it
+#[derive(Debug, Clone)]
+pub struct ContainerInfo {
+    pub version: u32,
+    pub format: ContainerFormat,
+    // None for files which were written before the cipher was stored in the header
+    pub cipher_id: Option<CipherId>,
+    pub kdf_id: KdfId,
+    pub kdf_params: KdfParams,
+    pub key_file_required: bool,
+    pub key_slots: Vec<String>,
+    pub recipients: Vec<String>
+}
+
+// Reads the header of an encrypted file and performs the same checks as AeadContext::from_reader. No password is needed.
+pub fn container_info(raw: &Vec<u8>) -> std::io::Result<ContainerInfo> {
+    let (header, data, format) = read_container(raw.as_slice())?;
+
+    let cipher_id = match &header.cipher {
+        Some(c) => match CipherId::from_str(c) {
+            Some(id) => Some(id),
+            None => return Err(Error::new(ErrorKind::Other, format!("Unknown cipher {}", c)))
+        },
+        None => None
+    };
+
+    let key_slots: Vec<String> = header.key_slots.iter().flatten().map(|s| s.header.label.clone()).collect();
+    let recipients: Vec<String> = header.recipients.iter().flatten().map(|r| r.header.label.clone()).collect();
+
+    // All ciphers which were available before the cipher was stored in the header use 96 bit nonces
+    let cipher = cipher_id.unwrap_or(CipherId::Aes256Gcm);
+    let mut ctx = AeadContext::new_for_cipher(derivers::sha256_deriver, KdfId::Sha256, cipher);
+    ctx.parse_header(header, cipher, format)?;
+    ctx.check_min_size(data.len())?;
+
+    return Ok(ContainerInfo {
+        version: ctx.version,
+        format: format,
+        cipher_id: cipher_id,
+        kdf_id: ctx.kdf_id,
+        kdf_params: ctx.kdf_params,
+        key_file_required: ctx.key_file_required,
+        key_slots: key_slots,
+        recipients: recipients
+    });
+}
+
+// Returns the labels of the key slots of an encrypted file. The list is empty for files without key slots.
+pub fn key_slot_labels(raw: &Vec<u8>) -> std::io::Result<Vec<String>> {
+    let (header, _, _) = read_container(raw.as_slice())?;
+
+    return match header.key_slots {
+        Some(slots) => Ok(slots.iter().map(|s| s.header.label.clone()).collect()),
+        None => Ok(Vec::new())
+    };
+}
+
+// Replaces the key slots in the header of an encrypted file which already uses key slots. The encrypted payload
+// and the format of the file are not changed.
+pub fn replace_key_slots(raw: &Vec<u8>, key_slots: &KeySlots) -> std::io::Result<Vec<u8>> {
+    let (mut header, data, format) = read_container(raw.as_slice())?;
+
+    if header.key_slots.is_none() {
+        return Err(Error::new(ErrorKind::Other, "File does not use key slots"));
+    }
+
+    header.key_slots = Some(key_slots.slots.clone());
+
+    let mut res: Vec<u8> = Vec::new();
+    write_container(&mut res, header, &data, format)?;
+
+    return Ok(res);
+}
+
+// Appends the hash of a key file in hex form to the password
+fn password_with_key_file(password: &str, key_file: &SecretBytes) -> SecretString {
+    let mut combined = SecretString::new(String::from(password));
+    key_file.iter().for_each(|b| combined.push_str(&format!("{:02x}", b)));
+
+    return combined;
+}
 
 pub struct AeadContext {
+    pub cipher_id: CipherId,
     pub salt: Vec<u8>,
     pub nonce: Vec<u8>,
+    // Size of the nonces which are generated by fill_random. It depends on the cipher.
+    pub nonce_size: usize,
     pub kdf: KeyDeriver,
-    pub kdf_id: KdfId
+    pub kdf_id: KdfId,
+    pub kdf_params: KdfParams,
+    // Version of the format which was read. Encrypting always upgrades to the current version.
+    pub version: u32,
+    // Hash of the key file. It is used for encryption if present and for decryption if the header requires it.
+    pub key_file: Option<SecretBytes>,
+    pub key_file_required: bool,
+    // If present the payload is encrypted with the data key from the key slots instead of a key derived from the password
+    pub key_slots: Option<KeySlots>,
+    // Data key which was unwrapped for a recipient. It takes precedence over key slots and the password.
+    pub data_key: Option<SecretBytes>,
+    pub format: ContainerFormat
 } 
 
 // This struct knows how to generarate, maintain, parse, serialze and deserialize a data structure which can be used to
@@ -216,11 +685,25 @@ impl AeadContext {
     }
 
     pub fn new_with_kdf(derive: KeyDeriver, deriver_id: KdfId) -> AeadContext {
+        return AeadContext::new_for_cipher(derive, deriver_id, CipherId::Aes256Gcm);
+    }
+
+    pub fn new_for_cipher(derive: KeyDeriver, deriver_id: KdfId, cipher_id: CipherId) -> AeadContext {
+        let nonce_size = cipher_id.nonce_size();
         let mut res = AeadContext {
+            cipher_id: cipher_id,
             salt: vec![0; DEFAULT_SALT_SIZE],
-            nonce: vec![0; DEFAULT_NONCE_SIZE],
+            nonce: vec![0; nonce_size],
+            nonce_size: nonce_size,
             kdf: derive,
-            kdf_id: deriver_id
+            kdf_id: deriver_id,
+            kdf_params: deriver_id.default_params(),
+            version: CONTAINER_VERSION,
+            key_file: None,
+            key_file_required: false,
+            key_slots: None,
+            data_key: None,
+            format: ContainerFormat::Json
         };
 
         res.fill_random();
@@ -228,59 +711,125 @@ impl AeadContext {
         return res;        
     }
 
-    pub fn from_reader<T: Read>(&mut self, reader: T) -> std::io::Result<Vec<u8>> {
-        let json_struct: CryptedJson = serde_json::from_reader(reader)?;
+    // The KDF is taken from the header. If the header names a cipher it has to be the given one.
+    pub fn from_reader<T: Read>(&mut self, mut reader: T, cipher: CipherId) -> std::io::Result<Vec<u8>> {
+        let mut raw: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut raw)?;
+        let (header, data, format) = read_container(raw.as_slice())?;
 
-        if json_struct.pbkdf != self.kdf_id.to_string() {
-            return Err(Error::new(ErrorKind::Other, format!("Key derivation function mismatch. {} was used not {}", &json_struct.pbkdf, &self.kdf_id.to_string())));
+        self.parse_header(header, cipher, format)?;
+
+        if (self.version == RECIPIENTS_VERSION) && self.data_key.is_none() {
+            return Err(Error::new(ErrorKind::Other, "A private key is required to open this file"));
+        }
+
+        if self.key_file_required && self.key_file.is_none() {
+            return Err(Error::new(ErrorKind::Other, "A key file is required to open this file"));
         }
 
-        let salt = match BASE64_STANDARD.decode(&json_struct.salt) {
+        self.check_min_size(data.len())?;
+    
+        return Ok(data);
+    }
+
+    // Checks all fields of the header and takes over their values. Whether the file can be opened with the private key
+    // or key file which is available is not checked here.
+    fn parse_header(&mut self, header: CryptedHeader, cipher: CipherId, format: ContainerFormat) -> std::io::Result<()> {
+        let version = header.version.unwrap_or(1);
+
+        if (version < 1) || (version > RECIPIENTS_VERSION) {
+            return Err(Error::new(ErrorKind::Other, format!("Unsupported container version {}", version)));
+        }
+
+        let kdf_id = match KdfId::from_string(&header.pbkdf) {
+            Some(k) => k,
+            None => return Err(Error::new(ErrorKind::Other, format!("Unknown key derivation function {}", &header.pbkdf)))
+        };
+
+        if let Some(c) = &header.cipher {
+            if *c != cipher.to_str() {
+                return Err(Error::new(ErrorKind::Other, format!("Cipher mismatch. {} was used not {}", c, cipher.to_str())));
+            }
+        }
+
+        let kdf_params = match header.kdf_params {
+            Some(p) => p,
+            None => kdf_id.default_params()
+        };
+
+        if let Some(e) = kdf_params.check(kdf_id) {
+            return Err(e);
+        }
+
+        let key_file_required = header.key_file.unwrap_or(false);
+
+        let salt = match BASE64_STANDARD.decode(&header.salt) {
             Ok(s) => s,
             Err(_) => {
                 return Err(Error::new(ErrorKind::Other, "Base64 decode error"));
             }
         };
 
-        let nonce = match BASE64_STANDARD.decode(&json_struct.nonce) {
+        let nonce = match BASE64_STANDARD.decode(&header.nonce) {
             Ok(s) => s,
             Err(_) => {
                 return Err(Error::new(ErrorKind::Other, "Base64 decode error"));
             }
         };
 
-        if nonce.len() != DEFAULT_NONCE_SIZE {
+        if nonce.len() != cipher.nonce_size() {
             return Err(Error::new(ErrorKind::Other, "Unsupported nonce size"));
         }       
 
         self.salt = salt;
         self.nonce = nonce;
-
-        let data = match BASE64_STANDARD.decode(&json_struct.data) {
-            Ok(s) => s,
-            Err(_) => {
-                return Err(Error::new(ErrorKind::Other, "Base64 decode error"));
-            }
+        (self.kdf, self.kdf_id) = kdf_id.to_named_func();
+        self.kdf_params = kdf_params;
+        self.version = version;
+        self.key_file_required = key_file_required;
+        self.key_slots = match header.key_slots {
+            Some(slots) if version >= KEY_SLOTS_VERSION => Some(KeySlots::from_slots(slots, key_file_required)),
+            Some(_) => return Err(Error::new(ErrorKind::Other, "Key slots are not supported in this container version")),
+            None => None
         };
+        self.format = format;
 
-        if data.len() < DEFAULT_TAG_SIZE {
-            return Err(Error::new(ErrorKind::Other, "Ciphertext too short"));
-        }
-    
-        return Ok(data);
+        return Ok(());
     }
 
-    pub fn to_writer<T: Write>(&self, writer: T, data: &Vec<u8>) -> std::io::Result<()> {
-        let j = CryptedJson {
+    fn make_header(&self, cipher: CipherId) -> CryptedHeader {
+        let params = match self.kdf_id {
+            KdfId::Sha256 => None,
+            _ => Some(self.kdf_params)
+        };
+
+        return CryptedHeader {
+            version: Some(self.version),
             pbkdf: self.kdf_id.to_string(),
+            kdf_params: params,
+            cipher: Some(cipher.to_string()),
+            key_file: if self.key_file_required { Some(true) } else { None },
+            key_slots: self.key_slots.as_ref().map(|k| k.slots.clone()),
+            recipients: None,
             salt: BASE64_STANDARD.encode(&self.salt),
-            nonce: BASE64_STANDARD.encode(&self.nonce),
-            data: BASE64_STANDARD.encode(data)
+            nonce: BASE64_STANDARD.encode(&self.nonce)
         };
+    }
+
+    // Version 1 files were encrypted without associated data
+    pub fn associated_data(&self, cipher: CipherId) -> std::io::Result<Vec<u8>> {
+        if self.version < 2 {
+            return Ok(Vec::new());
+        }
 
-        serde_json::to_writer_pretty(writer, &j)?;
+        let mut header = self.make_header(cipher);
+        header.key_slots = None;
 
-        return Ok(());
+        return Ok(serde_json::to_vec(&header)?);
+    }
+
+    pub fn to_writer<T: Write>(&self, writer: T, data: &Vec<u8>, cipher: CipherId) -> std::io::Result<()> {
+        return write_container(writer, self.make_header(cipher), data, self.format);
     }
 
     pub fn fill_random(&mut self) {
@@ -290,17 +839,31 @@ impl AeadContext {
         let mut rng = rand::thread_rng();
         
         // ToDo: Error handling with fill_bytes()?
-        let mut temp_nonce: [u8; DEFAULT_NONCE_SIZE] = [0; DEFAULT_NONCE_SIZE];
+        let mut temp_nonce = vec![0; self.nonce_size];
         rng.fill_bytes(&mut temp_nonce);
         let mut temp_salt: [u8; DEFAULT_SALT_SIZE] = [0; DEFAULT_SALT_SIZE];
         rng.fill_bytes(&mut temp_salt);
 
-        self.nonce = temp_nonce.to_vec();
+        self.nonce = temp_nonce;
         self.salt = temp_salt.to_vec();
     }
 
-    pub fn regenerate_key(&self, password: &str) -> Vec<u8> {
-        return (self.kdf)(&self.salt, password);
+    // If a key file is used its hash is appended to the password in hex form before the key is derived. Files with
+    // key slots use the unlocked data key instead.
+    pub fn regenerate_key(&self, password: &str) -> SecretKey {
+        if let Some(k) = &self.data_key {
+            return SecretKey::new(k.to_vec());
+        }
+
+        if let Some(k) = self.key_slots.as_ref().and_then(|s| s.key()) {
+            return k;
+        }
+
+        if let (true, Some(h)) = (self.key_file_required, &self.key_file) {
+            return (self.kdf)(&self.salt, &password_with_key_file(password, h), &self.kdf_params);
+        }
+
+        return (self.kdf)(&self.salt, password, &self.kdf_params);
     }
 
     pub fn check_min_size(&self, len: usize) -> std::io::Result<()> {
@@ -311,19 +874,61 @@ impl AeadContext {
         }       
     }
 
-    pub fn prepare_params_encrypt(&mut self, password: &str) -> (Vec<u8>, Vec<u8>) {
+    // Searches the key slot which can be opened with the password. The KDF of that slot becomes the KDF of this context.
+    pub fn unlock_key_slots(&mut self, password: &str) -> std::io::Result<()> {
+        if let Some(slots) = &mut self.key_slots {
+            let (kdf_id, params) = slots.unlock(password, &self.key_file)?;
+            (self.kdf, self.kdf_id) = kdf_id.to_named_func();
+            self.kdf_params = params;
+        }
+
+        return Ok(());
+    }
+
+    // Wraps the data key again for the slot which was unlocked using the current password, KDF and KDF parameters
+    pub fn update_key_slot(&mut self, password: &str, cipher: CipherId) -> std::io::Result<()> {
+        if let Some(slots) = &mut self.key_slots {
+            if let Some(label) = slots.unlocked.clone() {
+                slots.wrap(&label, password, &self.key_file, cipher, self.kdf_id, self.kdf_params)?;
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn prepare_params_encrypt(&mut self, password: &str) -> (SecretKey, Vec<u8>) {
         self.fill_random();
 
+        // Files for recipients are opened with a private key. A key file is not used in this case.
+        if self.data_key.is_some() {
+            self.version = RECIPIENTS_VERSION;
+            self.key_file_required = false;
+            return (self.regenerate_key(password), self.nonce.clone());
+        }
+
+        // All slots were created with or without the key file. Therefore this can not be changed for files with key slots.
+        match &self.key_slots {
+            Some(s) => {
+                self.version = KEY_SLOTS_VERSION;
+                self.key_file_required = s.key_file_required;
+            },
+            None => {
+                self.version = CONTAINER_VERSION;
+                self.key_file_required = self.key_file.is_some();
+            }
+        }
+
         let raw_32_byte_key = self.regenerate_key(password);
 
         return (raw_32_byte_key, self.nonce.clone())
     }
 
-    pub fn prepare_params_decrypt(&mut self, password: &str, data: &Vec<u8>) -> (Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>) {
+    pub fn prepare_params_decrypt(&mut self, password: &str, data: &Vec<u8>) -> (SecretKey, Vec<u8>, Vec<u8>, SecretBytes) {
         let raw_32_byte_key = self.regenerate_key(password);
 
         let data_len = data.len() - DEFAULT_TAG_SIZE;
-        let mut dec_buffer = Vec::new();
+        // The buffer is decrypted in place and therefore contains the plaintext afterwards
+        let mut dec_buffer = SecretBytes::new(Vec::with_capacity(data_len));
         for i in 0..data_len {
             dec_buffer.push(data[i]);
         }
@@ -337,22 +942,40 @@ impl AeadContext {
 
 // The following two functions provide a generic implementation of AEAD en- and decryption on the basis of an AeadContext struct for all ciphers which 
 // implement the corresponding RustCrypto traits. They are therefore helper functions in order to implement the Cryptor trait in this case.
-fn encrypt_aead<T: Aead + AeadInPlace + AeadCore<NonceSize = U12, TagSize = U16> + KeyInit>(ctx: &mut AeadContext, password: &str, data: &Vec<u8>, algo_name: &str) -> std::io::Result<Vec<u8>> {
+// The nonce size of the cipher has to match the nonce size of the AeadContext.
+fn check_nonce_size<T: AeadCore>(nonce: &Vec<u8>) -> std::io::Result<()> {
+    if nonce.len() != <T as AeadCore>::NonceSize::USIZE {
+        return Err(Error::new(ErrorKind::Other, "Unsupported nonce size"));
+    }
+
+    return Ok(());
+}
+
+fn encrypt_aead<T: Aead + AeadInPlace + AeadCore<TagSize = U16> + KeyInit>(ctx: &mut AeadContext, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
+    let cipher_id = ctx.cipher_id;
+    ctx.update_key_slot(password, cipher_id)?;
     let (key, nonce) = ctx.prepare_params_encrypt(password);
+    check_nonce_size::<T>(&nonce)?;
+    let associated_data = ctx.associated_data(cipher_id)?;
     let nonce_help = GenericArray::<u8, <T as AeadCore>::NonceSize>::from_slice(nonce.as_slice());
     let key_help = GenericArray::<u8, <T as KeySizeUser>::KeySize>::from_slice(&key[0..T::key_size()]);
 
     let cipher = T::new(&key_help);
 
-    return match cipher.encrypt(nonce_help, data.as_slice()) {
-        Err(_) => return Err(Error::new(ErrorKind::Other, format!("{} {}", algo_name, "Encryption error"))),
+    let payload = Payload { msg: data.as_slice(), aad: associated_data.as_slice() };
+
+    return match cipher.encrypt(nonce_help, payload) {
+        Err(_) => return Err(Error::new(ErrorKind::Other, format!("{} {}", cipher_id.algo_name(), "Encryption error"))),
         Ok(d) => Ok(d)
     };
 }
 
-fn decrypt_aead<T: Aead + AeadInPlace + AeadCore<NonceSize = U12, TagSize = U16> + KeyInit>(ctx: &mut AeadContext, password: &str, data: &Vec<u8>, algo_name: &str) -> std::io::Result<Vec<u8>> {
+fn decrypt_aead<T: Aead + AeadInPlace + AeadCore<TagSize = U16> + KeyInit>(ctx: &mut AeadContext, password: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes> {
+    let cipher_id = ctx.cipher_id;
     ctx.check_min_size(data.len())?;
-    let associated_data: [u8; 0] = [];
+    check_nonce_size::<T>(&ctx.nonce)?;
+    let associated_data = ctx.associated_data(cipher_id)?;
+    ctx.unlock_key_slots(password)?;
 
     let (key, nonce, tag, mut dec_buffer) = ctx.prepare_params_decrypt(password, data);
 
@@ -361,18 +984,59 @@ fn decrypt_aead<T: Aead + AeadInPlace + AeadCore<NonceSize = U12, TagSize = U16>
     let tag_help = GenericArray::<u8, <T as AeadCore>::TagSize>::from_slice(tag.as_slice());
 
     let cipher = T::new(&key_help);
-    let _ = match cipher.decrypt_in_place_detached(nonce_help, &associated_data, dec_buffer.as_mut_slice(), tag_help) {
+    let _ = match cipher.decrypt_in_place_detached(nonce_help, associated_data.as_slice(), dec_buffer.as_mut_slice(), tag_help) {
         Ok(_) => (),
         Err(_) => {
-            return Err(Error::new(ErrorKind::Other, format!("{} {}", algo_name, "Decryption error")));
+            return Err(Error::new(ErrorKind::Other, format!("{} {}", cipher_id.algo_name(), "Decryption error")));
         }
     };
 
     return Ok(dec_buffer);  
 }
 
-pub fn check_password(pw: &str) -> Option<Error> {
-    if pw.as_bytes().len() > MAX_PW_SIZE_IN_BYTES {
+// Returns the SHA-256 hash of the contents of a key file. Any non empty file can be used as a key file.
+pub fn key_file_hash(file_name: &str) -> std::io::Result<SecretBytes> {
+    let contents = SecretBytes::new(std::fs::read(file_name)?);
+
+    if contents.len() == 0 {
+        return Err(Error::new(ErrorKind::Other, "Key file is empty"));
+    }
+
+    let mut sha_256: Sha256 = Sha256::new();
+    sha_256.update(contents.as_slice());
+    let mut hash_res = sha_256.finalize();
+    let res = SecretBytes::new(hash_res.to_vec());
+    hash_res.as_mut_slice().zeroize();
+
+    return Ok(res);
+}
+
+// Creates a new key file with random contents. An existing file is never overwritten.
+pub fn create_key_file(file_name: &str) -> std::io::Result<()> {
+    let mut key_data = SecretBytes::new(vec![0; KEY_FILE_SIZE]);
+    rand::thread_rng().fill_bytes(key_data.as_mut_slice());
+
+    let mut file = std::fs::OpenOptions::new().write(true).create_new(true).open(file_name)?;
+    file.write_all(key_data.as_slice())?;
+
+    return Ok(());
+}
+
+// Checks a new password which is going to be used with the given KDF
+pub fn check_password(pw: &str, kdf_id: KdfId) -> Option<Error> {
+    if pw.as_bytes().len() > kdf_id.max_password_len() {
+        return Some(Error::new(ErrorKind::Other, format!("Password too long. {} allows at most {} bytes", kdf_id.to_str(), kdf_id.max_password_len())));
+    }
+
+    return None;
+}
+
+// The KDF of a file is not known before the file has been read. The password which is used to open a file is therefore
+// checked against the largest limit of all KDFs.
+pub fn check_existing_password(pw: &str) -> Option<Error> {
+    let max_len = KdfId::get_known_ids().iter().map(|k| k.max_password_len()).max().unwrap_or(MAX_PW_SIZE_IN_BYTES);
+
+    if pw.as_bytes().len() > max_len {
         return Some(Error::new(ErrorKind::Other, "Password too long"));
     }
 
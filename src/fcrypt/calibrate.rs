@@ -0,0 +1,173 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::time::{Duration, Instant};
+use super::{KdfId, KdfParams, DEFAULT_SALT_SIZE};
+use super::derivers;
+
+// Argon2 memory is only recommended in steps of 1 MiB
+const ARGON2_M_COST_STEP: u32 = 1024;
+
+// Returns the time one key derivation takes with the given parameters
+pub type Measure = fn(KdfId, &KdfParams) -> Duration;
+
+// Result of timing a KDF on the current machine
+pub struct Calibration {
+    pub kdf_id: KdfId,
+    measure: Measure,
+    // All parameters which were tried together with the time one key derivation took
+    pub measurements: Vec<(KdfParams, Duration)>,
+    // The strongest parameters which did not exceed the target time. If even the default parameters are too slow
+    // they are recommended nonetheless, because weaker parameters are not accepted.
+    pub recommended: KdfParams
+}
+
+impl Calibration {
+    fn new(kdf_id: KdfId, measure: Measure) -> Calibration {
+        return Calibration {
+            kdf_id: kdf_id,
+            measure: measure,
+            measurements: Vec::new(),
+            recommended: kdf_id.default_params()
+        };
+    }
+
+    // Returns the time which was measured for the recommended parameters
+    pub fn recommended_duration(&self) -> Option<Duration> {
+        return self.measurements.iter().rev().find(|m| m.0 == self.recommended).map(|m| m.1);
+    }
+
+    // Measures the given parameters and returns true if they do not exceed the target time
+    fn try_params(&mut self, params: KdfParams, target: Duration) -> bool {
+        let d = (self.measure)(self.kdf_id, &params);
+        self.measurements.push((params, d));
+
+        if d > target {
+            return false;
+        }
+
+        self.recommended = params;
+        return true;
+    }
+
+    fn last_duration(&self) -> Duration {
+        return self.measurements.last().map(|m| m.1).unwrap_or(Duration::ZERO);
+    }
+}
+
+// Time needed for one key derivation with the given parameters. The parameters have to be checked with KdfParams::check.
+pub fn measure(kdf_id: KdfId, params: &KdfParams) -> Duration {
+    let (derive, _) = kdf_id.to_named_func();
+    let salt = vec![0; DEFAULT_SALT_SIZE];
+
+    let start = Instant::now();
+    let _key = derive(&salt, "a password which is only used for timing", params);
+
+    return start.elapsed();
+}
+
+// Determines the strongest parameters for which one key derivation does not take longer than target and which
+// do not use more than max_mem_kib KiB of memory. Starting at the defaults the memory is raised first, because this
+// is what makes attacks with special hardware expensive. When the memory limit is reached the time cost is raised.
+pub fn calibrate(kdf_id: KdfId, target: Duration, max_mem_kib: u32) -> Calibration {
+    return calibrate_with(kdf_id, target, max_mem_kib, measure);
+}
+
+pub fn calibrate_with(kdf_id: KdfId, target: Duration, max_mem_kib: u32, measure: Measure) -> Calibration {
+    let mut c = Calibration::new(kdf_id, measure);
+    let defaults = kdf_id.default_params();
+
+    if !c.try_params(defaults, target) {
+        return c;
+    }
+
+    match kdf_id {
+        KdfId::Argon2 => calibrate_argon2(&mut c, target, max_mem_kib),
+        KdfId::Scrypt => calibrate_scrypt(&mut c, target, max_mem_kib),
+        // There is nothing to tune
+        KdfId::Sha256 => ()
+    }
+
+    return c;
+}
+
+// The time an Argon2 derivation takes grows linearly with the memory and the number of passes
+fn calibrate_argon2(c: &mut Calibration, target: Duration, max_mem_kib: u32) {
+    let max_m = max_mem_kib.min(derivers::ARGON2_MAX_M_COST);
+    let mut params = c.recommended;
+
+    while params.m < max_m {
+        let d_low = c.last_duration();
+        let next = KdfParams::new((params.m * 2).min(max_m), params.t, params.p);
+
+        if !c.try_params(next, target) {
+            let m = interpolate(params.m, next.m, d_low, c.last_duration(), target) / ARGON2_M_COST_STEP * ARGON2_M_COST_STEP;
+
+            if m > params.m {
+                c.try_params(KdfParams::new(m, params.t, params.p), target);
+            }
+
+            return;
+        }
+
+        params = next;
+    }
+
+    while params.t < derivers::ARGON2_MAX_T_COST {
+        let next = KdfParams::new(params.m, params.t + 1, params.p);
+
+        if !c.try_params(next, target) {
+            return;
+        }
+
+        params = next;
+    }
+}
+
+// scrypt only allows powers of two for N. The remaining gap to the target is closed by raising p, as the
+// scrypt crate computes the p lanes one after the other without using more memory.
+fn calibrate_scrypt(c: &mut Calibration, target: Duration, max_mem_kib: u32) {
+    let mut params = c.recommended;
+
+    // scrypt needs 128 * r * N bytes
+    while (params.m < derivers::SCRYPT_MAX_LOG_N) && ((params.t as u64) << (params.m + 1)) / 8 <= max_mem_kib as u64 {
+        let next = KdfParams::new(params.m + 1, params.t, params.p);
+
+        if !c.try_params(next, target) {
+            break;
+        }
+
+        params = next;
+    }
+
+    let d = c.recommended_duration().unwrap_or(target);
+    let p = interpolate(0, params.p, Duration::ZERO, d, target).min(derivers::MAX_PARALLELISM);
+
+    if p > params.p {
+        c.try_params(KdfParams::new(params.m, params.t, p), target);
+    }
+}
+
+// Linear interpolation of the cost value which needs the time target, if low needs d_low and high needs d_high
+fn interpolate(low: u32, high: u32, d_low: Duration, d_high: Duration, target: Duration) -> u32 {
+    if d_high <= d_low {
+        return low;
+    }
+
+    let fraction = (target.as_secs_f64() - d_low.as_secs_f64()) / (d_high.as_secs_f64() - d_low.as_secs_f64());
+    let value = (low as f64) + fraction * ((high - low) as f64);
+
+    return value.max(0.0).min(u32::MAX as f64) as u32;
+}
@@ -13,46 +13,71 @@ See the License for the specific language governing permissions and
 limitations under the License. */
 
 
-use std::io::Read;
-use std::io::Write;
-use crate::fcrypt::{Cryptor, AeadContext, KdfId, KeyDeriver};
+use crate::fcrypt::{Cryptor, AeadContext, CipherId, KdfId, KeyDeriver};
 use super::derivers;
-use chacha20poly1305::ChaCha20Poly1305;
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
 use crate::fcrypt::{decrypt_aead, encrypt_aead};
-
-const ALGO_CHACHA20: &str = "ChaCha20 Poly-1305";
+use crate::secret::SecretBytes;
 
 pub struct ChaCha20Poly1305Context(AeadContext);
 
 impl ChaCha20Poly1305Context {
     #![allow(dead_code)]
     pub fn new() -> ChaCha20Poly1305Context {
-        return ChaCha20Poly1305Context(AeadContext::new_with_kdf(derivers::sha256_deriver, KdfId::Sha256))
+        return ChaCha20Poly1305Context(AeadContext::new_for_cipher(derivers::sha256_deriver, KdfId::Sha256, CipherId::ChaCha20Poly1305))
     }
 
     pub fn new_with_kdf(derive: KeyDeriver, deriver_id: KdfId) -> ChaCha20Poly1305Context {
-        return ChaCha20Poly1305Context(AeadContext::new_with_kdf(derive, deriver_id));
-    }    
+        return ChaCha20Poly1305Context(AeadContext::new_for_cipher(derive, deriver_id, CipherId::ChaCha20Poly1305));
+    }
 }
 
 impl Cryptor for ChaCha20Poly1305Context {
-    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
-        return decrypt_aead::<ChaCha20Poly1305>(&mut self.0, password, data, ALGO_CHACHA20);        
+    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes> {
+        return decrypt_aead::<ChaCha20Poly1305>(&mut self.0, password, data);
     }
 
     fn encrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
-        return encrypt_aead::<ChaCha20Poly1305>(&mut self.0, password, data, ALGO_CHACHA20);
+        return encrypt_aead::<ChaCha20Poly1305>(&mut self.0, password, data);
+    }
+
+    fn ctx(&self) -> &AeadContext {
+        return &self.0;
+    }
+
+    fn ctx_mut(&mut self) -> &mut AeadContext {
+        return &mut self.0;
+    }
+}
+
+// Same as ChaCha20 Poly-1305 but with 192 bit nonces
+pub struct XChaCha20Poly1305Context(AeadContext);
+
+impl XChaCha20Poly1305Context {
+    #![allow(dead_code)]
+    pub fn new() -> XChaCha20Poly1305Context {
+        return XChaCha20Poly1305Context(AeadContext::new_for_cipher(derivers::sha256_deriver, KdfId::Sha256, CipherId::XChaCha20Poly1305))
     }
 
-    fn from_dyn_reader(&mut self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
-        return self.0.from_reader(reader);
+    pub fn new_with_kdf(derive: KeyDeriver, deriver_id: KdfId) -> XChaCha20Poly1305Context {
+        return XChaCha20Poly1305Context(AeadContext::new_for_cipher(derive, deriver_id, CipherId::XChaCha20Poly1305));
     }
+}
 
-    fn to_dyn_writer(&self, writer: &mut dyn Write, data: &Vec<u8>) -> std::io::Result<()> {
-        return self.0.to_writer(writer, data);
+impl Cryptor for XChaCha20Poly1305Context {
+    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes> {
+        return decrypt_aead::<XChaCha20Poly1305>(&mut self.0, password, data);
     }
 
-    fn algo_name(&self) -> &'static str {
-        return ALGO_CHACHA20;
+    fn encrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
+        return encrypt_aead::<XChaCha20Poly1305>(&mut self.0, password, data);
     }
-}
\ No newline at end of file
+
+    fn ctx(&self) -> &AeadContext {
+        return &self.0;
+    }
+
+    fn ctx_mut(&mut self) -> &mut AeadContext {
+        return &mut self.0;
+    }
+}
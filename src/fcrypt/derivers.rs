@@ -16,41 +16,59 @@ limitations under the License. */
 use sha2::{Sha256, Digest};
 use scrypt::scrypt;
 use argon2;
+use crate::fcrypt::KdfParams;
+use crate::secret::SecretKey;
+use zeroize::Zeroize;
 
+// 15 MiB, t=2, p=1
+pub const ARGON2_DEFAULT_M_COST: u32 = 15 * 1024;
+pub const ARGON2_DEFAULT_T_COST: u32 = 2;
+pub const ARGON2_DEFAULT_P_COST: u32 = 1;
+// 4 GiB
+pub const ARGON2_MAX_M_COST: u32 = 4 * 1024 * 1024;
+pub const ARGON2_MAX_T_COST: u32 = 64;
+// N = 32768 = 2^15, r=8, p=2
+pub const SCRYPT_DEFAULT_LOG_N: u32 = 15;
+pub const SCRYPT_DEFAULT_R: u32 = 8;
+pub const SCRYPT_DEFAULT_P: u32 = 2;
+pub const SCRYPT_MAX_LOG_N: u32 = 24;
+pub const SCRYPT_MAX_R: u32 = 32;
+pub const MAX_PARALLELISM: u32 = 16;
 
-pub fn argon2id_deriver(salt: &Vec<u8>, password: &str) -> Vec<u8> {
+// The parameters have to be checked with KdfParams::check before calling a deriver
+pub fn argon2id_deriver(salt: &Vec<u8>, password: &str, p: &KdfParams) -> SecretKey {
     let mut aes_key: [u8; 32] = [0; 32];
     //let no_ad: [u8; 0] = [];
 
-    let params = argon2::Params::new(15*1024, 2, 1, Some(32)).unwrap();
-    // 15 MiB, t=2, p=1
+    let params = argon2::Params::new(p.m, p.t, p.p, Some(32)).unwrap();
     let ctx = argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
     ctx.hash_password_into(password.as_bytes(), &salt, &mut aes_key).unwrap();
-    let mut res:Vec<u8> = Vec::new();
-    aes_key.iter().for_each(|i| { res.push(*i) });
+    let res = SecretKey::new(aes_key.to_vec());
+    aes_key.zeroize();
 
     return res;        
 }
 
-pub fn scrypt_deriver(salt: &Vec<u8>, password: &str) -> Vec<u8> {
-    // N = 32768 = 2^15, r=8, p=2
-    let parms = scrypt::Params::new(15, 8, 2, 32).unwrap();
+pub fn scrypt_deriver(salt: &Vec<u8>, password: &str, p: &KdfParams) -> SecretKey {
+    let parms = scrypt::Params::new(p.m as u8, p.t, p.p, 32).unwrap();
     let mut aes_key: [u8; 32] = [0; 32];
 
     scrypt(password.as_bytes(), salt.as_slice(), &parms, &mut aes_key).unwrap();
-    let mut res:Vec<u8> = Vec::new();
-    aes_key.iter().for_each(|i| { res.push(*i) });
+    let res = SecretKey::new(aes_key.to_vec());
+    aes_key.zeroize();
 
     return res;
 }
 
-pub fn sha256_deriver(salt: &Vec<u8>, password: &str) -> Vec<u8> {
+pub fn sha256_deriver(salt: &Vec<u8>, password: &str, _p: &KdfParams) -> SecretKey {
     let mut sha_256: Sha256 = Sha256::new();
 
     sha_256.update(password);
     sha_256.update(salt);
     sha_256.update(password);
-    let hash_res = sha_256.finalize();
+    let mut hash_res = sha_256.finalize();
+    let res = SecretKey::new(hash_res.to_vec());
+    hash_res.as_mut_slice().zeroize();
 
-    return hash_res.to_vec();
+    return res;
 }
\ No newline at end of file
@@ -0,0 +1,297 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::io::{Error, ErrorKind};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use base64::prelude::*;
+use aead::{Aead, KeyInit, AeadCore, KeySizeUser, Payload};
+use cipher::typenum::Unsigned;
+use cipher::generic_array::GenericArray;
+use cipher::generic_array::typenum;
+use aes_gcm::AesGcm;
+use aes_gcm_siv::Aes256GcmSiv;
+use chacha20poly1305::{ChaCha20Poly1305, XChaCha20Poly1305};
+use crate::fcrypt::{CipherId, KdfId, KdfParams, DEFAULT_SALT_SIZE, password_with_key_file};
+use crate::secret::{SecretBytes, SecretKey};
+
+const DATA_KEY_SIZE: usize = 32;
+
+// Everything which is needed in order to derive the key which wraps the data key of a slot. The serialized
+// header is used as associated data when wrapping, i.e. it can not be changed without breaking the slot.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KeySlotHeader {
+    #[serde(rename(deserialize = "Label"))]
+    #[serde(rename(serialize = "Label"))]
+    pub label: String,
+    #[serde(rename(deserialize = "Cipher"))]
+    #[serde(rename(serialize = "Cipher"))]
+    pub cipher: String,
+    #[serde(rename(deserialize = "PbKdf"))]
+    #[serde(rename(serialize = "PbKdf"))]
+    pub pbkdf: String,
+    #[serde(rename(deserialize = "KdfParams"))]
+    #[serde(rename(serialize = "KdfParams"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf_params: Option<KdfParams>,
+    #[serde(rename(deserialize = "Salt"))]
+    #[serde(rename(serialize = "Salt"))]
+    pub salt: String,
+    #[serde(rename(deserialize = "Nonce"))]
+    #[serde(rename(serialize = "Nonce"))]
+    pub nonce: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KeySlot {
+    #[serde(flatten)]
+    pub header: KeySlotHeader,
+    // The encrypted data key
+    #[serde(rename(deserialize = "Key"))]
+    #[serde(rename(serialize = "Key"))]
+    pub key: String
+}
+
+// The payload of a file with key slots is encrypted with a random data key. This data key is stored once per slot,
+// encrypted with a key which is derived from the password of that slot. Slots can therefore be added or removed
+// without reencrypting the payload.
+#[derive(Debug, Clone)]
+pub struct KeySlots {
+    pub slots: Vec<KeySlot>,
+    // None until one of the slots has been unlocked
+    pub data_key: Option<SecretBytes>,
+    // Label of the slot which was unlocked by the password. This slot is wrapped again with the current password,
+    // KDF and KDF parameters each time the file is saved.
+    pub unlocked: Option<String>,
+    // The key file is used for all slots or for none of them
+    pub key_file_required: bool
+}
+
+impl KeySlots {
+    // Creates a new random data key. The slot with the given label is added the next time the file is saved.
+    pub fn new(label: &str, key_file_required: bool) -> KeySlots {
+        let mut data_key = SecretBytes::new(vec![0; DATA_KEY_SIZE]);
+        rand::thread_rng().fill_bytes(data_key.as_mut_slice());
+
+        return KeySlots {
+            slots: Vec::new(),
+            data_key: Some(data_key),
+            unlocked: Some(String::from(label)),
+            key_file_required: key_file_required
+        };
+    }
+
+    pub fn from_slots(slots: Vec<KeySlot>, key_file_required: bool) -> KeySlots {
+        return KeySlots {
+            slots: slots,
+            data_key: None,
+            unlocked: None,
+            key_file_required: key_file_required
+        };
+    }
+
+    // The unlocked slot is part of the list even if it has not been written yet
+    pub fn labels(&self) -> Vec<String> {
+        let mut res: Vec<String> = self.slots.iter().map(|s| s.header.label.clone()).collect();
+
+        if let Some(l) = &self.unlocked {
+            if !res.contains(l) {
+                res.insert(0, l.clone());
+            }
+        }
+
+        return res;
+    }
+
+    // Returns the data key as a key which can be used to en- or decrypt the payload
+    pub fn key(&self) -> Option<SecretKey> {
+        return self.data_key.as_ref().map(|k| SecretKey::new(k.to_vec()));
+    }
+
+    fn derive_slot_key(&self, header: &KeySlotHeader, kdf_id: KdfId, params: &KdfParams, password: &str, key_file: &Option<SecretBytes>) -> std::io::Result<SecretKey> {
+        let salt = match BASE64_STANDARD.decode(&header.salt) {
+            Ok(s) => s,
+            Err(_) => return Err(Error::new(ErrorKind::Other, "Base64 decode error"))
+        };
+
+        let (kdf, _) = kdf_id.to_named_func();
+
+        if self.key_file_required {
+            let h = match key_file {
+                Some(h) => h,
+                None => return Err(Error::new(ErrorKind::Other, "A key file is required to open this file"))
+            };
+
+            return Ok(kdf(&salt, &password_with_key_file(password, h), params));
+        }
+
+        return Ok(kdf(&salt, password, params));
+    }
+
+    // Tries all slots with the given password. On success the data key is available and the KDF and KDF parameters
+    // of the slot which could be unlocked are returned.
+    pub fn unlock(&mut self, password: &str, key_file: &Option<SecretBytes>) -> std::io::Result<(KdfId, KdfParams)> {
+        let mut found: Option<(SecretBytes, String, KdfId, KdfParams)> = None;
+
+        for slot in &self.slots {
+            let kdf_id = match KdfId::from_string(&slot.header.pbkdf) {
+                Some(k) => k,
+                None => continue
+            };
+
+            let cipher_id = match CipherId::from_str(&slot.header.cipher) {
+                Some(c) => c,
+                None => continue
+            };
+
+            let params = slot.header.kdf_params.unwrap_or(kdf_id.default_params());
+
+            if params.check(kdf_id).is_some() {
+                continue;
+            }
+
+            let slot_key = self.derive_slot_key(&slot.header, kdf_id, &params, password, key_file)?;
+
+            let nonce = match BASE64_STANDARD.decode(&slot.header.nonce) {
+                Ok(n) if n.len() == cipher_id.nonce_size() => n,
+                _ => continue
+            };
+
+            let wrapped = match BASE64_STANDARD.decode(&slot.key) {
+                Ok(k) => k,
+                Err(_) => continue
+            };
+
+            let associated_data = serde_json::to_vec(&slot.header)?;
+
+            if let Ok(k) = open(cipher_id, &slot_key, &nonce, &associated_data, &wrapped) {
+                found = Some((k, slot.header.label.clone(), kdf_id, params));
+                break;
+            }
+        }
+
+        if let Some((k, label, kdf_id, params)) = found {
+            self.data_key = Some(k);
+            self.unlocked = Some(label);
+
+            return Ok((kdf_id, params));
+        }
+
+        return Err(Error::new(ErrorKind::Other, "No key slot can be opened with this password"));
+    }
+
+    // Encrypts the data key for the slot with the given label. An existing slot with the same label is replaced.
+    pub fn wrap(&mut self, label: &str, password: &str, key_file: &Option<SecretBytes>, cipher_id: CipherId, kdf_id: KdfId, params: KdfParams) -> std::io::Result<()> {
+        let data_key = match &self.data_key {
+            Some(k) => k.clone(),
+            None => return Err(Error::new(ErrorKind::Other, "Key slots are locked"))
+        };
+
+        let mut rng = rand::thread_rng();
+        let mut salt = vec![0; DEFAULT_SALT_SIZE];
+        rng.fill_bytes(&mut salt);
+        let mut nonce = vec![0; cipher_id.nonce_size()];
+        rng.fill_bytes(&mut nonce);
+
+        let header = KeySlotHeader {
+            label: String::from(label),
+            cipher: cipher_id.to_string(),
+            pbkdf: kdf_id.to_string(),
+            kdf_params: match kdf_id { KdfId::Sha256 => None, _ => Some(params) },
+            salt: BASE64_STANDARD.encode(&salt),
+            nonce: BASE64_STANDARD.encode(&nonce)
+        };
+
+        let slot_key = self.derive_slot_key(&header, kdf_id, &params, password, key_file)?;
+        let associated_data = serde_json::to_vec(&header)?;
+        let wrapped = seal(cipher_id, &slot_key, &nonce, &associated_data, &data_key)?;
+
+        let slot = KeySlot {
+            header: header,
+            key: BASE64_STANDARD.encode(&wrapped)
+        };
+
+        match self.slots.iter().position(|s| s.header.label == label) {
+            Some(i) => self.slots[i] = slot,
+            None => self.slots.push(slot)
+        }
+
+        return Ok(());
+    }
+
+    // The slot which was used to open the file can not be removed
+    pub fn revoke(&mut self, label: &str) -> std::io::Result<()> {
+        if self.unlocked.as_deref() == Some(label) {
+            return Err(Error::new(ErrorKind::Other, "The key slot which was used to open the file can not be revoked"));
+        }
+
+        match self.slots.iter().position(|s| s.header.label == label) {
+            Some(i) => { self.slots.remove(i); },
+            None => return Err(Error::new(ErrorKind::Other, format!("Key slot '{}' does not exist", label)))
+        }
+
+        return Ok(());
+    }
+}
+
+fn seal_with<T: Aead + AeadCore + KeyInit>(key: &[u8], nonce: &[u8], associated_data: &[u8], msg: &[u8]) -> std::io::Result<Vec<u8>> {
+    if nonce.len() != <T as AeadCore>::NonceSize::USIZE {
+        return Err(Error::new(ErrorKind::Other, "Unsupported nonce size"));
+    }
+
+    let key_help = GenericArray::<u8, <T as KeySizeUser>::KeySize>::from_slice(&key[0..T::key_size()]);
+    let nonce_help = GenericArray::<u8, <T as AeadCore>::NonceSize>::from_slice(nonce);
+    let cipher = T::new(&key_help);
+
+    return match cipher.encrypt(nonce_help, Payload { msg: msg, aad: associated_data }) {
+        Ok(d) => Ok(d),
+        Err(_) => Err(Error::new(ErrorKind::Other, "Unable to wrap data key"))
+    };
+}
+
+fn open_with<T: Aead + AeadCore + KeyInit>(key: &[u8], nonce: &[u8], associated_data: &[u8], msg: &[u8]) -> std::io::Result<SecretBytes> {
+    if nonce.len() != <T as AeadCore>::NonceSize::USIZE {
+        return Err(Error::new(ErrorKind::Other, "Unsupported nonce size"));
+    }
+
+    let key_help = GenericArray::<u8, <T as KeySizeUser>::KeySize>::from_slice(&key[0..T::key_size()]);
+    let nonce_help = GenericArray::<u8, <T as AeadCore>::NonceSize>::from_slice(nonce);
+    let cipher = T::new(&key_help);
+
+    return match cipher.decrypt(nonce_help, Payload { msg: msg, aad: associated_data }) {
+        Ok(d) => Ok(SecretBytes::new(d)),
+        Err(_) => Err(Error::new(ErrorKind::Other, "Unable to unwrap data key"))
+    };
+}
+
+pub(super) fn seal(cipher_id: CipherId, key: &[u8], nonce: &[u8], associated_data: &[u8], msg: &[u8]) -> std::io::Result<Vec<u8>> {
+    return match cipher_id {
+        CipherId::Aes192Gcm => seal_with::<AesGcm::<aes::Aes192, typenum::U12>>(key, nonce, associated_data, msg),
+        CipherId::Aes256Gcm => seal_with::<AesGcm::<aes::Aes256, typenum::U12>>(key, nonce, associated_data, msg),
+        CipherId::ChaCha20Poly1305 => seal_with::<ChaCha20Poly1305>(key, nonce, associated_data, msg),
+        CipherId::XChaCha20Poly1305 => seal_with::<XChaCha20Poly1305>(key, nonce, associated_data, msg),
+        CipherId::Aes256GcmSiv => seal_with::<Aes256GcmSiv>(key, nonce, associated_data, msg)
+    };
+}
+
+pub(super) fn open(cipher_id: CipherId, key: &[u8], nonce: &[u8], associated_data: &[u8], msg: &[u8]) -> std::io::Result<SecretBytes> {
+    return match cipher_id {
+        CipherId::Aes192Gcm => open_with::<AesGcm::<aes::Aes192, typenum::U12>>(key, nonce, associated_data, msg),
+        CipherId::Aes256Gcm => open_with::<AesGcm::<aes::Aes256, typenum::U12>>(key, nonce, associated_data, msg),
+        CipherId::ChaCha20Poly1305 => open_with::<ChaCha20Poly1305>(key, nonce, associated_data, msg),
+        CipherId::XChaCha20Poly1305 => open_with::<XChaCha20Poly1305>(key, nonce, associated_data, msg),
+        CipherId::Aes256GcmSiv => open_with::<Aes256GcmSiv>(key, nonce, associated_data, msg)
+    };
+}
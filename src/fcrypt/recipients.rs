@@ -0,0 +1,421 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::io::Read;
+use std::io::Write;
+use std::io::{Error, ErrorKind};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use base64::prelude::*;
+use hkdf::Hkdf;
+use sha2::Sha256;
+use x25519_dalek::{StaticSecret, PublicKey};
+use crate::fcrypt::{Cryptor, AeadContext, CipherId, KeySlots, read_container, write_container};
+use crate::fcrypt::keyslots::{seal, open};
+use crate::secret::SecretBytes;
+
+const X25519_KEY_SIZE: usize = 32;
+const DATA_KEY_SIZE: usize = 32;
+const HKDF_INFO: &[u8] = b"rustpwman recipient key";
+const PUBLIC_KEY_SUFFIX: &str = ".pub";
+
+// Format of the files which hold a private or a public key
+#[derive(Serialize, Deserialize, Debug)]
+struct KeyPairFile {
+    #[serde(rename(deserialize = "Label"))]
+    #[serde(rename(serialize = "Label"))]
+    label: String,
+    #[serde(rename(deserialize = "PublicKey"))]
+    #[serde(rename(serialize = "PublicKey"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    public_key: Option<String>,
+    #[serde(rename(deserialize = "PrivateKey"))]
+    #[serde(rename(serialize = "PrivateKey"))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    private_key: Option<String>
+}
+
+// Everything which is needed in order to derive the key which wraps the data key for a recipient. The serialized
+// header is used as associated data when wrapping.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RecipientHeader {
+    #[serde(rename(deserialize = "Label"))]
+    #[serde(rename(serialize = "Label"))]
+    pub label: String,
+    #[serde(rename(deserialize = "Cipher"))]
+    #[serde(rename(serialize = "Cipher"))]
+    pub cipher: String,
+    #[serde(rename(deserialize = "PublicKey"))]
+    #[serde(rename(serialize = "PublicKey"))]
+    pub public_key: String,
+    #[serde(rename(deserialize = "EphemeralKey"))]
+    #[serde(rename(serialize = "EphemeralKey"))]
+    pub ephemeral_key: String,
+    #[serde(rename(deserialize = "Nonce"))]
+    #[serde(rename(serialize = "Nonce"))]
+    pub nonce: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Recipient {
+    #[serde(flatten)]
+    pub header: RecipientHeader,
+    // The encrypted data key
+    #[serde(rename(deserialize = "Key"))]
+    #[serde(rename(serialize = "Key"))]
+    pub key: String
+}
+
+// A public key to which a file is encrypted
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecipientKey {
+    pub label: String,
+    pub public_key: [u8; X25519_KEY_SIZE]
+}
+
+#[derive(Debug, Clone)]
+pub struct PrivateKey {
+    pub label: String,
+    key: SecretBytes
+}
+
+impl PrivateKey {
+    fn secret(&self) -> StaticSecret {
+        let mut k: [u8; X25519_KEY_SIZE] = [0; X25519_KEY_SIZE];
+        k.copy_from_slice(&self.key);
+
+        return StaticSecret::from(k);
+    }
+
+    pub fn public_key(&self) -> [u8; X25519_KEY_SIZE] {
+        return PublicKey::from(&self.secret()).to_bytes();
+    }
+
+    // The owner of this key as a recipient
+    pub fn recipient(&self) -> RecipientKey {
+        return RecipientKey { label: self.label.clone(), public_key: self.public_key() };
+    }
+}
+
+fn decode_key(encoded: &Option<String>) -> std::io::Result<SecretBytes> {
+    let key = match encoded {
+        Some(k) => match BASE64_STANDARD.decode(k) {
+            Ok(d) => SecretBytes::new(d),
+            Err(_) => return Err(Error::new(ErrorKind::Other, "Base64 decode error"))
+        },
+        None => return Err(Error::new(ErrorKind::Other, "Key file does not contain the expected key"))
+    };
+
+    if key.len() != X25519_KEY_SIZE {
+        return Err(Error::new(ErrorKind::Other, "Key has wrong length"));
+    }
+
+    return Ok(key);
+}
+
+fn write_new_file(file_name: &str, data: &[u8], private: bool) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let mut file = options.open(file_name)?;
+    file.write_all(data)?;
+
+    return Ok(());
+}
+
+pub fn public_key_file_name(private_key_file: &str) -> String {
+    return format!("{}{}", private_key_file, PUBLIC_KEY_SUFFIX);
+}
+
+// Writes the private key to the given file and the public key to a file of the same name with the suffix .pub.
+// Existing files are never overwritten.
+pub fn generate_key_pair(file_name: &str, label: &str) -> std::io::Result<()> {
+    let secret = StaticSecret::random_from_rng(rand::thread_rng());
+    let public = PublicKey::from(&secret);
+    let secret_bytes = SecretBytes::new(secret.to_bytes().to_vec());
+
+    let mut private_file = KeyPairFile {
+        label: String::from(label),
+        public_key: None,
+        private_key: Some(BASE64_STANDARD.encode(secret_bytes.as_slice()))
+    };
+
+    let public_file = KeyPairFile {
+        label: String::from(label),
+        public_key: Some(BASE64_STANDARD.encode(public.as_bytes())),
+        private_key: None
+    };
+
+    let public_name = public_key_file_name(file_name);
+
+    if std::path::Path::new(&public_name).exists() {
+        return Err(Error::new(ErrorKind::AlreadyExists, format!("File {} already exists", public_name)));
+    }
+
+    let private_data = SecretBytes::new(serde_json::to_vec_pretty(&private_file)?);
+    if let Some(k) = &mut private_file.private_key {
+        zeroize::Zeroize::zeroize(k);
+    }
+
+    write_new_file(file_name, &private_data, true)?;
+    write_new_file(&public_name, &serde_json::to_vec_pretty(&public_file)?, false)?;
+
+    return Ok(());
+}
+
+pub fn read_public_key(file_name: &str) -> std::io::Result<RecipientKey> {
+    let data = std::fs::read(file_name)?;
+    let key_file: KeyPairFile = serde_json::from_slice(&data)?;
+    let key = decode_key(&key_file.public_key)?;
+
+    let mut public_key: [u8; X25519_KEY_SIZE] = [0; X25519_KEY_SIZE];
+    public_key.copy_from_slice(&key);
+
+    return Ok(RecipientKey { label: key_file.label, public_key: public_key });
+}
+
+pub fn read_private_key(file_name: &str) -> std::io::Result<PrivateKey> {
+    let data = SecretBytes::new(std::fs::read(file_name)?);
+    let mut key_file: KeyPairFile = serde_json::from_slice(&data)?;
+    let key = decode_key(&key_file.private_key);
+
+    if let Some(k) = &mut key_file.private_key {
+        zeroize::Zeroize::zeroize(k);
+    }
+
+    return Ok(PrivateKey { label: key_file.label, key: key? });
+}
+
+// The key which wraps the data key is derived from the shared secret and both public keys
+fn derive_wrapping_key(shared: &[u8], ephemeral_key: &[u8], public_key: &[u8]) -> std::io::Result<SecretBytes> {
+    let mut salt: Vec<u8> = Vec::new();
+    salt.extend_from_slice(ephemeral_key);
+    salt.extend_from_slice(public_key);
+
+    let hk = Hkdf::<Sha256>::new(Some(&salt), shared);
+    let mut res = SecretBytes::new(vec![0; DATA_KEY_SIZE]);
+
+    if hk.expand(HKDF_INFO, res.as_mut_slice()).is_err() {
+        return Err(Error::new(ErrorKind::Other, "Unable to derive key"));
+    }
+
+    return Ok(res);
+}
+
+fn wrap(recipient: &RecipientKey, cipher_id: CipherId, data_key: &SecretBytes) -> std::io::Result<Recipient> {
+    let ephemeral = StaticSecret::random_from_rng(rand::thread_rng());
+    let ephemeral_public = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(&PublicKey::from(recipient.public_key));
+
+    if !shared.was_contributory() {
+        return Err(Error::new(ErrorKind::Other, format!("Public key of {} is not valid", recipient.label)));
+    }
+
+    let mut nonce = vec![0; cipher_id.nonce_size()];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let header = RecipientHeader {
+        label: recipient.label.clone(),
+        cipher: cipher_id.to_string(),
+        public_key: BASE64_STANDARD.encode(recipient.public_key),
+        ephemeral_key: BASE64_STANDARD.encode(ephemeral_public.as_bytes()),
+        nonce: BASE64_STANDARD.encode(&nonce)
+    };
+
+    let wrapping_key = derive_wrapping_key(shared.as_bytes(), ephemeral_public.as_bytes(), &recipient.public_key)?;
+    let associated_data = serde_json::to_vec(&header)?;
+    let wrapped = seal(cipher_id, &wrapping_key, &nonce, &associated_data, data_key)?;
+
+    return Ok(Recipient { header: header, key: BASE64_STANDARD.encode(&wrapped) });
+}
+
+fn unwrap(recipients: &Vec<Recipient>, private_key: &PrivateKey) -> std::io::Result<SecretBytes> {
+    let own_key = BASE64_STANDARD.encode(private_key.public_key());
+
+    for r in recipients.iter().filter(|r| r.header.public_key == own_key) {
+        let cipher_id = match CipherId::from_str(&r.header.cipher) {
+            Some(c) => c,
+            None => continue
+        };
+
+        let ephemeral_key: [u8; X25519_KEY_SIZE] = match BASE64_STANDARD.decode(&r.header.ephemeral_key) {
+            Ok(k) if k.len() == X25519_KEY_SIZE => k.try_into().unwrap(),
+            _ => continue
+        };
+
+        let nonce = match BASE64_STANDARD.decode(&r.header.nonce) {
+            Ok(n) if n.len() == cipher_id.nonce_size() => n,
+            _ => continue
+        };
+
+        let wrapped = match BASE64_STANDARD.decode(&r.key) {
+            Ok(k) => k,
+            Err(_) => continue
+        };
+
+        let shared = private_key.secret().diffie_hellman(&PublicKey::from(ephemeral_key));
+
+        if !shared.was_contributory() {
+            continue;
+        }
+
+        let wrapping_key = derive_wrapping_key(shared.as_bytes(), &ephemeral_key, &private_key.public_key())?;
+        let associated_data = serde_json::to_vec(&r.header)?;
+
+        if let Ok(k) = open(cipher_id, &wrapping_key, &nonce, &associated_data, &wrapped) {
+            return Ok(k);
+        }
+    }
+
+    return Err(Error::new(ErrorKind::Other, format!("File is not encrypted for the private key {}", private_key.label)));
+}
+
+// Returns the recipients named in the header of an encrypted file. The list is empty if the file is protected by a password.
+pub fn recipients_from_header(raw: &Vec<u8>) -> Vec<RecipientKey> {
+    let header = match read_container(raw.as_slice()) {
+        Ok((h, _, _)) => h,
+        Err(_) => return Vec::new()
+    };
+
+    return match header.recipients {
+        Some(r) => r.iter().filter_map(|r| to_recipient_key(&r.header)).collect(),
+        None => Vec::new()
+    };
+}
+
+fn to_recipient_key(header: &RecipientHeader) -> Option<RecipientKey> {
+    return match BASE64_STANDARD.decode(&header.public_key) {
+        Ok(k) if k.len() == X25519_KEY_SIZE => Some(RecipientKey { label: header.label.clone(), public_key: k.try_into().unwrap() }),
+        _ => None
+    };
+}
+
+// A Cryptor which encrypts the payload with a random data key using another Cryptor. The data key is wrapped for each
+// recipient using X25519 with an ephemeral key pair. Files are opened with a private key instead of a password, i.e.
+// the password which is passed to encrypt and decrypt is ignored.
+pub struct RecipientCryptor {
+    inner: Box<dyn Cryptor>,
+    recipients: Vec<RecipientKey>,
+    private_key: Option<PrivateKey>,
+    wrapped: Vec<Recipient>
+}
+
+impl RecipientCryptor {
+    pub fn new(inner: Box<dyn Cryptor>, recipients: Vec<RecipientKey>, private_key: Option<PrivateKey>) -> RecipientCryptor {
+        return RecipientCryptor {
+            inner: inner,
+            recipients: recipients,
+            private_key: private_key,
+            wrapped: Vec::new()
+        };
+    }
+}
+
+impl Cryptor for RecipientCryptor {
+    fn encrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
+        if self.recipients.len() == 0 {
+            return Err(Error::new(ErrorKind::Other, "No recipients"));
+        }
+
+        // A new data key is used each time the file is saved
+        let mut data_key = SecretBytes::new(vec![0; DATA_KEY_SIZE]);
+        rand::thread_rng().fill_bytes(data_key.as_mut_slice());
+        let cipher_id = self.inner.cipher_id();
+
+        let mut wrapped: Vec<Recipient> = Vec::new();
+        for r in &self.recipients {
+            wrapped.push(wrap(r, cipher_id, &data_key)?);
+        }
+
+        self.wrapped = wrapped;
+        self.inner.set_data_key(Some(data_key));
+
+        return self.inner.encrypt(password, data);
+    }
+
+    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes> {
+        return self.inner.decrypt(password, data);
+    }
+
+    // The wrapped data keys are created by encrypt. Without them nobody would be able to open the file.
+    fn to_dyn_writer(&self, writer: &mut dyn Write, data: &Vec<u8>) -> std::io::Result<()> {
+        if self.wrapped.len() == 0 {
+            return Err(Error::new(ErrorKind::Other, "Data key has not been wrapped for any recipient"));
+        }
+
+        let mut raw: Vec<u8> = Vec::new();
+        self.inner.to_dyn_writer(&mut raw, data)?;
+
+        let (mut header, enc_data, format) = read_container(raw.as_slice())?;
+        header.recipients = Some(self.wrapped.clone());
+
+        return write_container(writer, header, &enc_data, format);
+    }
+
+    // The data key is unwrapped when the file is read. Therefore a private key has to be present at this point.
+    fn from_dyn_reader(&mut self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
+        let mut raw: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut raw)?;
+
+        let (header, _, _) = read_container(raw.as_slice())?;
+
+        let recipients = match header.recipients {
+            Some(r) if r.len() > 0 => r,
+            _ => return Err(Error::new(ErrorKind::Other, "File is not encrypted for public keys"))
+        };
+
+        let data_key = match &self.private_key {
+            Some(k) => unwrap(&recipients, k)?,
+            None => return Err(Error::new(ErrorKind::Other, "A private key is required to open this file"))
+        };
+
+        self.recipients = recipients.iter().filter_map(|r| to_recipient_key(&r.header)).collect();
+        self.inner.set_data_key(Some(data_key));
+
+        return self.inner.from_dyn_reader(&mut raw.as_slice());
+    }
+
+    fn ctx(&self) -> &AeadContext {
+        return self.inner.ctx();
+    }
+
+    fn ctx_mut(&mut self) -> &mut AeadContext {
+        return self.inner.ctx_mut();
+    }
+
+    // A key file is not used in addition to a private key
+    fn set_key_file(&mut self, _key_file: Option<SecretBytes>) {
+    }
+
+    fn key_slots(&self) -> Option<KeySlots> {
+        return None;
+    }
+
+    fn set_key_slots(&mut self, _key_slots: Option<KeySlots>) {
+    }
+
+    fn recipients(&self) -> Vec<RecipientKey> {
+        return self.recipients.clone();
+    }
+}
@@ -0,0 +1,166 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::io::{Error, ErrorKind};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use base64::prelude::*;
+use crate::fcrypt::{CipherId, KdfId, KdfParams, DEFAULT_SALT_SIZE, password_with_key_file};
+use crate::fcrypt::keyslots::{seal, open};
+use crate::secret::{SecretBytes, SecretKey};
+
+// Everything which is needed in order to derive the key of a sequence of records from the password
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct RecordHeader {
+    #[serde(rename(deserialize = "Cipher"))]
+    #[serde(rename(serialize = "Cipher"))]
+    cipher: String,
+    #[serde(rename(deserialize = "PbKdf"))]
+    #[serde(rename(serialize = "PbKdf"))]
+    pbkdf: String,
+    #[serde(rename(deserialize = "KdfParams"))]
+    #[serde(rename(serialize = "KdfParams"))]
+    kdf_params: KdfParams,
+    #[serde(rename(deserialize = "KeyFile"))]
+    #[serde(rename(serialize = "KeyFile"))]
+    key_file: bool,
+    #[serde(rename(deserialize = "Salt"))]
+    #[serde(rename(serialize = "Salt"))]
+    salt: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Record {
+    #[serde(rename(deserialize = "Nonce"))]
+    #[serde(rename(serialize = "Nonce"))]
+    nonce: String,
+    #[serde(rename(deserialize = "Data"))]
+    #[serde(rename(serialize = "Data"))]
+    data: String
+}
+
+// Encrypts a sequence of records, e.g. the changes recorded in a journal, with one key. The key is derived from the
+// password only once, so encrypting another record is cheap. The header and all records are single lines of JSON.
+// The header and the position of a record are used as associated data, i.e. records can not be reordered or moved
+// to another sequence.
+pub struct RecordCryptor {
+    header: String,
+    cipher_id: CipherId,
+    key: SecretKey
+}
+
+impl RecordCryptor {
+    // Derives a new key from the password using a random salt
+    pub fn new(cipher_id: CipherId, kdf_id: KdfId, params: KdfParams, password: &str, key_file: &Option<SecretBytes>) -> std::io::Result<RecordCryptor> {
+        let mut salt = vec![0; DEFAULT_SALT_SIZE];
+        rand::thread_rng().fill_bytes(&mut salt);
+
+        let header = RecordHeader {
+            cipher: cipher_id.to_string(),
+            pbkdf: kdf_id.to_string(),
+            kdf_params: params,
+            key_file: key_file.is_some(),
+            salt: BASE64_STANDARD.encode(&salt)
+        };
+
+        let (kdf, _) = kdf_id.to_named_func();
+        let key = match key_file {
+            Some(h) => kdf(&salt, &password_with_key_file(password, h), &params),
+            None => kdf(&salt, password, &params)
+        };
+
+        return Ok(RecordCryptor {
+            header: serde_json::to_string(&header)?,
+            cipher_id: cipher_id,
+            key: key
+        });
+    }
+
+    // Derives the key again from a header which was written by another RecordCryptor
+    pub fn from_header(header: &str, password: &str, key_file: &Option<SecretBytes>) -> std::io::Result<RecordCryptor> {
+        let h: RecordHeader = serde_json::from_str(header)?;
+
+        let cipher_id = match CipherId::from_str(&h.cipher) {
+            Some(c) => c,
+            None => return Err(Error::new(ErrorKind::Other, format!("Unknown cipher {}", &h.cipher)))
+        };
+
+        let kdf_id = match KdfId::from_string(&h.pbkdf) {
+            Some(k) => k,
+            None => return Err(Error::new(ErrorKind::Other, format!("Unknown key derivation function {}", &h.pbkdf)))
+        };
+
+        if let Some(e) = h.kdf_params.check(kdf_id) {
+            return Err(e);
+        }
+
+        let salt = match BASE64_STANDARD.decode(&h.salt) {
+            Ok(s) => s,
+            Err(_) => return Err(Error::new(ErrorKind::Other, "Base64 decode error"))
+        };
+
+        let (kdf, _) = kdf_id.to_named_func();
+        let key = match (h.key_file, key_file) {
+            (true, Some(f)) => kdf(&salt, &password_with_key_file(password, f), &h.kdf_params),
+            (true, None) => return Err(Error::new(ErrorKind::Other, "A key file is required to open this file")),
+            (false, _) => kdf(&salt, password, &h.kdf_params)
+        };
+
+        return Ok(RecordCryptor {
+            header: String::from(header),
+            cipher_id: cipher_id,
+            key: key
+        });
+    }
+
+    pub fn header(&self) -> &str {
+        return &self.header;
+    }
+
+    fn associated_data(&self, index: usize) -> Vec<u8> {
+        let mut res = self.header.as_bytes().to_vec();
+        res.extend_from_slice(&(index as u64).to_le_bytes());
+
+        return res;
+    }
+
+    // Encrypts the record which is stored at the given position
+    pub fn seal(&self, index: usize, msg: &[u8]) -> std::io::Result<String> {
+        let mut nonce = vec![0; self.cipher_id.nonce_size()];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let data = seal(self.cipher_id, &self.key, &nonce, &self.associated_data(index), msg)?;
+        let record = Record {
+            nonce: BASE64_STANDARD.encode(&nonce),
+            data: BASE64_STANDARD.encode(&data)
+        };
+
+        return Ok(serde_json::to_string(&record)?);
+    }
+
+    pub fn open(&self, index: usize, record: &str) -> std::io::Result<SecretBytes> {
+        let r: Record = serde_json::from_str(record)?;
+
+        let (nonce, data) = match (BASE64_STANDARD.decode(&r.nonce), BASE64_STANDARD.decode(&r.data)) {
+            (Ok(n), Ok(d)) => (n, d),
+            _ => return Err(Error::new(ErrorKind::Other, "Base64 decode error"))
+        };
+
+        return match open(self.cipher_id, &self.key, &nonce, &self.associated_data(index), &data) {
+            Ok(d) => Ok(d),
+            Err(_) => Err(Error::new(ErrorKind::Other, format!("Unable to decrypt record {}", index)))
+        };
+    }
+}
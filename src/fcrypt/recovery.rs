@@ -0,0 +1,148 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::io::{Error, ErrorKind};
+use rand::RngCore;
+use base64::prelude::*;
+use sha2::{Sha256, Digest};
+use crate::fcrypt::shamir;
+use crate::fcrypt::shamir::Share;
+use qrcode::QrCode;
+use qrcode::render::unicode::Dense1x2;
+use crate::secret::{SecretBytes, SecretString};
+
+// The recovery secret unlocks the key slot with this label. Its base64 encoding is used as the password of the slot, i.e.
+// the slot is protected by the KDF of the slot like any other slot.
+pub const RECOVERY_KEY_SLOT: &str = "recovery";
+const RECOVERY_SECRET_SIZE: usize = 32;
+const SHARE_PREFIX: &str = "rpwm-share";
+// Number of bytes of the SHA-256 hash of the secret which are contained in each share. It allows to detect shares which
+// belong to different secrets before the file is opened.
+const FINGERPRINT_SIZE: usize = 4;
+
+pub fn generate_recovery_secret() -> SecretBytes {
+    let mut secret = SecretBytes::new(vec![0; RECOVERY_SECRET_SIZE]);
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    return secret;
+}
+
+pub fn recovery_password(secret: &SecretBytes) -> SecretString {
+    return SecretString::new(BASE64_STANDARD.encode(secret.as_slice()));
+}
+
+fn fingerprint(secret: &[u8]) -> String {
+    let hash = Sha256::digest(secret);
+
+    return hash[..FINGERPRINT_SIZE].iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+// Splits the secret into count shares of which any threshold shares allow to reconstruct it. Each share is a line of
+// text of the form rpwm-share:<threshold>:<fingerprint>:<base64 of share>.
+pub fn split_recovery_secret(secret: &SecretBytes, threshold: u8, count: u8) -> std::io::Result<Vec<String>> {
+    if (threshold < 2) || (count < threshold) {
+        return Err(Error::new(ErrorKind::Other, "At least two shares have to be needed and at least as many shares have to be created"));
+    }
+
+    let fp = fingerprint(secret);
+
+    let res = shamir::split(secret, threshold, count)?.iter().map(|s| {
+        format!("{}:{}:{}:{}", SHARE_PREFIX, threshold, fp, BASE64_STANDARD.encode(s.to_bytes().as_slice()))
+    }).collect();
+
+    return Ok(res);
+}
+
+// Parses a share and returns its threshold, fingerprint and value
+fn parse_share(share: &str) -> std::io::Result<(u8, String, Share)> {
+    let parts: Vec<&str> = share.trim().split(':').collect();
+
+    if (parts.len() != 4) || (parts[0] != SHARE_PREFIX) {
+        return Err(Error::new(ErrorKind::Other, "Not a recovery share"));
+    }
+
+    let threshold = match parts[1].parse::<u8>() {
+        Ok(t) => t,
+        Err(_) => return Err(Error::new(ErrorKind::Other, "Threshold of recovery share invalid"))
+    };
+
+    let share_bytes = match BASE64_STANDARD.decode(parts[3]) {
+        Ok(b) => b,
+        Err(_) => return Err(Error::new(ErrorKind::Other, "Base64 decode error"))
+    };
+
+    let value = match Share::from_bytes(&share_bytes) {
+        Ok(s) => s,
+        Err(e) => return Err(Error::new(ErrorKind::Other, format!("Recovery share invalid. {}", e)))
+    };
+
+    return Ok((threshold, String::from(parts[2]), value));
+}
+
+// Reconstructs the recovery secret. All shares have to belong to the same secret. Shares which were given more than
+// once are only counted once.
+pub fn combine_shares(shares: &Vec<String>) -> std::io::Result<SecretBytes> {
+    let mut threshold: Option<u8> = None;
+    let mut fp: Option<String> = None;
+    let mut values: Vec<Share> = Vec::new();
+
+    for s in shares {
+        let (t, f, v) = parse_share(s)?;
+
+        if (threshold.is_some() && (threshold != Some(t))) || (fp.is_some() && (fp.as_ref() != Some(&f))) {
+            return Err(Error::new(ErrorKind::Other, "Recovery shares belong to different secrets"));
+        }
+
+        threshold = Some(t);
+        fp = Some(f);
+
+        if !values.iter().any(|other| other.x == v.x) {
+            values.push(v);
+        }
+    }
+
+    let threshold = match threshold {
+        Some(t) => t,
+        None => return Err(Error::new(ErrorKind::Other, "No recovery shares given"))
+    };
+
+    if values.len() < threshold as usize {
+        return Err(Error::new(ErrorKind::Other, format!("{} recovery shares are needed but only {} were given", threshold, values.len())));
+    }
+
+    let secret = match shamir::combine(&values) {
+        Ok(s) => s,
+        Err(e) => return Err(Error::new(ErrorKind::Other, format!("Unable to combine recovery shares. {}", e)))
+    };
+
+    if Some(fingerprint(&secret)) != fp {
+        return Err(Error::new(ErrorKind::Other, "Recovery shares are damaged"));
+    }
+
+    return Ok(secret);
+}
+
+// Renders a share as a QR code made of unicode block characters
+pub fn share_to_qr(share: &str) -> std::io::Result<String> {
+    let code = match QrCode::new(share.as_bytes()) {
+        Ok(c) => c,
+        Err(e) => return Err(Error::new(ErrorKind::Other, format!("Unable to create QR code. {:?}", e)))
+    };
+
+    return Ok(code.render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build());
+}
@@ -13,48 +13,42 @@ See the License for the specific language governing permissions and
 limitations under the License. */
 
 
-use std::io::Read;
-use std::io::Write;
-use crate::fcrypt::{Cryptor, AeadContext, KdfId, KeyDeriver};
+use crate::fcrypt::{Cryptor, AeadContext, CipherId, KdfId, KeyDeriver};
 use crate::fcrypt::{decrypt_aead, encrypt_aead};
+use crate::secret::SecretBytes;
 use cipher::generic_array::typenum;
 use aes_gcm::AesGcm;
+use aes_gcm_siv::Aes256GcmSiv;
 use super::derivers;
 
 pub struct Gcm256Context(AeadContext);
-const ALGO_AES256: &str = "AES-256 GCM";
-const ALGO_AES192: &str = "AES-192 GCM";
 
 impl Gcm256Context {
     #![allow(dead_code)]
     pub fn new() -> Gcm256Context {
-        return Gcm256Context(AeadContext::new_with_kdf(derivers::sha256_deriver, KdfId::Sha256))
+        return Gcm256Context(AeadContext::new_for_cipher(derivers::sha256_deriver, KdfId::Sha256, CipherId::Aes256Gcm))
     }
 
     pub fn new_with_kdf(derive: KeyDeriver, deriver_id: KdfId) -> Gcm256Context {
-        return Gcm256Context(AeadContext::new_with_kdf(derive, deriver_id));
+        return Gcm256Context(AeadContext::new_for_cipher(derive, deriver_id, CipherId::Aes256Gcm));
     }
 }
 
 impl Cryptor for Gcm256Context {
-    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
-        return decrypt_aead::<AesGcm::<aes::Aes256, typenum::U12>>(&mut self.0, password, data, ALGO_AES256);
+    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes> {
+        return decrypt_aead::<AesGcm::<aes::Aes256, typenum::U12>>(&mut self.0, password, data);
     }
 
     fn encrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
-        return encrypt_aead::<AesGcm::<aes::Aes256, typenum::U12>>(&mut self.0, password, data, ALGO_AES256);
+        return encrypt_aead::<AesGcm::<aes::Aes256, typenum::U12>>(&mut self.0, password, data);
     }
 
-    fn from_dyn_reader(&mut self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
-        return self.0.from_reader(reader);
+    fn ctx(&self) -> &AeadContext {
+        return &self.0;
     }
 
-    fn to_dyn_writer(&self, writer: &mut dyn Write, data: &Vec<u8>) -> std::io::Result<()> {
-        return self.0.to_writer(writer, data);
-    }
-
-    fn algo_name(&self) -> &'static str {
-        return ALGO_AES256;
+    fn ctx_mut(&mut self) -> &mut AeadContext {
+        return &mut self.0;
     }
 }
 
@@ -63,32 +57,60 @@ pub struct Gcm192Context(AeadContext);
 impl Gcm192Context {
     #![allow(dead_code)]
     pub fn new() -> Gcm192Context {
-        return Gcm192Context(AeadContext::new_with_kdf(derivers::sha256_deriver, KdfId::Sha256))
+        return Gcm192Context(AeadContext::new_for_cipher(derivers::sha256_deriver, KdfId::Sha256, CipherId::Aes192Gcm))
     }
 
     pub fn new_with_kdf(derive: KeyDeriver, deriver_id: KdfId) -> Gcm192Context {
-        return Gcm192Context(AeadContext::new_with_kdf(derive, deriver_id));
+        return Gcm192Context(AeadContext::new_for_cipher(derive, deriver_id, CipherId::Aes192Gcm));
     }
 }
 
 impl Cryptor for Gcm192Context {
-    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
-        return decrypt_aead::<AesGcm::<aes::Aes192, typenum::U12>>(&mut self.0,password, data, ALGO_AES192);
+    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes> {
+        return decrypt_aead::<AesGcm::<aes::Aes192, typenum::U12>>(&mut self.0, password, data);
     }
 
     fn encrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
-        return encrypt_aead::<AesGcm::<aes::Aes192, typenum::U12>>(&mut self.0, password, data, ALGO_AES192);
+        return encrypt_aead::<AesGcm::<aes::Aes192, typenum::U12>>(&mut self.0, password, data);
     }
 
-    fn from_dyn_reader(&mut self, reader: &mut dyn Read) -> std::io::Result<Vec<u8>> {
-        return self.0.from_reader(reader);
+    fn ctx(&self) -> &AeadContext {
+        return &self.0;
+    }
+
+    fn ctx_mut(&mut self) -> &mut AeadContext {
+        return &mut self.0;
+    }
+}
+
+// AES-256 GCM-SIV does not lose its confidentiality completely if a nonce is ever used twice
+pub struct GcmSiv256Context(AeadContext);
+
+impl GcmSiv256Context {
+    #![allow(dead_code)]
+    pub fn new() -> GcmSiv256Context {
+        return GcmSiv256Context(AeadContext::new_for_cipher(derivers::sha256_deriver, KdfId::Sha256, CipherId::Aes256GcmSiv))
+    }
+
+    pub fn new_with_kdf(derive: KeyDeriver, deriver_id: KdfId) -> GcmSiv256Context {
+        return GcmSiv256Context(AeadContext::new_for_cipher(derive, deriver_id, CipherId::Aes256GcmSiv));
+    }
+}
+
+impl Cryptor for GcmSiv256Context {
+    fn decrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<SecretBytes> {
+        return decrypt_aead::<Aes256GcmSiv>(&mut self.0, password, data);
+    }
+
+    fn encrypt(&mut self, password: &str, data: &Vec<u8>) -> std::io::Result<Vec<u8>> {
+        return encrypt_aead::<Aes256GcmSiv>(&mut self.0, password, data);
     }
 
-    fn to_dyn_writer(&self, writer: &mut dyn Write, data: &Vec<u8>) -> std::io::Result<()> {
-        return self.0.to_writer(writer, data);
+    fn ctx(&self) -> &AeadContext {
+        return &self.0;
     }
 
-    fn algo_name(&self) -> &'static str {
-        return ALGO_AES192;
+    fn ctx_mut(&mut self) -> &mut AeadContext {
+        return &mut self.0;
     }
 }
@@ -0,0 +1,156 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::io::{Error, ErrorKind};
+use rand::RngCore;
+use zeroize::Zeroize;
+use crate::secret::SecretBytes;
+
+// Shamir's secret sharing over GF(2^8). Each byte of the secret is the constant term of its own random polynomial of
+// degree threshold - 1 and a share contains the values of all these polynomials at the x coordinate of the share.
+// All other coefficients are drawn uniformly from the whole field, including 0, as otherwise each share would leak
+// information about the secret. The field uses the reduction polynomial x^8 + x^4 + x^3 + x^2 + 1 (0x11d).
+// Arithmetic does not use lookup tables or branches which depend on secret values.
+
+const REDUCTION: u8 = 0x1d;
+
+fn gf_mul(a: u8, b: u8) -> u8 {
+    let mut a = a;
+    let mut b = b;
+    let mut res: u8 = 0;
+
+    for _ in 0..8 {
+        // All bits set if the lowest bit of b is set
+        res ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & REDUCTION);
+        b >>= 1;
+    }
+
+    return res;
+}
+
+// a^254 is the inverse of a for all a != 0
+fn gf_inv(a: u8) -> u8 {
+    let mut res: u8 = 1;
+    let mut square = a;
+    let mut e: u8 = 254;
+
+    for _ in 0..8 {
+        let factor = gf_mul(res, square);
+        let mask = (e & 1).wrapping_neg();
+        res = (factor & mask) | (res & !mask);
+        square = gf_mul(square, square);
+        e >>= 1;
+    }
+
+    return res;
+}
+
+// The serialized form is the x coordinate followed by one y value per byte of the secret
+pub struct Share {
+    pub x: u8,
+    pub y: SecretBytes
+}
+
+impl Share {
+    pub fn to_bytes(&self) -> SecretBytes {
+        let mut res = SecretBytes::new(Vec::with_capacity(self.y.len() + 1));
+        res.push(self.x);
+        res.extend_from_slice(&self.y);
+
+        return res;
+    }
+
+    pub fn from_bytes(data: &[u8]) -> std::io::Result<Share> {
+        if data.len() < 2 {
+            return Err(Error::new(ErrorKind::Other, "Share is too short"));
+        }
+
+        if data[0] == 0 {
+            return Err(Error::new(ErrorKind::Other, "Share has an invalid x coordinate"));
+        }
+
+        return Ok(Share { x: data[0], y: SecretBytes::new(data[1..].to_vec()) });
+    }
+}
+
+// Creates count shares with the x coordinates 1 to count of which any threshold shares allow to reconstruct the secret
+pub fn split(secret: &[u8], threshold: u8, count: u8) -> std::io::Result<Vec<Share>> {
+    if (threshold < 1) || (count < threshold) || (secret.len() == 0) {
+        return Err(Error::new(ErrorKind::Other, "Invalid parameters for secret sharing"));
+    }
+
+    // coefficients[i * (threshold - 1) + j] is the coefficient of x^(j + 1) of the polynomial of byte i
+    let degree = (threshold - 1) as usize;
+    let mut coefficients = SecretBytes::new(vec![0; secret.len() * degree]);
+    rand::thread_rng().fill_bytes(&mut coefficients);
+
+    let mut res: Vec<Share> = Vec::new();
+
+    for x in 1..=count {
+        let mut y = SecretBytes::new(Vec::with_capacity(secret.len()));
+
+        for (i, s) in secret.iter().enumerate() {
+            // Horner's method starting with the highest coefficient
+            let mut value: u8 = 0;
+
+            for c in coefficients[i * degree..(i + 1) * degree].iter().rev() {
+                value = gf_mul(value ^ c, x);
+            }
+
+            y.push(value ^ s);
+        }
+
+        res.push(Share { x: x, y: y });
+    }
+
+    coefficients.zeroize();
+
+    return Ok(res);
+}
+
+// Reconstructs the secret by Lagrange interpolation at x = 0. All shares have to use different x coordinates and
+// exactly as many shares as the threshold or more have to be given. Fewer shares result in a wrong secret.
+pub fn combine(shares: &[Share]) -> std::io::Result<SecretBytes> {
+    if shares.len() == 0 {
+        return Err(Error::new(ErrorKind::Other, "No shares given"));
+    }
+
+    let len = shares[0].y.len();
+
+    for (i, s) in shares.iter().enumerate() {
+        if (s.x == 0) || (s.y.len() != len) || shares[..i].iter().any(|o| o.x == s.x) {
+            return Err(Error::new(ErrorKind::Other, "Shares do not fit together"));
+        }
+    }
+
+    // Lagrange basis polynomials evaluated at 0. Subtraction is the same as addition in GF(2^8).
+    let basis: Vec<u8> = shares.iter().map(|s_i| {
+        shares.iter()
+            .filter(|s_j| s_j.x != s_i.x)
+            .fold(1, |acc, s_j| gf_mul(acc, gf_mul(s_j.x, gf_inv(s_j.x ^ s_i.x))))
+    }).collect();
+
+    let mut res = SecretBytes::new(vec![0; len]);
+
+    for (s, b) in shares.iter().zip(basis.iter()) {
+        for k in 0..len {
+            res[k] ^= gf_mul(s.y[k], *b);
+        }
+    }
+
+    return Ok(res);
+}
@@ -22,26 +22,379 @@ use std::io::{Error, ErrorKind};
 use crate::fcrypt;
 use crate::persist::SendSyncPersister;
 use crate::undo::UndoRepo;
+use crate::tagfilter;
+use crate::merge;
+use crate::journal;
+use crate::journal::JournalOp;
+use crate::timestamp;
+use crate::timestamp::Timestamp;
 use fcrypt::KeyDeriver;
 use fcrypt::KdfId;
+use fcrypt::KdfParams;
 use fcrypt::Cryptor;
+use fcrypt::CipherId;
+use fcrypt::KeySlots;
+use fcrypt::ContainerFormat;
+use fcrypt::{RecipientCryptor, RecipientKey, PrivateKey};
+use crate::secret::SecretBytes;
+use zeroize::Zeroize;
+use rand::RngCore;
+use sha2::{Sha256, Digest};
 
 
 pub type CryptorGen = Box<dyn Fn(KeyDeriver, KdfId) -> Box<dyn Cryptor>  + Send + Sync>;
 pub type BackupCallback = fn(&Vec<u8>) -> std::io::Result<()>;
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct CustomField {
+    #[serde(rename(deserialize = "Name"))]
+    #[serde(rename(serialize = "Name"))]
+    pub name: String,
+    #[serde(rename(deserialize = "Value"))]
+    #[serde(rename(serialize = "Value"))]
+    pub value: String
+}
+
+impl CustomField {
+    pub fn new(n: &str, v: &str) -> CustomField {
+        return CustomField {
+            name: String::from(n),
+            value: String::from(v)
+        }
+    }
+
+    // Parses lines of the form "name: value". Empty lines are ignored.
+    pub fn parse_lines(text: &str) -> Result<Vec<CustomField>, String> {
+        let mut res: Vec<CustomField> = Vec::new();
+
+        for (line_no, line) in text.lines().enumerate() {
+            if line.trim() == "" {
+                continue;
+            }
+
+            let (name, value) = match line.split_once(':') {
+                Some(p) => p,
+                None => return Err(format!("Custom field in line {} has no ':'", line_no + 1))
+            };
+
+            if name.trim() == "" {
+                return Err(format!("Custom field in line {} has no name", line_no + 1));
+            }
+
+            res.push(CustomField::new(name.trim(), value.trim()));
+        }
+
+        return Ok(res);
+    }
+
+    pub fn to_lines(fields: &Vec<CustomField>) -> String {
+        let mut res = String::from("");
+
+        for i in fields {
+            res.push_str(format!("{}: {}\n", i.name, i.value).as_str());
+        }
+
+        return res;
+    }
+}
+
+// The free text of an entry is still serialized as "Text" so that files which were created before
+// structured fields existed can be read without any conversion. All other fields are optional.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Entry {
+    // Stable identity of the entry which does not change when the entry is renamed or modified
+    #[serde(rename(deserialize = "Id"))]
+    #[serde(rename(serialize = "Id"))]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub id: String,
+    #[serde(rename(deserialize = "Text"))]
+    #[serde(rename(serialize = "Text"))]
+    pub text: String,
+    #[serde(rename(deserialize = "UserName"))]
+    #[serde(rename(serialize = "UserName"))]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub user_name: String,
+    #[serde(rename(deserialize = "Password"))]
+    #[serde(rename(serialize = "Password"))]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub password: String,
+    #[serde(rename(deserialize = "Url"))]
+    #[serde(rename(serialize = "Url"))]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    #[serde(rename(deserialize = "CustomFields"))]
+    #[serde(rename(serialize = "CustomFields"))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_fields: Vec<CustomField>,
+    #[serde(rename(deserialize = "Group"))]
+    #[serde(rename(serialize = "Group"))]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub group: String,
+    #[serde(rename(deserialize = "Tags"))]
+    #[serde(rename(serialize = "Tags"))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(rename(deserialize = "Created"))]
+    #[serde(rename(serialize = "Created"))]
+    #[serde(default, skip_serializing_if = "timestamp::is_unknown")]
+    pub created: Timestamp,
+    #[serde(rename(deserialize = "Modified"))]
+    #[serde(rename(serialize = "Modified"))]
+    #[serde(default, skip_serializing_if = "timestamp::is_unknown")]
+    pub modified: Timestamp,
+    #[serde(rename(deserialize = "SecretChanged"))]
+    #[serde(rename(serialize = "SecretChanged"))]
+    #[serde(default, skip_serializing_if = "timestamp::is_unknown")]
+    pub secret_changed: Timestamp,
+    #[serde(rename(deserialize = "Attachments"))]
+    #[serde(rename(serialize = "Attachments"))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+    #[serde(rename(deserialize = "History"))]
+    #[serde(rename(serialize = "History"))]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<HistoryItem>
+}
+
+// Binary data like key files or certificates which belong to an entry. As everything else in the payload the data
+// is stored as JSON and is therefore base64 encoded in the same way as the ciphertext in the outer container.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Attachment {
+    #[serde(rename(deserialize = "Name"))]
+    #[serde(rename(serialize = "Name"))]
+    pub name: String,
+    #[serde(rename(deserialize = "Added"))]
+    #[serde(rename(serialize = "Added"))]
+    #[serde(default, skip_serializing_if = "timestamp::is_unknown")]
+    pub added: Timestamp,
+    #[serde(rename(deserialize = "Data"))]
+    #[serde(rename(serialize = "Data"))]
+    #[serde(with = "base64_data")]
+    pub data: Vec<u8>
+}
+
+mod base64_data {
+    use base64::prelude::*;
+    use serde::{Serializer, Deserializer, Deserialize};
+
+    pub fn serialize<S: Serializer>(data: &Vec<u8>, s: S) -> Result<S::Ok, S::Error> {
+        return s.serialize_str(&BASE64_STANDARD.encode(data));
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(d)?;
+
+        return BASE64_STANDARD.decode(encoded.as_bytes()).map_err(serde::de::Error::custom);
+    }
+}
+
+// Number of random bytes in the id of an entry
+const ENTRY_ID_SIZE: usize = 16;
+
+// Returns a new random id for an entry
+pub fn new_entry_id() -> String {
+    let mut id = vec![0u8; ENTRY_ID_SIZE];
+    rand::thread_rng().fill_bytes(&mut id);
+
+    return id.iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+// Entries which were written before ids were introduced get an id which is derived from their name and creation time.
+// This way all copies of a file derive the same id for the same entry.
+fn legacy_entry_id(key: &String, created: Timestamp) -> String {
+    let mut h = Sha256::new();
+    h.update(key.as_bytes());
+    h.update(created.to_le_bytes());
+
+    return h.finalize()[..ENTRY_ID_SIZE].iter().map(|b| format!("{:02x}", b)).collect();
+}
+
+// Maximum number of previous values which are kept for each entry
+pub const MAX_HISTORY_ITEMS: usize = 15;
+// Label of the slot which holds the password that was used before a store was converted to key slots
+pub const DEFAULT_KEY_SLOT: &str = "default";
+
+// A previous value of an entry. The value itself has no history of its own.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct HistoryItem {
+    #[serde(rename(deserialize = "Timestamp"))]
+    #[serde(rename(serialize = "Timestamp"))]
+    pub timestamp: Timestamp,
+    #[serde(rename(deserialize = "Value"))]
+    #[serde(rename(serialize = "Value"))]
+    pub value: Entry
+}
+
+// Overwrites all data of an entry which may be secret
+impl Zeroize for Entry {
+    fn zeroize(&mut self) {
+        self.text.zeroize();
+        self.user_name.zeroize();
+        self.password.zeroize();
+        self.url.zeroize();
+        self.custom_fields.iter_mut().for_each(|f| { f.name.zeroize(); f.value.zeroize(); });
+        self.attachments.iter_mut().for_each(|a| { a.data.zeroize(); });
+        self.history.iter_mut().for_each(|h| { h.value.zeroize(); });
+    }
+}
+
+impl Entry {
+    pub fn new(text: &String) -> Entry {
+        return Entry {
+            id: new_entry_id(),
+            text: text.clone(),
+            ..Default::default()
+        }
+    }
+
+    // Resets the contents of the entry but keeps all information that describes where it is filed
+    pub fn clear_contents(&mut self, text: &String) {
+        self.text = text.clone();
+        self.user_name.clear();
+        self.password.clear();
+        self.url.clear();
+        self.custom_fields.clear();
+    }
+
+    // Returns true if text and fields of both entries are the same. Filing information and history are ignored.
+    pub fn same_contents(&self, other: &Entry) -> bool {
+        return (self.text == other.text) && (self.user_name == other.user_name) && (self.password == other.password) &&
+               (self.url == other.url) && (self.custom_fields == other.custom_fields);
+    }
+
+    // Copies text and fields from other. Filing information and history are left unchanged.
+    pub fn set_contents_from(&mut self, other: &Entry) {
+        self.text = other.text.clone();
+        self.user_name = other.user_name.clone();
+        self.password = other.password.clone();
+        self.url = other.url.clone();
+        self.custom_fields = other.custom_fields.clone();
+    }
+
+    // The secret of an entry is its password field. Entries which have no password field keep their secrets
+    // in the free text, so in this case all contents is regarded as secret.
+    pub fn secret_differs(&self, other: &Entry) -> bool {
+        if (self.password != "") || (other.password != "") {
+            return self.password != other.password;
+        }
+
+        return !self.same_contents(other);
+    }
+
+    // Stores the current contents of old as the newest history item of this entry. The oldest items are
+    // dropped when more than MAX_HISTORY_ITEMS are present.
+    pub fn push_history(&mut self, old: &Entry, ts: Timestamp) {
+        let mut value = old.clone();
+        value.history.clear();
+        value.id.clear();
+        // Attachments are not part of the versioned contents
+        value.attachments.clear();
+
+        self.history.insert(0, HistoryItem { timestamp: ts, value: value });
+        self.history.truncate(MAX_HISTORY_ITEMS);
+    }
+
+    pub fn has_fields(&self) -> bool {
+        return (self.user_name != "") || (self.password != "") || (self.url != "") || (self.custom_fields.len() != 0);
+    }
+
+    // Returns all structured fields as (name, value) pairs in the order in which they are displayed
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut res: Vec<(String, String)> = Vec::new();
+
+        if self.user_name != "" {
+            res.push((String::from(FIELD_USER_NAME), self.user_name.clone()));
+        }
+
+        if self.password != "" {
+            res.push((String::from(FIELD_PASSWORD), self.password.clone()));
+        }
+
+        if self.url != "" {
+            res.push((String::from(FIELD_URL), self.url.clone()));
+        }
+
+        for i in &self.custom_fields {
+            res.push((i.name.clone(), i.value.clone()));
+        }
+
+        return res;
+    }
+
+    // Formats the whole entry as text. An entry without structured fields is represented
+    // by its free text only, i.e. it looks exactly like an entry of older versions.
+    pub fn to_formatted_string(&self) -> String {
+        if !self.has_fields() {
+            return self.text.clone();
+        }
+
+        let fields = self.fields();
+        let width = fields.iter().map(|i| i.0.chars().count()).max().unwrap_or(0);
+        let mut res = String::from("");
+
+        for (name, value) in fields {
+            res.push_str(format!("{:width$}: {}\n", name, value, width = width).as_str());
+        }
+
+        if self.text != "" {
+            res.push_str("\n");
+            res.push_str(&self.text);
+        }
+
+        return res;
+    }
+}
+
+pub const GROUP_SEPARATOR: char = '/';
+
+// Removes empty path components and surrounding white space from a group path, i.e. " a//b/ " becomes "a/b".
+// The empty string denotes the top level.
+pub fn normalize_group(group: &str) -> String {
+    let components: Vec<&str> = group.split(GROUP_SEPARATOR).map(|i| i.trim()).filter(|i| *i != "").collect();
+
+    return components.join(&GROUP_SEPARATOR.to_string());
+}
+
+// Returns the given group paths including all their parent groups in sorted order
+pub fn collect_groups<'a, I: Iterator<Item = &'a String>>(groups: I) -> Vec<String> {
+    let mut res: Vec<String> = Vec::new();
+
+    for g in groups {
+        let mut path = String::from("");
+
+        for component in g.split(GROUP_SEPARATOR).filter(|c| *c != "") {
+            if path != "" {
+                path.push(GROUP_SEPARATOR);
+            }
+
+            path.push_str(component);
+
+            if !res.contains(&path) {
+                res.push(path.clone());
+            }
+        }
+    }
+
+    res.sort();
+
+    return res;
+}
+
+pub const FIELD_USER_NAME: &str = "User name";
+pub const FIELD_PASSWORD: &str = "Password";
+pub const FIELD_URL: &str = "URL";
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct KvEntry {
     #[serde(rename(deserialize = "Key"))]
     #[serde(rename(serialize = "Key"))]    
     pub key: String,
-    #[serde(rename(deserialize = "Text"))]
-    #[serde(rename(serialize = "Text"))]    
-    pub value: String 
+    #[serde(flatten)]
+    pub value: Entry
 }
 
 impl KvEntry {
-    pub fn new(k: &String, v: &String) -> KvEntry {
+    pub fn new(k: &String, v: &Entry) -> KvEntry {
         return KvEntry {
             key: k.clone(),
             value: v.clone()
@@ -49,6 +402,36 @@ impl KvEntry {
     }
 }
 
+// An entry which has been deleted. Entries in the trash can be restored or purged.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TrashItem {
+    #[serde(rename(deserialize = "Key"))]
+    #[serde(rename(serialize = "Key"))]
+    pub key: String,
+    #[serde(rename(deserialize = "Deleted"))]
+    #[serde(rename(serialize = "Deleted"))]
+    pub deleted: Timestamp,
+    #[serde(flatten)]
+    pub value: Entry
+}
+
+// The payload was a plain list of entries before the trash existed. This format is still used as long as
+// the trash is empty in order to stay compatible with older versions.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+enum Payload {
+    Entries(Vec<KvEntry>),
+    WithTrash {
+        #[serde(rename(deserialize = "Entries"))]
+        #[serde(rename(serialize = "Entries"))]
+        entries: Vec<KvEntry>,
+        #[serde(rename(deserialize = "Trash"))]
+        #[serde(rename(serialize = "Trash"))]
+        #[serde(default)]
+        trash: Vec<TrashItem>
+    }
+}
+
 pub struct JotsIter<'a> {
     all_keys: Vec<&'a String>,
     current_pos: usize,
@@ -81,13 +464,38 @@ impl<'a> Iterator for JotsIter<'a> {
 }
 
 pub struct Jots {
-    pub contents: HashMap<String, String>,
+    pub contents: HashMap<String, Entry>,
     pub kdf: KeyDeriver,
     pub kdf_id: fcrypt::KdfId,
+    // Cost parameters used when encrypting. They are replaced by the parameters of a file when it is read.
+    pub kdf_params: KdfParams,
+    // The cipher named in the header of the file which was read. If None the cipher is chosen by cr_gen.
+    pub cipher_id: Option<CipherId>,
+    // Hash of a key file which is used as a second factor in addition to the password
+    pub key_file: Option<SecretBytes>,
+    // Key slots of the file which was read. If present the payload is encrypted with the data key from the slots.
+    pub key_slots: Option<KeySlots>,
+    // If not empty the store is encrypted for these public keys instead of a password
+    pub recipients: Vec<RecipientKey>,
+    // Private key which is used to open files that are encrypted for recipients
+    pub private_key: Option<PrivateKey>,
+    // Format which is used when writing. It is replaced by the format of a file when it is read.
+    pub container_format: ContainerFormat,
     pub dirty: bool,
-    pub undoer: UndoRepo<String, String>,
+    pub trash: Vec<TrashItem>,
+    // Number of days after which entries are removed from the trash when saving. 0 means never.
+    pub trash_retention_days: u64,
+    // The entries as they were when the store was last read from or written to storage. This is used as
+    // the common base when merging with the stored version.
+    pub sync_base: HashMap<String, Entry>,
+    // SHA-256 hash of the encrypted bytes which were last read from or written to storage. If the stored bytes
+    // differ from this hash someone else has changed the stored version.
+    pub stored_hash: Option<Vec<u8>>,
+    pub undoer: UndoRepo<Jots>,
     pub cr_gen: CryptorGen,
-    pub backup_cb: Option<BackupCallback>
+    pub backup_cb: Option<BackupCallback>,
+    // If present all changes are additionally written to an encrypted journal until the store is saved
+    pub journal: Option<journal::Journal>
 }
 
 impl Jots {
@@ -96,10 +504,22 @@ impl Jots {
             contents: HashMap::new(),
             kdf: d,
             kdf_id: kdf_id,
+            kdf_params: kdf_id.default_params(),
+            cipher_id: None,
+            key_file: None,
+            key_slots: None,
+            recipients: Vec::new(),
+            private_key: None,
+            container_format: ContainerFormat::Json,
             dirty: false,
-            undoer: UndoRepo::<String, String>::new(),
+            trash: Vec::new(),
+            trash_retention_days: 0,
+            sync_base: HashMap::new(),
+            stored_hash: None,
+            undoer: UndoRepo::<Jots>::new(),
             cr_gen: g,
-            backup_cb: None
+            backup_cb: None,
+            journal: None
         };
     }
 
@@ -107,6 +527,174 @@ impl Jots {
         return Jots::new(d, kdf_id, g);
     }
 
+    fn make_cryptor(&self) -> Box<dyn Cryptor> {
+        let mut ctx = match self.cipher_id {
+            Some(c) => c.make(self.kdf, self.kdf_id),
+            None => (self.cr_gen)(self.kdf, self.kdf_id)
+        };
+        ctx.set_kdf_params(self.kdf_params);
+        ctx.set_key_file(self.key_file.clone());
+        ctx.set_container_format(self.container_format);
+
+        if self.recipients.len() > 0 {
+            return Box::new(RecipientCryptor::new(ctx, self.recipients.clone(), self.private_key.clone()));
+        }
+
+        ctx.set_key_slots(self.key_slots.clone());
+
+        return ctx;
+    }
+
+    // Old files do not name the cipher in their header. For these the cipher of this store is used.
+    fn make_cryptor_for(&self, raw_data: &Vec<u8>) -> Box<dyn Cryptor> {
+        let recipients = fcrypt::recipients_from_header(raw_data);
+
+        let ctx = match fcrypt::cipher_from_header(raw_data) {
+            Some(c) => {
+                let mut ctx = c.make(self.kdf, self.kdf_id);
+                ctx.set_key_file(self.key_file.clone());
+                ctx
+            },
+            None => self.make_cryptor()
+        };
+
+        if recipients.len() > 0 {
+            return Box::new(RecipientCryptor::new(ctx, recipients, self.private_key.clone()));
+        }
+
+        return ctx;
+    }
+
+    // Uses cipher, KDF, KDF parameters and format of the file which was read through ctx for all further writes
+    fn adopt_crypto(&mut self, ctx: &Box<dyn Cryptor>) {
+        self.cipher_id = Some(ctx.cipher_id());
+        (self.kdf, self.kdf_id) = ctx.kdf();
+        self.kdf_params = ctx.kdf_params();
+        self.key_slots = ctx.key_slots();
+        self.recipients = ctx.recipients();
+        self.container_format = ctx.container_format();
+    }
+
+    // false means the parameters can not be used with the KDF of this store
+    pub fn set_kdf_params(&mut self, params: KdfParams) -> bool {
+        if params.check(self.kdf_id).is_some() {
+            return false;
+        }
+
+        self.kdf_params = params;
+
+        return true;
+    }
+
+    // Cipher, KDF and KDF parameters which are used the next time this store is saved. false means the
+    // parameters can not be used with the given KDF. In this case nothing is changed.
+    pub fn rekey(&mut self, cipher_id: CipherId, kdf_id: KdfId, params: KdfParams) -> bool {
+        if params.check(kdf_id).is_some() {
+            return false;
+        }
+
+        self.cipher_id = Some(cipher_id);
+        (self.kdf, self.kdf_id) = kdf_id.to_named_func();
+        self.kdf_params = params;
+
+        return true;
+    }
+
+    // Replaces a deprecated KDF by Argon2id and keeps the cipher. The store is marked as modified, i.e. it is
+    // reencrypted the next time it is saved.
+    pub fn upgrade_kdf(&mut self, params: KdfParams) -> bool {
+        let cipher_id = match self.cipher_id {
+            Some(c) => c,
+            None => (self.cr_gen)(self.kdf, self.kdf_id).cipher_id()
+        };
+
+        if !self.rekey(cipher_id, KdfId::Argon2, params) {
+            return false;
+        }
+
+        self.dirty = true;
+
+        return true;
+    }
+
+    // Adds a slot which allows to open the file with another password. A store which does not use key slots yet is
+    // converted the next time it is saved. The current password then unlocks the slot DEFAULT_KEY_SLOT.
+    pub fn add_key_slot(&mut self, label: &str, password: &str, kdf_id: KdfId, params: KdfParams) -> std::io::Result<()> {
+        if let Some(e) = params.check(kdf_id) {
+            return Err(e);
+        }
+
+        if let Some(e) = fcrypt::check_password(password, kdf_id) {
+            return Err(e);
+        }
+
+        if label.trim().len() == 0 {
+            return Err(Error::new(ErrorKind::Other, "Label of key slot is empty"));
+        }
+
+        if self.recipients.len() > 0 {
+            return Err(Error::new(ErrorKind::Other, "Files which are encrypted for public keys do not use key slots"));
+        }
+
+        let mut slots = match &self.key_slots {
+            Some(s) => s.clone(),
+            None => KeySlots::new(DEFAULT_KEY_SLOT, self.key_file.is_some())
+        };
+
+        if slots.labels().iter().any(|l| l == label) {
+            return Err(Error::new(ErrorKind::Other, format!("Key slot '{}' already exists", label)));
+        }
+
+        let cipher_id = match self.cipher_id {
+            Some(c) => c,
+            None => (self.cr_gen)(self.kdf, self.kdf_id).cipher_id()
+        };
+
+        slots.wrap(label, password, &self.key_file, cipher_id, kdf_id, params)?;
+        self.key_slots = Some(slots);
+
+        return Ok(());
+    }
+
+    pub fn revoke_key_slot(&mut self, label: &str) -> std::io::Result<()> {
+        return match &mut self.key_slots {
+            Some(s) => s.revoke(label),
+            None => Err(Error::new(ErrorKind::Other, "File does not use key slots"))
+        };
+    }
+
+    // Adds a key slot or replaces the password of an existing slot
+    pub fn replace_key_slot(&mut self, label: &str, password: &str, kdf_id: KdfId, params: KdfParams) -> std::io::Result<()> {
+        if self.key_slot_labels().iter().any(|l| l == label) {
+            self.revoke_key_slot(label)?;
+        }
+
+        return self.add_key_slot(label, password, kdf_id, params);
+    }
+
+    // The recovery key slot is unlocked by a random secret which is split into shares by fcrypt::split_recovery_secret.
+    // An existing recovery slot is replaced, i.e. the shares which were created before become useless. Stores which
+    // require a key file are refused, because the key file is needed for all slots and the shares alone would not suffice.
+    pub fn set_recovery_secret(&mut self, secret: &SecretBytes, kdf_id: KdfId, params: KdfParams) -> std::io::Result<()> {
+        let key_file_required = match &self.key_slots {
+            Some(s) => s.key_file_required,
+            None => self.key_file.is_some()
+        };
+
+        if key_file_required {
+            return Err(Error::new(ErrorKind::Other, "Recovery shares can not be used for files which require a key file"));
+        }
+
+        return self.replace_key_slot(fcrypt::RECOVERY_KEY_SLOT, &fcrypt::recovery_password(secret), kdf_id, params);
+    }
+
+    pub fn key_slot_labels(&self) -> Vec<String> {
+        return match &self.key_slots {
+            Some(s) => s.labels(),
+            None => Vec::new()
+        };
+    }
+
     pub fn is_dirty(&self) -> bool {
         return self.dirty;
     }
@@ -114,22 +702,214 @@ impl Jots {
     pub fn mark_as_clean(&mut self) {
         self.dirty = false;
         self.undoer.clear();
+        self.sync_base = self.contents.clone();
+
+        if let Some(j) = &mut self.journal {
+            j.ops.clear();
+            journal::remove(&j.file_name);
+        }
+    }
+
+    // The journal key is derived once with the KDF and cipher of the store and then used for all changes
+    fn make_journal_cryptor(&self, password: &str) -> std::io::Result<fcrypt::RecordCryptor> {
+        let cipher_id = self.make_cryptor().cipher_id();
+
+        return fcrypt::RecordCryptor::new(cipher_id, self.kdf_id, self.kdf_params, password, &self.key_file);
+    }
+
+    // The journal key is derived from the password. Stores which are opened with a private key or only with a key
+    // file do not have a real password, so their journal would be readable by anyone who has the key file or even
+    // by everyone. No journal is written for these stores.
+    pub fn journal_possible(&self, password: &str) -> bool {
+        return (password.len() != 0) && self.recipients.is_empty() && self.private_key.is_none();
+    }
+
+    // Starts to record all changes in the given journal file. false means that no journal is written for this store.
+    pub fn enable_journal(&mut self, file_name: &str, password: &str) -> std::io::Result<bool> {
+        if !self.journal_possible(password) {
+            self.journal = None;
+            return Ok(false);
+        }
+
+        let cryptor = self.make_journal_cryptor(password)?;
+        self.journal = Some(journal::Journal::new(file_name, cryptor));
+
+        return Ok(true);
+    }
+
+    pub fn discard_journal(&mut self) {
+        if let Some(j) = self.journal.take() {
+            journal::remove(&j.file_name);
+        }
+    }
+
+    pub fn set_journal_password(&mut self, password: &str) -> std::io::Result<()> {
+        if self.journal.is_none() {
+            return Ok(());
+        }
+
+        if !self.journal_possible(password) {
+            self.discard_journal();
+            return Ok(());
+        }
+
+        let cryptor = self.make_journal_cryptor(password)?;
+
+        return match &mut self.journal {
+            Some(j) => j.set_cryptor(cryptor),
+            None => Ok(())
+        };
+    }
+
+    fn record(&mut self, op: JournalOp) {
+        if let Some(j) = &mut self.journal {
+            // ignore result. The journal is only a safety net and failing to write it must not prevent any change.
+            _ = j.record(op);
+        }
+    }
+
+    fn record_snapshot(&mut self) {
+        if self.journal.is_none() {
+            return;
+        }
+
+        // Nothing has to be replayed if all changes have been undone
+        if !self.dirty {
+            if let Some(j) = &mut self.journal {
+                j.ops.clear();
+                journal::remove(&j.file_name);
+            }
+
+            return;
+        }
+
+        let op = JournalOp::Snapshot { entries: self.contents.clone(), trash: self.trash.clone() };
+        self.record(op);
+    }
+
+    // Applies all changes recorded in the given journal file. The replayed changes can be undone as a whole.
+    // Returns the number of replayed changes.
+    pub fn replay_journal(&mut self, file_name: &str, password: &str) -> std::io::Result<usize> {
+        let ops = journal::read(file_name, password, &self.key_file)?;
+        let mut new_contents = self.contents.clone();
+        let mut new_trash = self.trash.clone();
+
+        for op in &ops {
+            if !op.apply(&mut new_contents, &mut new_trash) {
+                return Err(Error::new(ErrorKind::Other, "Journal does not fit to the data file"));
+            }
+        }
+
+        if ops.len() == 0 {
+            return Ok(0);
+        }
+
+        let redo_contents = new_contents.clone();
+        let redo_trash = new_trash.clone();
+        let old_contents = std::mem::replace(&mut self.contents, new_contents);
+        let old_trash = std::mem::replace(&mut self.trash, new_trash);
+        self.dirty = true;
+
+        let msg = format!("Replay {} change(s) from journal", ops.len());
+
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.contents = old_contents.clone();
+            s.trash = old_trash.clone();
+
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            s.contents = redo_contents.clone();
+            s.trash = redo_trash.clone();
+
+            return true;
+        }));
+
+        self.record_snapshot();
+
+        return Ok(ops.len());
+    }
+
+    // Merges the changes made in the remote version since both versions have been synchronized with the changes made in this store
+    pub fn merge_with(&self, remote_contents: &HashMap<String, Entry>, remote_trash: &Vec<TrashItem>) -> merge::MergeResult {
+        return merge::merge(&self.sync_base, &self.contents, remote_contents, &self.trash, remote_trash);
+    }
+
+    // Replaces all entries by a completely resolved merge result. As the changes of the remote version are now
+    // contained in this store the remote version becomes the new base for further merges.
+    // false means applying the merge result has failed
+    pub fn apply_merge(&mut self, result: merge::MergeResult, remote_contents: &HashMap<String, Entry>, remote_hash: &Option<Vec<u8>>) -> bool {
+        if !result.is_resolved() {
+            return false;
+        }
+
+        let new_contents = result.merged.clone();
+        let new_trash = result.trash.clone();
+        let new_base = remote_contents.clone();
+        let old_contents = std::mem::replace(&mut self.contents, result.merged);
+        let old_trash = std::mem::replace(&mut self.trash, result.trash);
+        let old_base = std::mem::replace(&mut self.sync_base, remote_contents.clone());
+        let new_hash = remote_hash.clone();
+        let old_hash = std::mem::replace(&mut self.stored_hash, remote_hash.clone());
+        self.dirty = true;
+
+        let msg = String::from("Merge with other version");
+
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.contents = old_contents.clone();
+            s.trash = old_trash.clone();
+            s.sync_base = old_base.clone();
+            s.stored_hash = old_hash.clone();
+
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            s.contents = new_contents.clone();
+            s.trash = new_trash.clone();
+            s.sync_base = new_base.clone();
+            s.stored_hash = new_hash.clone();
+
+            return true;
+        }));
+
+        self.record_snapshot();
+
+        return true;
     }
 
     pub fn len(&self) -> usize {
         return self.contents.len();
     }
 
-    pub fn from_reader<T: Read>(&mut self, r: T) -> std::io::Result<()> {
+    fn read_payload<T: Read>(r: T) -> std::io::Result<(HashMap<String, Entry>, Vec<TrashItem>)> {
         let reader = BufReader::new(r);
-        let raw_struct: Vec<KvEntry> = serde_json::from_reader(reader)?;
+        let payload: Payload = serde_json::from_reader(reader)?;
 
-        self.contents.clear();
+        let (raw_struct, trash) = match payload {
+            Payload::Entries(e) => (e, Vec::new()),
+            Payload::WithTrash { entries, trash } => (entries, trash)
+        };
+
+        let mut contents: HashMap<String, Entry> = HashMap::new();
     
-        for i in raw_struct {
-            self.contents.insert(i.key, i.value);
+        for mut i in raw_struct {
+            i.value.group = normalize_group(&i.value.group);
+
+            if i.value.id.is_empty() {
+                i.value.id = legacy_entry_id(&i.key, i.value.created);
+            }
+
+
+            contents.insert(i.key, i.value);
         }
 
+        return Ok((contents, trash));
+    }
+
+    pub fn from_reader<T: Read>(&mut self, r: T) -> std::io::Result<()> {
+        let (contents, trash) = Jots::read_payload(r)?;
+
+        self.contents = contents;
+        self.trash = trash;
+
         return Ok(());
     }
 
@@ -141,16 +921,38 @@ impl Jots {
             raw_data.push(KvEntry::new(i.0, i.1));
         }
 
-        serde_json::to_writer_pretty(writer, &raw_data)?;
+        let payload = if self.trash.len() == 0 {
+            Payload::Entries(raw_data)
+        } else {
+            Payload::WithTrash { entries: raw_data, trash: self.trash.clone() }
+        };
+
+        serde_json::to_writer_pretty(writer, &payload)?;
 
         return Ok(());
     }
 
     pub fn print(&self) {
-        (&self.contents).iter().for_each(|i| {println!("{}: {}", i.0, i.1);} );
+        (&self.contents).iter().for_each(|i| {println!("{}: {}", i.0, i.1.to_formatted_string());} );
     }    
 
-    fn insert_int(&mut self, k: &String, v: &String) {
+    // Returns the names of all entries whose secrets have not changed for at least the given number of days together with
+    // the time of the last change. Entries for which this time is unknown are always contained. Oldest entries come first.
+    pub fn get_stale_entries(&self, now: Timestamp, days: u64) -> Vec<(String, Timestamp)> {
+        let mut res: Vec<(String, Timestamp)> = Vec::new();
+
+        for (k, v) in &self.contents {
+            if timestamp::is_unknown(&v.secret_changed) || (timestamp::days_between(v.secret_changed, now) >= days) {
+                res.push((k.clone(), v.secret_changed));
+            }
+        }
+
+        res.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+
+        return res;
+    }
+
+    fn insert_int(&mut self, k: &String, v: &Entry) {
         self.contents.insert(k.clone(), v.clone());
         self.dirty = true;
     }
@@ -160,117 +962,505 @@ impl Jots {
         self.dirty = true;
     }
 
+    // Replaces the free text of an entry. Structured fields are left unchanged.
     pub fn modify(&mut self, k: &String, v: &String) {
-        let old_value = match self.get(k) {
+        let mut new_value = match self.get_entry(k) {
             Some(o) => o,
             None => return
         };
 
-        self.insert_int(k, v);
+        new_value.text = v.clone();
+        self.modify_entry(k, &new_value);
+    }
 
+    pub fn modify_entry(&mut self, k: &String, v: &Entry) {
         let msg = format!("Modify entry '{}'", k);
+        self.modify_entry_with_comment(k, v, &msg);
+    }
+
+    // false means move has failed
+    pub fn move_to_group(&mut self, k: &String, group: &str) -> bool {
+        let mut new_value = match self.get_entry(k) {
+            Some(o) => o,
+            None => return false
+        };
+
+        new_value.group = normalize_group(group);
+
+        let msg = if new_value.group == "" {
+            format!("Move entry '{}' to top level", k)
+        } else {
+            format!("Move entry '{}' to folder '{}'", k, &new_value.group)
+        };
+
+        self.modify_entry_with_comment(k, &new_value, &msg);
+
+        return true;
+    }
+
+    // false means setting the tags has failed
+    pub fn set_tags(&mut self, k: &String, tags: &Vec<String>) -> bool {
+        let mut new_value = match self.get_entry(k) {
+            Some(o) => o,
+            None => return false
+        };
+
+        if !tags.iter().all(|t| tagfilter::is_valid_tag(t)) {
+            return false;
+        }
+
+        let mut new_tags = tags.clone();
+        new_tags.sort();
+        new_tags.dedup();
+        new_value.tags = new_tags;
+
+        let msg = format!("Change tags of entry '{}'", k);
+        self.modify_entry_with_comment(k, &new_value, &msg);
+
+        return true;
+    }
+
+    // Returns all tags which are in use in sorted order
+    pub fn get_tags(&self) -> Vec<String> {
+        let mut res: Vec<String> = Vec::new();
+
+        for i in self.contents.values() {
+            for t in &i.tags {
+                if !res.contains(t) {
+                    res.push(t.clone());
+                }
+            }
+        }
+
+        res.sort();
+
+        return res;
+    }
+
+    // Returns all group paths which are in use including all their parent groups in sorted order
+    pub fn get_groups(&self) -> Vec<String> {
+        return collect_groups(self.contents.values().map(|i| &i.group));
+    }
+
+    // The error describes why the attachment could not be added
+    pub fn add_attachment(&mut self, k: &String, name: &str, data: &Vec<u8>) -> std::io::Result<()> {
+        let mut new_value = match self.get_entry(k) {
+            Some(o) => o,
+            None => return Err(Error::new(ErrorKind::Other, format!("Entry '{}' does not exist", k)))
+        };
+
+        let name = name.trim();
+
+        if name == "" {
+            return Err(Error::new(ErrorKind::Other, "Name of attachment is empty"));
+        }
+
+        if new_value.attachments.iter().any(|a| a.name == name) {
+            return Err(Error::new(ErrorKind::Other, format!("An attachment named '{}' already exists", name)));
+        }
+
+        new_value.attachments.push(Attachment {
+            name: String::from(name),
+            added: timestamp::now(),
+            data: data.clone()
+        });
+
+        let msg = format!("Attach '{}' to entry '{}'", name, k);
+        self.modify_entry_with_comment(k, &new_value, &msg);
+
+        return Ok(());
+    }
+
+    // false means deleting the attachment has failed
+    pub fn delete_attachment(&mut self, k: &String, name: &str) -> bool {
+        let mut new_value = match self.get_entry(k) {
+            Some(o) => o,
+            None => return false
+        };
+
+        let len_before = new_value.attachments.len();
+        new_value.attachments.retain(|a| a.name != name);
+
+        if new_value.attachments.len() == len_before {
+            return false;
+        }
+
+        let msg = format!("Delete attachment '{}' of entry '{}'", name, k);
+        self.modify_entry_with_comment(k, &new_value, &msg);
+
+        return true;
+    }
+
+    pub fn get_attachment(&self, k: &String, name: &str) -> Option<Vec<u8>> {
+        let v = match self.contents.get(k) {
+            None => { return None },
+            Some(val) => val
+        };
+
+        return v.attachments.iter().find(|a| a.name == name).map(|a| a.data.clone());
+    }
+
+    // Restores the history item with the given index. The current value becomes the newest history item.
+    // false means restore has failed
+    pub fn restore_version(&mut self, k: &String, index: usize) -> bool {
+        let mut new_value = match self.get_entry(k) {
+            Some(o) => o,
+            None => return false
+        };
+
+        let item = match new_value.history.get(index) {
+            Some(i) => i.clone(),
+            None => return false
+        };
+
+        new_value.set_contents_from(&item.value);
+
+        let msg = format!("Restore version of entry '{}' from {}", k, timestamp::format(item.timestamp));
+        self.modify_entry_with_comment(k, &new_value, &msg);
+
+        return true;
+    }
+
+    // The previous value is added to the history of the entry if its contents change
+    fn modify_entry_with_comment(&mut self, k: &String, v: &Entry, msg: &String) {
+        let old_value = match self.get_entry(k) {
+            Some(o) => o,
+            None => return
+        };
+
+        let now = timestamp::now();
+        let mut new_value = v.clone();
+        new_value.id = old_value.id.clone();
+        new_value.history = old_value.history.clone();
+        new_value.created = old_value.created;
+        new_value.modified = old_value.modified;
+        new_value.secret_changed = old_value.secret_changed;
+
+        if new_value != old_value {
+            new_value.modified = now;
+        }
+
+        if new_value.secret_differs(&old_value) {
+            new_value.secret_changed = now;
+        }
+
+        if !old_value.same_contents(v) {
+            new_value.push_history(&old_value, now);
+        }
+
+        self.insert_int(k, &new_value);
+        self.record(JournalOp::Modify { key: k.clone(), value: new_value.clone() });
+
         let old_key = k.clone();
+        let new_key = k.clone();
 
-        self.undoer.push(&msg, Box::new(move |s: &mut HashMap<String, String>| -> bool {
-            s.insert(old_key.clone(), old_value.clone());
+        self.undoer.push(msg, Box::new(move |s: &mut Jots| -> bool {
+            s.contents.insert(old_key.clone(), old_value.clone());
     
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            s.contents.insert(new_key.clone(), new_value.clone());
+
             return true;
         }));
     }
 
     pub fn delete(&mut self, k: &String) {
-        let old_value = match self.get(k) {
+        let old_value = match self.get_entry(k) {
             Some(o) => o,
             None => return
         };
 
+        let deleted = timestamp::now();
+
         self.remove_int(k);
+        self.trash.push(TrashItem { key: k.clone(), deleted: deleted, value: old_value.clone() });
+        self.record(JournalOp::Delete { key: k.clone(), deleted: deleted });
 
         let msg = format!("Delete entry '{}'", k);
         let old_key = k.clone();
+        let trash_item = TrashItem { key: k.clone(), deleted: deleted, value: old_value.clone() };
 
-        self.undoer.push(&msg, Box::new(move |s: &mut HashMap<String, String>| -> bool {
-            s.insert(old_key.clone(), old_value.clone());
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.contents.insert(old_key.clone(), old_value.clone());
+
+            if let Some(pos) = s.trash.iter().rposition(|t| (t.key == old_key) && (t.deleted == deleted)) {
+                s.trash.remove(pos);
+            }
     
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            s.contents.remove(&trash_item.key);
+            s.trash.push(trash_item.clone());
+
+            return true;
+        }));
+    }
+
+    // Moves the entry with the given index in the trash back into the store. false means restore has failed, which
+    // happens if an entry with the same name exists.
+    pub fn restore_from_trash(&mut self, index: usize) -> bool {
+        let item = match self.trash.get(index) {
+            Some(i) => i.clone(),
+            None => return false
+        };
+
+        if self.entry_exists(&item.key) {
+            return false;
+        }
+
+        let mut restored_item = item.clone();
+
+        // A merge may have brought in another entry with the same id in the meantime
+        if self.id_in_use(&restored_item.value.id) {
+            restored_item.value.id = new_entry_id();
+        }
+
+        self.trash.remove(index);
+        self.insert_int(&restored_item.key, &restored_item.value);
+        self.record_snapshot();
+
+        let msg = format!("Restore entry '{}' from trash", &item.key);
+
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.contents.remove(&item.key);
+            s.trash.insert(index, item.clone());
+
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            if index >= s.trash.len() {
+                return false;
+            }
+
+            s.trash.remove(index);
+            s.contents.insert(restored_item.key.clone(), restored_item.value.clone());
+
+            return true;
+        }));
+
+        return true;
+    }
+
+    // false means purge has failed
+    pub fn purge_from_trash(&mut self, index: usize) -> bool {
+        if index >= self.trash.len() {
+            return false;
+        }
+
+        let item = self.trash.remove(index);
+        self.dirty = true;
+        self.record_snapshot();
+
+        let msg = format!("Purge entry '{}' from trash", &item.key);
+
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.trash.insert(index, item.clone());
+
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            if index >= s.trash.len() {
+                return false;
+            }
+
+            s.trash.remove(index);
+
             return true;
         }));
+
+        return true;
     }
 
+    // Permanently removes all entries from the trash which were deleted at least the given number of days ago.
+    // Returns the number of purged entries.
+    pub fn purge_trash_older_than(&mut self, now: Timestamp, days: u64) -> usize {
+        let old_trash = self.trash.clone();
+        self.trash.retain(|t| timestamp::days_between(t.deleted, now) < days);
+
+        let num_purged = old_trash.len() - self.trash.len();
+
+        if num_purged == 0 {
+            return 0;
+        }
+
+        self.dirty = true;
+        self.record_snapshot();
+
+        let msg = format!("Purge {} entries from trash", num_purged);
+        let new_trash = self.trash.clone();
+
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.trash = old_trash.clone();
+
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            s.trash = new_trash.clone();
+
+            return true;
+        }));
+
+        return num_purged;
+    }
+
+    fn purge_expired_trash(&mut self) {
+        if self.trash_retention_days != 0 {
+            let _ = self.purge_trash_older_than(timestamp::now(), self.trash_retention_days);
+        }
+    }
+
+    // Returns the free text of an entry
     pub fn get(&self, k: &String) -> Option<String> {
         let v = match self.contents.get(k) {
             None => { return None },
             Some(val) => val
         };
 
+        return Some(v.text.clone());
+    }
+
+    pub fn get_entry(&self, k: &String) -> Option<Entry> {
+        let v = match self.contents.get(k) {
+            None => { return None },
+            Some(val) => val
+        };
+
         return Some(v.clone());
-    }  
+    }
+
+    // Returns the free text and all structured fields of an entry as one string
+    pub fn get_formatted(&self, k: &String) -> Option<String> {
+        let v = match self.contents.get(k) {
+            None => { return None },
+            Some(val) => val
+        };
+
+        return Some(v.to_formatted_string());
+    }
 
     // false means add has failed
     pub fn add(&mut self, k: &String, v: &String) -> bool {
+        return self.add_entry(k, &Entry::new(v));
+    }
+
+    // false means add has failed
+    pub fn add_entry(&mut self, k: &String, v: &Entry) -> bool {
+        let now = timestamp::now();
+        let mut new_value = v.clone();
+        new_value.created = now;
+        new_value.modified = now;
+        new_value.secret_changed = now;
+
+        if new_value.id.is_empty() || self.id_in_use(&new_value.id) {
+            new_value.id = new_entry_id();
+        }
+
         // Check for entry with the given name. It must not exist.
-        let res = match self.get(k) {
+        let res = match self.get_entry(k) {
             None => {
-                self.insert_int(k, v);
+                self.insert_int(k, &new_value);
                 true
             },
             _ => return false // Entry already exists
         };
 
+        self.record(JournalOp::Add { key: k.clone(), value: new_value.clone() });
+
         let msg = format!("Add entry '{}'", k);
         let old_key = k.clone();
+        let new_key = k.clone();
 
-        self.undoer.push(&msg, Box::new(move |s: &mut HashMap<String, String>| -> bool {
-            s.remove(&old_key);
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.contents.remove(&old_key);
     
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            s.contents.insert(new_key.clone(), new_value.clone());
+
             return true;
         }));
 
         return res;
     }
 
+    fn id_in_use(&self, id: &String) -> bool {
+        return self.contents.values().any(|e| e.id == *id);
+    }
+
     pub fn entry_exists(&self, k: &String) -> bool {
-        match self.get(k) {
+        match self.get_entry(k) {
             None => false,
             Some(_) => true,
         }
     }
 
     pub fn undo(&mut self) -> (String, bool) {
-        let res = self.undoer.undo_one(&mut self.contents);
+        // The undo functions need access to the whole store which also owns the undo repository
+        let mut undoer = std::mem::replace(&mut self.undoer, UndoRepo::<Jots>::new());
+        let res = undoer.undo_one(self);
+        self.undoer = undoer;
 
         if res.1 {
             self.dirty = !self.undoer.is_all_undone();
+            self.record_snapshot();
         }
 
         return res;
     }
 
+    pub fn redo(&mut self) -> (String, bool) {
+        let mut undoer = std::mem::replace(&mut self.undoer, UndoRepo::<Jots>::new());
+        let res = undoer.redo_one(self);
+        self.undoer = undoer;
+
+        if res.1 {
+            self.dirty = !self.undoer.is_all_undone();
+            self.record_snapshot();
+        }
+
+        return res;
+    }
+
+    pub fn can_redo(&self) -> bool {
+        return !self.undoer.is_all_redone();
+    }
+
     // false means rename has failed
     pub fn rename(&mut self, k_old: &String, k_new: &String) -> bool {
         // Check if entry k_old exists. It has to exist.
-        let contents = match self.get(k_old) {
+        let contents = match self.get_entry(k_old) {
             None => { return false; },
             Some(c) => c,
         };
 
+        let mut new_value = contents.clone();
+        new_value.modified = timestamp::now();
+
         // Check if entry k_new exists. It must not exist.
-        let res = match self.get(k_new) {
+        let res = match self.get_entry(k_new) {
             None => {
                 self.remove_int(k_old);
-                self.insert_int(k_new, &contents);
+                self.insert_int(k_new, &new_value);
                 true
             },
             _ => return false
         };
 
+        self.record(JournalOp::Rename { old_key: k_old.clone(), new_key: k_new.clone(), value: new_value.clone() });
+
         let msg = format!("Rename entry '{}' to '{}'", k_old, k_new);
         let old_key = k_old.clone();
         let new_key = k_new.clone();
+        let redo_old_key = k_old.clone();
+        let redo_new_key = k_new.clone();
 
-        self.undoer.push(&msg, Box::new(move |s: &mut HashMap<String, String>| -> bool {
-            s.remove(&new_key);
-            s.insert(old_key.clone(), contents.clone());
+        self.undoer.push(&msg, Box::new(move |s: &mut Jots| -> bool {
+            s.contents.remove(&new_key);
+            s.contents.insert(old_key.clone(), contents.clone());
     
+            return true;
+        }), Box::new(move |s: &mut Jots| -> bool {
+            s.contents.remove(&redo_old_key);
+            s.contents.insert(redo_new_key.clone(), new_value.clone());
+
             return true;
         }));  
 
@@ -278,24 +1468,28 @@ impl Jots {
     }
 
     pub fn from_enc_file(&mut self, file_name: &str, password: &str) -> std::io::Result<()> {
-        let mut ctx = (self.cr_gen)(self.kdf, self.kdf_id);
+        let raw_data = std::fs::read(file_name)?;
+        let mut ctx = self.make_cryptor_for(&raw_data);
 
-        let data = ctx.from_file(file_name)?;
+        let data = ctx.from_dyn_reader(&mut raw_data.as_slice())?;
         let plain_data = match ctx.decrypt(password, &data) {
             Err(e) => { return Err(Error::new(ErrorKind::Other, format!("{:?}", e))); },
             Ok(d) => d
         };
 
         self.from_reader(plain_data.as_slice())?;
+        self.adopt_crypto(&ctx);
         self.mark_as_clean();
+        self.stored_hash = Some(Jots::hash_stored(&raw_data));
 
         return Ok(());
     }
 
     pub fn retrieve(&mut self, p: &mut SendSyncPersister, password: &str) -> std::io::Result<()> {
-        let mut ctx = (self.cr_gen)(self.kdf, self.kdf_id);
+        let raw_data = *p.retrieve()?;
+        let mut ctx = self.make_cryptor_for(&raw_data);
 
-        let (data, raw_data) = ctx.retrieve(p)?;
+        let data = ctx.from_dyn_reader(&mut raw_data.as_slice())?;
 
         if let Some(cb) = self.backup_cb {
             // ignore result
@@ -308,44 +1502,110 @@ impl Jots {
         };
 
         self.from_reader(plain_data.as_slice())?;
+        self.adopt_crypto(&ctx);
         self.mark_as_clean();
+        self.stored_hash = Some(Jots::hash_stored(&raw_data));
 
         return Ok(());
     }
 
+    // Reads the stored version without changing this store, e.g. in order to merge it. The hash of the stored bytes is
+    // returned in addition to the entries and the trash.
+    pub fn retrieve_other(&self, p: &mut SendSyncPersister, password: &str) -> std::io::Result<(HashMap<String, Entry>, Vec<TrashItem>, Vec<u8>)> {
+        let raw_data = *p.retrieve()?;
+        let plain_data = self.decrypt_other(&raw_data, password)?;
+        let (contents, trash) = Jots::read_payload(plain_data.as_slice())?;
+
+        return Ok((contents, trash, Jots::hash_stored(&raw_data)));
+    }
+
+    fn hash_stored(raw_data: &[u8]) -> Vec<u8> {
+        return Sha256::digest(raw_data).to_vec();
+    }
+
+    // Returns true if the stored bytes differ from the ones this store has last read, written or merged with, i.e.
+    // someone else has changed the stored version and saving would discard these changes. No password is needed
+    // for this check.
+    pub fn stored_file_changed(&self, p: &mut SendSyncPersister) -> std::io::Result<bool> {
+        if !p.does_exist()? {
+            return Ok(false);
+        }
+
+        let raw_data = *p.retrieve()?;
+
+        return Ok(self.stored_hash != Some(Jots::hash_stored(&raw_data)));
+    }
+
+    // Decrypts an encrypted file with the key file and private key of this store. The plaintext is not parsed.
+    pub fn decrypt_other(&self, raw_data: &Vec<u8>, password: &str) -> std::io::Result<SecretBytes> {
+        let mut ctx = self.make_cryptor_for(raw_data);
+
+        let data = ctx.from_dyn_reader(&mut raw_data.as_slice())?;
+
+        return match ctx.decrypt(password, &data) {
+            Err(e) => Err(Error::new(ErrorKind::Other, format!("{:?}", e))),
+            Ok(d) => Ok(d)
+        };
+    }
+
+    // Checks that a decrypted payload can be read and returns the number of entries and trash items it contains
+    pub fn check_payload<T: Read>(r: T) -> std::io::Result<(usize, usize)> {
+        let (contents, trash) = Jots::read_payload(r)?;
+
+        return Ok((contents.len(), trash.len()));
+    }
+
     pub fn to_enc_file(&mut self, file_name: &str, password: &str) -> std::io::Result<()> {
-        let mut ctx = (self.cr_gen)(self.kdf, self.kdf_id);
-        let mut serialized: Vec<u8> = Vec::new();
+        let mut ctx = self.make_cryptor();
+        let mut serialized = SecretBytes::new(Vec::new());
 
-        self.to_writer(&mut serialized)?;
+        self.purge_expired_trash();
+        self.to_writer(&mut *serialized)?;
         let enc_data = match ctx.encrypt(password, &serialized) {
             Err(e) => { return Err(Error::new(ErrorKind::Other, format!("{:?}", e))); },
             Ok(d) => d
         };
 
-        ctx.to_file(&enc_data, file_name)?;
+        let mut raw_data: Vec<u8> = vec![];
+        ctx.to_dyn_writer(&mut raw_data, &enc_data)?;
+        std::fs::write(file_name, &raw_data)?;
         self.mark_as_clean();
+        self.stored_hash = Some(Jots::hash_stored(&raw_data));
 
         return Ok(());
     }
 
     pub fn persist(&mut self, p: &mut SendSyncPersister, password: &str) -> std::io::Result<()> {
-        let mut ctx = (self.cr_gen)(self.kdf, self.kdf_id);
-        let mut serialized: Vec<u8> = Vec::new();
+        let mut ctx = self.make_cryptor();
+        let mut serialized = SecretBytes::new(Vec::new());
 
-        self.to_writer(&mut serialized)?;
+        self.purge_expired_trash();
+        self.to_writer(&mut *serialized)?;
         let enc_data = match ctx.encrypt(password, &serialized) {
             Err(e) => { return Err(Error::new(ErrorKind::Other, format!("{:?}", e))); },
             Ok(d) => d
         };
 
-        ctx.persist(&enc_data, p)?;
+        let mut raw_data: Vec<u8> = vec![];
+        ctx.to_dyn_writer(&mut raw_data, &enc_data)?;
+        p.persist(&raw_data)?;
         self.mark_as_clean();
+        self.stored_hash = Some(Jots::hash_stored(&raw_data));
 
         return Ok(());
     }    
 }
 
+// Decrypted entries are overwritten when the store is dropped. Copies, which are for instance held by the undo
+// repository, are not affected by this.
+impl Drop for Jots {
+    fn drop(&mut self) {
+        self.contents.values_mut().for_each(|e| e.zeroize());
+        self.sync_base.values_mut().for_each(|e| e.zeroize());
+        self.trash.iter_mut().for_each(|t| t.value.zeroize());
+    }
+}
+
 impl<'a> IntoIterator for &'a Jots {
     type Item = &'a String;
     type IntoIter = JotsIter<'a>;
@@ -0,0 +1,239 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::io::{Error, ErrorKind};
+use crate::jots::{Entry, TrashItem};
+use crate::timestamp::Timestamp;
+use crate::fcrypt::RecordCryptor;
+use crate::persist::FilePersister;
+use crate::secret::{SecretBytes, SecretString};
+
+pub const JOURNAL_SUFFIX: &str = ".journal";
+
+// A change which has not been saved yet. Modify contains the complete new value of the entry. All changes
+// which are not simple operations on a single entry, e.g. undo or a merge, are recorded as a snapshot of
+// the complete state.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "Op")]
+pub enum JournalOp {
+    Add {
+        #[serde(rename = "Key")]
+        key: String,
+        #[serde(rename = "Value")]
+        value: Entry
+    },
+    Modify {
+        #[serde(rename = "Key")]
+        key: String,
+        #[serde(rename = "Value")]
+        value: Entry
+    },
+    Delete {
+        #[serde(rename = "Key")]
+        key: String,
+        #[serde(rename = "Deleted")]
+        deleted: Timestamp
+    },
+    Rename {
+        #[serde(rename = "OldKey")]
+        old_key: String,
+        #[serde(rename = "NewKey")]
+        new_key: String,
+        #[serde(rename = "Value")]
+        value: Entry
+    },
+    Snapshot {
+        #[serde(rename = "Entries")]
+        entries: HashMap<String, Entry>,
+        #[serde(rename = "Trash")]
+        trash: Vec<TrashItem>
+    }
+}
+
+impl JournalOp {
+    // false means the operation does not fit to the given state
+    pub fn apply(&self, contents: &mut HashMap<String, Entry>, trash: &mut Vec<TrashItem>) -> bool {
+        match self {
+            JournalOp::Add { key, value } => {
+                if contents.contains_key(key) {
+                    return false;
+                }
+
+                contents.insert(key.clone(), value.clone());
+            },
+            JournalOp::Modify { key, value } => {
+                if !contents.contains_key(key) {
+                    return false;
+                }
+
+                contents.insert(key.clone(), value.clone());
+            },
+            JournalOp::Delete { key, deleted } => {
+                let value = match contents.remove(key) {
+                    Some(v) => v,
+                    None => return false
+                };
+
+                trash.push(TrashItem { key: key.clone(), deleted: *deleted, value: value });
+            },
+            JournalOp::Rename { old_key, new_key, value } => {
+                if contents.contains_key(new_key) || contents.remove(old_key).is_none() {
+                    return false;
+                }
+
+                contents.insert(new_key.clone(), value.clone());
+            },
+            JournalOp::Snapshot { entries, trash: t } => {
+                *contents = entries.clone();
+                *trash = t.clone();
+            }
+        }
+
+        return true;
+    }
+}
+
+// The journal is written to the local file system next to the data file. If the data file is accessed via
+// WebDAV the store id may be a URL and only its last component is used.
+pub fn file_name_for(store_id: &str) -> String {
+    let base_name = match store_id.find("://") {
+        Some(_) => store_id.rsplit('/').next().unwrap_or(store_id),
+        None => store_id
+    };
+
+    return format!("{}{}", base_name, JOURNAL_SUFFIX);
+}
+
+pub fn exists(file_name: &str) -> bool {
+    return fs::metadata(file_name).is_ok();
+}
+
+pub fn remove(file_name: &str) {
+    // ignore result as the journal may not exist
+    let _ = fs::remove_file(file_name);
+}
+
+// Where and with which key the journal of a store is written. The journal file consists of a header followed by
+// one encrypted line per change. Single changes are appended to the file. Snapshots replace all previous changes
+// and therefore cause the file to be written again.
+pub struct Journal {
+    pub file_name: String,
+    cryptor: RecordCryptor,
+    pub ops: Vec<JournalOp>
+}
+
+impl Journal {
+    pub fn new(file_name: &str, cryptor: RecordCryptor) -> Journal {
+        return Journal {
+            file_name: String::from(file_name),
+            cryptor: cryptor,
+            ops: Vec::new()
+        };
+    }
+
+    // A snapshot makes all previous operations obsolete
+    pub fn record(&mut self, op: JournalOp) -> std::io::Result<()> {
+        let is_snapshot = match op {
+            JournalOp::Snapshot { .. } => true,
+            _ => false
+        };
+
+        if is_snapshot {
+            self.ops.clear();
+        }
+
+        self.ops.push(op);
+
+        if is_snapshot || (self.ops.len() == 1) || !exists(&self.file_name) {
+            return self.write();
+        }
+
+        // A failed append may have left a partial line. Writing the whole file again removes it.
+        return self.append(self.ops.len() - 1).or_else(|_| self.write());
+    }
+
+    // Encrypts all recorded operations with the new key
+    pub fn set_cryptor(&mut self, cryptor: RecordCryptor) -> std::io::Result<()> {
+        self.cryptor = cryptor;
+
+        if self.ops.len() == 0 {
+            return Ok(());
+        }
+
+        return self.write();
+    }
+
+    fn encrypt_op(&self, index: usize) -> std::io::Result<String> {
+        let serialized = SecretBytes::new(serde_json::to_vec(&self.ops[index])?);
+
+        return self.cryptor.seal(index, &serialized);
+    }
+
+    fn append(&self, index: usize) -> std::io::Result<()> {
+        let line = format!("{}\n", self.encrypt_op(index)?);
+        let mut file = OpenOptions::new().append(true).open(&self.file_name)?;
+
+        file.write_all(line.as_bytes())?;
+        file.sync_all()?;
+
+        return Ok(());
+    }
+
+    // The file is replaced in the same way as the data file, i.e. it is never left in a partially written state
+    fn write(&self) -> std::io::Result<()> {
+        let mut data = format!("{}\n", self.cryptor.header());
+
+        for i in 0..self.ops.len() {
+            data.push_str(&self.encrypt_op(i)?);
+            data.push('\n');
+        }
+
+        let mut p = FilePersister::new(&self.file_name);
+
+        return p.persist(&data.into_bytes());
+    }
+}
+
+// Reads all operations from a journal file. A last line which is incomplete stems from an append which has been
+// interrupted and is ignored.
+pub fn read(file_name: &str, password: &str, key_file: &Option<SecretBytes>) -> std::io::Result<Vec<JournalOp>> {
+    let raw_data = SecretString::new(fs::read_to_string(file_name)?);
+    let complete = match raw_data.rfind('\n') {
+        Some(pos) => &raw_data[..pos],
+        None => return Err(Error::new(ErrorKind::Other, "Journal is empty"))
+    };
+
+    let mut lines = complete.split('\n');
+
+    let header = match lines.next() {
+        Some(h) => h,
+        None => return Err(Error::new(ErrorKind::Other, "Journal is empty"))
+    };
+
+    let cryptor = RecordCryptor::from_header(header, password, key_file)?;
+    let mut res: Vec<JournalOp> = Vec::new();
+
+    for (index, line) in lines.enumerate() {
+        let plain_data = cryptor.open(index, line)?;
+        res.push(serde_json::from_slice(&plain_data)?);
+    }
+
+    return Ok(res);
+}
@@ -25,6 +25,12 @@ mod undo;
 mod persist;
 mod obfuscate;
 mod theme;
+mod tagfilter;
+mod timestamp;
+mod diff;
+mod merge;
+mod journal;
+mod secret;
 
 #[cfg(feature = "webdav")]
 mod webdav;
@@ -49,9 +55,12 @@ use std::fs::File;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::{Error, ErrorKind};
+use std::io::IsTerminal;
 use pwgen::GenerationStrategy;
 use obfuscate::de_obfuscate;
 use obfuscate::obfuscate;
+use std::sync::{Arc, Mutex};
+use secret::SecretString;
 
 pub const VERSION_STRING: &'static str = env!("CARGO_PKG_VERSION");
 const COMMAND_ENCRYPT: &str = "enc";
@@ -60,12 +69,55 @@ const COMMAND_GUI: &str = "gui";
 const COMMAND_CONFIG: &str = "cfg";
 const COMMAND_GENERATE: &str = "gen";
 const COMMAND_OBFUSCATE: &str = "obf";
+const COMMAND_AGE_REPORT: &str = "age";
+const COMMAND_MERGE: &str = "merge";
+const COMMAND_REKEY: &str = "rekey";
+const COMMAND_KEY_SLOT: &str = "slot";
+const COMMAND_KEYGEN: &str = "keygen";
+const COMMAND_VERIFY: &str = "verify";
+const COMMAND_BENCH: &str = "bench";
+const COMMAND_SHARE: &str = "share";
+const COMMAND_RECOVER: &str = "recover";
 const ARG_INPUT_FILE: &str = "inputfile";
 const ARG_OUTPUT_FILE: &str = "outputfile";
 const ARG_CONFIG_FILE: &str = "cfgfile";
 const ARG_KDF: &str = "kdf";
 const ARG_CIPHER: &str = "cipher";
 const ARG_EXPORT: &str = "backup";
+const ARG_DAYS: &str = "days";
+const ARG_KDF_PARAMS: &str = "kdfparams";
+const ARG_BASE_FILE: &str = "basefile";
+const ARG_REMOTE_FILE: &str = "remotefile";
+const ARG_NEW_PASSWORD: &str = "newpw";
+const ARG_KEY_FILE: &str = "keyfile";
+const ARG_ADD_SLOT: &str = "add";
+const ARG_REVOKE_SLOT: &str = "revoke";
+const ARG_RECIPIENT: &str = "recipient";
+const ARG_PRIVATE_KEY: &str = "privatekey";
+const ARG_LABEL: &str = "label";
+const ARG_FORMAT: &str = "format";
+const ARG_FORCE: &str = "force";
+const ARG_HEADER_ONLY: &str = "headeronly";
+const ARG_TARGET: &str = "target";
+const ARG_MAX_MEM: &str = "maxmem";
+const ARG_WRITE_CONFIG: &str = "write";
+const DEFAULT_BENCH_TARGET_MS: u64 = 1000;
+const DEFAULT_BENCH_MAX_MEM_MIB: u32 = 1024;
+const ARG_SHARES: &str = "shares";
+const ARG_THRESHOLD: &str = "threshold";
+const ARG_QR: &str = "qr";
+const ARG_SHARE: &str = "share";
+const ARG_PW_FILE: &str = "pwfile";
+const DEFAULT_SHARES: u8 = 5;
+const DEFAULT_THRESHOLD: u8 = 3;
+
+// Exit codes of the verify command
+const EXIT_VERIFY_OK: i32 = 0;
+const EXIT_VERIFY_ERROR: i32 = 1;
+const EXIT_VERIFY_HEADER: i32 = 2;
+const EXIT_VERIFY_DECRYPTION: i32 = 3;
+const EXIT_VERIFY_PAYLOAD: i32 = 4;
+const EXIT_VERIFY_NO_PASSWORD: i32 = 5;
 #[cfg(not(feature = "chacha20"))]
 const SINGLE_CIPHER_DEFAULT: CipherId = CipherId::Aes256Gcm;
 #[cfg(feature = "chacha20")]
@@ -91,7 +143,10 @@ struct RustPwMan {
     copy_command: String,
     webdav_user: String,
     webdav_pw: String,
-    webdav_server: String
+    webdav_server: String,
+    trash_days: u64,
+    argon2_params: String,
+    scrypt_params: String
 }
 
 #[allow(unused_variables)]
@@ -134,6 +189,9 @@ impl RustPwMan {
             webdav_user: String::from(""),
             webdav_pw: String::from(""),
             webdav_server: String::from(""),
+            trash_days: 0,
+            argon2_params: String::from(""),
+            scrypt_params: String::from(""),
         }
     }
 
@@ -202,6 +260,9 @@ impl RustPwMan {
         self.webdav_user = loaded_config.webdav_user;
         self.webdav_pw = loaded_config.webdav_pw;
         self.webdav_server = loaded_config.webdav_server;
+        self.trash_days = loaded_config.trash_days;
+        self.argon2_params = loaded_config.argon2_params;
+        self.scrypt_params = loaded_config.scrypt_params;
     }
 
     fn str_to_gen_strategy(&self, strategy_name: &str) -> GenerationStrategy {
@@ -228,6 +289,70 @@ impl RustPwMan {
         }
     }
 
+    // Returns the KDF parameters which are used for new files. Parameters from the config file are only
+    // used if they fit to the selected KDF.
+    fn default_kdf_params(&self) -> fcrypt::KdfParams {
+        let configured = match self.default_deriver_id {
+            fcrypt::KdfId::Argon2 => &self.argon2_params,
+            fcrypt::KdfId::Scrypt => &self.scrypt_params,
+            fcrypt::KdfId::Sha256 => return self.default_deriver_id.default_params()
+        };
+
+        return match fcrypt::KdfParams::from_str(configured) {
+            Some(p) if p.check(self.default_deriver_id).is_none() => p,
+            _ => self.default_deriver_id.default_params()
+        };
+    }
+
+    fn set_kdf_params_from_command_line(&mut self, matches: &clap::ArgMatches) -> std::io::Result<()> {
+        let a: Option<&String> = matches.get_one(ARG_KDF_PARAMS);
+
+        let params_str = match a {
+            Some(p) => p.clone(),
+            None => return Ok(())
+        };
+
+        let params = match fcrypt::KdfParams::from_str(&params_str) {
+            Some(p) => p,
+            None => return Err(Error::new(ErrorKind::Other, "KDF parameters have to be of the form m,t,p"))
+        };
+
+        if let Some(e) = params.check(self.default_deriver_id) {
+            return Err(e);
+        }
+
+        match self.default_deriver_id {
+            fcrypt::KdfId::Argon2 => self.argon2_params = params.to_string(),
+            fcrypt::KdfId::Scrypt => self.scrypt_params = params.to_string(),
+            fcrypt::KdfId::Sha256 => ()
+        }
+
+        return Ok(());
+    }
+
+    // The key file is optional. If it is given its hash is used together with the password.
+    fn set_key_file_from_command_line(matches: &clap::ArgMatches, store: &mut jots::Jots) -> std::io::Result<()> {
+        let a: Option<&String> = matches.get_one(ARG_KEY_FILE);
+
+        if let Some(file_name) = a {
+            store.key_file = Some(fcrypt::key_file_hash(file_name)?);
+        }
+
+        return Ok(());
+    }
+
+    // Returns true if a private key was given. In this case no password is needed.
+    fn set_private_key_from_command_line(matches: &clap::ArgMatches, store: &mut jots::Jots) -> std::io::Result<bool> {
+        let a: Option<&String> = matches.get_one(ARG_PRIVATE_KEY);
+
+        if let Some(file_name) = a {
+            store.private_key = Some(fcrypt::read_private_key(file_name)?);
+            return Ok(true);
+        }
+
+        return Ok(false);
+    }
+
     fn set_pbkdf_from_command_line(&mut self, matches: &clap::ArgMatches) {
         if RustPwMan::is_option_present(matches, ARG_KDF) {
             let a: Option<&String> = matches.get_one(ARG_KDF);
@@ -244,6 +369,22 @@ impl RustPwMan {
         }
     }
 
+    // New files are only created with a deprecated KDF if this is forced on the command line
+    fn check_deprecated_kdf(kdf_id: fcrypt::KdfId, matches: &clap::ArgMatches) -> bool {
+        if kdf_id.is_deprecated() && !matches.get_flag(ARG_FORCE) {
+            eprintln!("The KDF {} is deprecated. Use --{} if you really want to use it.", kdf_id.to_str(), ARG_FORCE);
+            return false;
+        }
+
+        return true;
+    }
+
+    fn warn_if_deprecated(jots_file: &jots::Jots) {
+        if jots_file.kdf_id.is_deprecated() && (jots_file.recipients.len() == 0) {
+            eprintln!("Warning: The file uses the deprecated KDF {}. Use the rekey command to switch to {}.", jots_file.kdf_id.to_str(), fcrypt::KdfId::Argon2.to_str());
+        }
+    }
+
     fn determine_in_out_files(matches: &clap::ArgMatches) -> (String, String) {
         let in_f: Option<&String> = matches.get_one(ARG_INPUT_FILE);
         let out_f: Option<&String> = matches.get_one(ARG_OUTPUT_FILE);
@@ -261,15 +402,16 @@ impl RustPwMan {
         return (file_name_in, file_name_out);
     }
 
-    fn enter_password_verified() -> std::io::Result<String> {
-        let pw1 = rpassword::prompt_password("Password: ")?;
-        let pw2 = rpassword::prompt_password("Verfication: ")?;
+    // The password is checked against the limit of the KDF which is used with it
+    fn enter_password_verified(kdf_id: fcrypt::KdfId) -> std::io::Result<SecretString> {
+        let pw1 = SecretString::new(rpassword::prompt_password("Password: ")?);
+        let pw2 = SecretString::new(rpassword::prompt_password("Verfication: ")?);
 
         if pw1 != pw2 {
             return Err(Error::new(ErrorKind::Other, "Passwords differ"));
         }
 
-        match fcrypt::check_password(&pw1) {
+        match fcrypt::check_password(&pw1, kdf_id) {
             Some(e) => return Err(e),
             None => ()
         }
@@ -277,16 +419,72 @@ impl RustPwMan {
         return Ok(pw1);
     }
 
+    fn enter_password() -> std::io::Result<SecretString> {
+        let pw = SecretString::new(rpassword::prompt_password("Password: ")?);
+
+        match fcrypt::check_existing_password(&pw) {
+            Some(e) => return Err(e),
+            None => ()
+        }
+
+        return Ok(pw);
+    }
+
+    // Reads the password from the first line of the given file. The file name - stands for stdin.
+    fn read_password_file(file_name: &str) -> std::io::Result<SecretString> {
+        let content = if file_name == "-" {
+            let mut line = SecretString::new(String::new());
+            std::io::stdin().read_line(&mut line)?;
+            line
+        } else {
+            SecretString::new(std::fs::read_to_string(file_name)?)
+        };
+
+        let pw = content.lines().next().unwrap_or("");
+
+        return Ok(SecretString::from(pw));
+    }
+
     fn perform_encrypt_command(&mut self, encrypt_matches: &clap::ArgMatches) {
         self.set_pbkdf_from_command_line(encrypt_matches);
+
+        if let Err(e) = self.set_kdf_params_from_command_line(encrypt_matches) {
+            eprintln!("{}", e);
+            return;
+        }
+
         let (file_in, file_out) = RustPwMan::determine_in_out_files(encrypt_matches);
 
-        let pw = match RustPwMan::enter_password_verified() {
-            Err(e) => {
-                eprintln!("Error reading password: {:?}", e);
-                return;
-            },
-            Ok(p) => p
+        let mut recipients: Vec<fcrypt::RecipientKey> = Vec::new();
+
+        if let Some(files) = encrypt_matches.get_many::<String>(ARG_RECIPIENT) {
+            for f in files {
+                match fcrypt::read_public_key(f) {
+                    Ok(k) => recipients.push(k),
+                    Err(e) => {
+                        eprintln!("Error reading public key {}. {:?}", f, e);
+                        return;
+                    }
+                }
+            }
+        }
+
+        // Files for recipients do not derive their key from a password, i.e. the KDF does not matter
+        if (recipients.len() == 0) && !RustPwMan::check_deprecated_kdf(self.default_deriver_id, encrypt_matches) {
+            return;
+        }
+
+        // Files for recipients are not protected by a password
+        let pw = if recipients.len() > 0 {
+            SecretString::from("")
+        } else {
+            match RustPwMan::enter_password_verified(self.default_deriver_id) {
+                Err(e) => {
+                    eprintln!("Error reading password: {:?}", e);
+                    return;
+                },
+                Ok(p) => p
+            }
         };
 
         let a: Option<&String> = encrypt_matches.get_one(ARG_CIPHER);
@@ -300,6 +498,17 @@ impl RustPwMan {
         });
 
         let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+        jots_file.kdf_params = self.default_kdf_params();
+        jots_file.recipients = recipients;
+
+        if let Some(f) = encrypt_matches.get_one::<String>(ARG_FORMAT) {
+            jots_file.container_format = fcrypt::ContainerFormat::from_str(f).unwrap(); // clap only allows known formats
+        }
+
+        if let Err(e) = RustPwMan::set_key_file_from_command_line(encrypt_matches, &mut jots_file) {
+            eprintln!("Error reading key file. {:?}", e);
+            return;
+        }
 
         let file = match File::open(&file_in) {
             Ok(f) => f,
@@ -344,49 +553,840 @@ impl RustPwMan {
 
         let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
 
-        let pw = match rpassword::prompt_password("Password: ") {
-            Err(_) => {
-                eprintln!("Error reading password");
-                return;
-            },
-            Ok(p) => p
+        if let Err(e) = RustPwMan::set_key_file_from_command_line(decrypt_matches, &mut jots_file) {
+            eprintln!("Error reading key file. {:?}", e);
+            return;
+        }
+
+        let has_private_key = match RustPwMan::set_private_key_from_command_line(decrypt_matches, &mut jots_file) {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("Error reading private key. {:?}", e);
+                return;
+            }
+        };
+
+        let mut pw = SecretString::from("");
+
+        if !has_private_key {
+            pw = match rpassword::prompt_password("Password: ") {
+                Err(_) => {
+                    eprintln!("Error reading password");
+                    return;
+                },
+                Ok(p) => SecretString::new(p)
+            };
+
+            match fcrypt::check_existing_password(&pw) {
+                Some(e) => {
+                    eprintln!("Password illegal: {:?}", e);
+                    return;
+                },
+                None => ()
+            }
+
+            println!();
+        }
+
+        match jots_file.from_enc_file(&file_in, &pw[..]) {
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            },
+            Ok(_) => ()
+        };
+
+        RustPwMan::warn_if_deprecated(&jots_file);
+
+        let file = match File::create(&file_out) {
+            Err(e) => {
+                eprintln!("Error creating file. {:?}", e);
+                return;
+            },
+            Ok(f) => f
+        };
+
+        let w = BufWriter::new(file);
+
+        match jots_file.to_writer(w) {
+            Err(e) => {
+                eprintln!("Error writing file. {:?}", e);
+                return;
+            },
+            Ok(_) => ()
+        };
+    }
+
+    fn perform_age_report_command(&mut self, report_matches: &clap::ArgMatches) {
+        self.set_pbkdf_from_command_line(report_matches);
+
+        let file_in = match report_matches.get_one::<String>(ARG_INPUT_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine input file") // Should not happen
+        };
+
+        let days = match report_matches.get_one::<u64>(ARG_DAYS) {
+            Some(d) => *d,
+            None => modtui::report::DEFAULT_MAX_AGE_DAYS
+        };
+
+        let a: Option<&String> = report_matches.get_one(ARG_CIPHER);
+        let algo_id = match a {
+            Some(s) => String::from(s.as_str()),
+            None => String::from("")
+        };
+
+        let cr_gen = Box::new(move |k: fcrypt::KeyDeriver, i: fcrypt::KdfId| -> Box<dyn fcrypt::Cryptor>  {
+            return make_cryptor(algo_id.as_str(), k, i);
+        });
+
+        let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+
+        if let Err(e) = RustPwMan::set_key_file_from_command_line(report_matches, &mut jots_file) {
+            eprintln!("Error reading key file. {:?}", e);
+            return;
+        }
+
+        let pw = match RustPwMan::enter_password() {
+            Err(e) => {
+                eprintln!("Error reading password: {:?}", e);
+                return;
+            },
+            Ok(p) => p
+        };
+
+        println!();
+
+        match jots_file.from_enc_file(&file_in, &pw[..]) {
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            },
+            Ok(_) => ()
+        };
+
+        let now = timestamp::now();
+
+        for (name, changed) in jots_file.get_stale_entries(now, days) {
+            println!("{}", modtui::report::format_report_line(&name, changed, now));
+        }
+    }
+
+    fn perform_merge_command(&mut self, merge_matches: &clap::ArgMatches) {
+        self.set_pbkdf_from_command_line(merge_matches);
+        let (file_local, file_out) = RustPwMan::determine_in_out_files(merge_matches);
+
+        let file_base = match merge_matches.get_one::<String>(ARG_BASE_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine base file") // Should not happen
+        };
+
+        let file_remote = match merge_matches.get_one::<String>(ARG_REMOTE_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine remote file") // Should not happen
+        };
+
+        let a: Option<&String> = merge_matches.get_one(ARG_CIPHER);
+        let algo_id = match a {
+            Some(s) => String::from(s.as_str()),
+            None => String::from("")
+        };
+
+        let pw = match RustPwMan::enter_password() {
+            Err(e) => {
+                eprintln!("Error reading password: {:?}", e);
+                return;
+            },
+            Ok(p) => p
+        };
+
+        println!();
+
+        let mut versions: Vec<jots::Jots> = Vec::new();
+
+        for file_name in [&file_base, &file_local, &file_remote] {
+            let h = algo_id.clone();
+            let cr_gen = Box::new(move |k: fcrypt::KeyDeriver, i: fcrypt::KdfId| -> Box<dyn fcrypt::Cryptor>  {
+                return make_cryptor(h.as_str(), k, i);
+            });
+
+            let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+
+            if let Err(e) = RustPwMan::set_key_file_from_command_line(merge_matches, &mut jots_file) {
+                eprintln!("Error reading key file. {:?}", e);
+                return;
+            }
+
+            match jots_file.from_enc_file(file_name, &pw[..]) {
+                Err(e) => {
+                    eprintln!("Error reading file {}. {:?}", file_name, e);
+                    return;
+                },
+                Ok(_) => ()
+            };
+
+            versions.push(jots_file);
+        }
+
+        let remote = versions.pop().unwrap();
+        let mut local = versions.pop().unwrap();
+        let mut base = versions.pop().unwrap();
+
+        local.sync_base = std::mem::take(&mut base.contents);
+        let merge_result = Arc::new(Mutex::new(local.merge_with(&remote.contents, &remote.trash)));
+        let num_conflicts = merge_result.lock().unwrap().conflicts.len();
+
+        if (num_conflicts > 0) && !modtui::merge::merge_main(merge_result.clone()) {
+            eprintln!("Merge cancelled");
+            return;
+        }
+
+        let merged = std::mem::replace(&mut *merge_result.lock().unwrap(), merge::MergeResult::new());
+
+        if !local.apply_merge(merged, &remote.contents, &remote.stored_hash) {
+            eprintln!("Unable to apply merge result");
+            return;
+        }
+
+        match local.to_enc_file(&file_out, &pw[..]) {
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("Error creating file. {:?}", e);
+                return;
+            },
+        };
+
+        println!("{} conflict(s) were resolved", num_conflicts);
+    }
+
+    fn perform_rekey_command(&mut self, rekey_matches: &clap::ArgMatches) {
+        let file_name = match rekey_matches.get_one::<String>(ARG_INPUT_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine input file") // Should not happen
+        };
+
+        // Files which do not name their cipher are read with the cipher from the environment
+        let cr_gen = Box::new(move |k: fcrypt::KeyDeriver, i: fcrypt::KdfId| -> Box<dyn fcrypt::Cryptor>  {
+            return make_cryptor("", k, i);
+        });
+
+        let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+
+        if let Err(e) = RustPwMan::set_key_file_from_command_line(rekey_matches, &mut jots_file) {
+            eprintln!("Error reading key file. {:?}", e);
+            return;
+        }
+
+        let pw = match RustPwMan::enter_password() {
+            Err(e) => {
+                eprintln!("Error reading password: {:?}", e);
+                return;
+            },
+            Ok(p) => p
+        };
+
+        println!();
+
+        let mut persister = persist::FilePersister::new(&file_name);
+
+        match jots_file.retrieve(&mut persister, &pw[..]) {
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            },
+            Ok(_) => ()
+        };
+
+        // Everything which is not specified on the command line is taken from the file
+        let cipher_id = match rekey_matches.get_one::<String>(ARG_CIPHER) {
+            Some(c) => CipherId::from_str(c).unwrap(), // clap only allows known ciphers
+            None => match jots_file.cipher_id {
+                Some(c) => c,
+                None => panic!("Unable to determine cipher") // Should not happen
+            }
+        };
+
+        self.default_deriver = jots_file.kdf;
+        self.default_deriver_id = jots_file.kdf_id;
+        self.set_pbkdf_from_command_line(rekey_matches);
+
+        if let Err(e) = self.set_kdf_params_from_command_line(rekey_matches) {
+            eprintln!("{}", e);
+            return;
+        }
+
+        if (self.default_deriver_id != jots_file.kdf_id) && !RustPwMan::check_deprecated_kdf(self.default_deriver_id, rekey_matches) {
+            return;
+        }
+
+        let mut kdf_params = jots_file.kdf_params;
+
+        if (self.default_deriver_id != jots_file.kdf_id) || RustPwMan::is_option_present(rekey_matches, ARG_KDF_PARAMS) {
+            kdf_params = self.default_kdf_params();
+        }
+
+        if !jots_file.rekey(cipher_id, self.default_deriver_id, kdf_params) {
+            eprintln!("KDF parameters {} can not be used with {}", kdf_params.to_string(), self.default_deriver_id.to_str());
+            return;
+        }
+
+        if let Some(f) = rekey_matches.get_one::<String>(ARG_FORMAT) {
+            jots_file.container_format = fcrypt::ContainerFormat::from_str(f).unwrap(); // clap only allows known formats
+        }
+
+        let mut new_pw = pw;
+
+        if rekey_matches.get_flag(ARG_NEW_PASSWORD) {
+            println!("Enter new password");
+
+            new_pw = match RustPwMan::enter_password_verified(self.default_deriver_id) {
+                Err(e) => {
+                    eprintln!("Error reading password: {:?}", e);
+                    return;
+                },
+                Ok(p) => p
+            };
+        }
+
+        // The current password may be too long for the new KDF
+        if let Some(e) = fcrypt::check_password(&new_pw, jots_file.kdf_id) {
+            eprintln!("{}", e);
+            return;
+        }
+
+        match jots_file.persist(&mut persister, &new_pw[..]) {
+            Ok(_) => (),
+            Err(e) => {
+                eprintln!("Error writing file. {:?}", e);
+                return;
+            },
+        };
+
+        println!("File is now encrypted with {} and {} ({}) in {} format", cipher_id.to_str(), jots_file.kdf_id.to_str(), jots_file.kdf_params.to_string(), jots_file.container_format.to_str());
+        RustPwMan::warn_if_deprecated(&jots_file);
+    }
+
+    fn perform_key_slot_command(&mut self, slot_matches: &clap::ArgMatches) {
+        let file_name = match slot_matches.get_one::<String>(ARG_INPUT_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine input file") // Should not happen
+        };
+
+        let mut persister = persist::FilePersister::new(&file_name);
+
+        let raw_data = match persister.retrieve() {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            }
+        };
+
+        // Labels are stored in plain text, i.e. no password is needed to list them
+        let labels = match fcrypt::key_slot_labels(&raw_data) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            }
+        };
+
+        let add_label = slot_matches.get_one::<String>(ARG_ADD_SLOT);
+        let revoke_label = slot_matches.get_one::<String>(ARG_REVOKE_SLOT);
+
+        if add_label.is_none() && revoke_label.is_none() {
+            if labels.len() == 0 {
+                println!("File does not use key slots");
+            }
+
+            for l in &labels {
+                println!("{}", l);
+            }
+
+            return;
+        }
+
+        // Files which do not name their cipher are read with the cipher from the environment
+        let cr_gen = Box::new(move |k: fcrypt::KeyDeriver, i: fcrypt::KdfId| -> Box<dyn fcrypt::Cryptor>  {
+            return make_cryptor("", k, i);
+        });
+
+        let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+
+        if let Err(e) = RustPwMan::set_key_file_from_command_line(slot_matches, &mut jots_file) {
+            eprintln!("Error reading key file. {:?}", e);
+            return;
+        }
+
+        let pw = match RustPwMan::enter_password() {
+            Err(e) => {
+                eprintln!("Error reading password: {:?}", e);
+                return;
+            },
+            Ok(p) => p
+        };
+
+        println!();
+
+        if let Err(e) = jots_file.retrieve(&mut persister, &pw[..]) {
+            eprintln!("Error reading file. {:?}", e);
+            return;
+        }
+
+        if let Some(label) = add_label {
+            // The new slot uses the KDF from the command line or the config file
+            self.set_pbkdf_from_command_line(slot_matches);
+
+            if let Err(e) = self.set_kdf_params_from_command_line(slot_matches) {
+                eprintln!("{}", e);
+                return;
+            }
+
+            println!("Enter password for key slot '{}'", label);
+
+            let new_pw = match RustPwMan::enter_password_verified(self.default_deriver_id) {
+                Err(e) => {
+                    eprintln!("Error reading password: {:?}", e);
+                    return;
+                },
+                Ok(p) => p
+            };
+
+            if let Err(e) = jots_file.add_key_slot(label, &new_pw[..], self.default_deriver_id, self.default_kdf_params()) {
+                eprintln!("Unable to add key slot. {}", e);
+                return;
+            }
+        }
+
+        if let Some(label) = revoke_label {
+            if let Err(e) = jots_file.revoke_key_slot(label) {
+                eprintln!("Unable to revoke key slot. {}", e);
+                return;
+            }
+        }
+
+        if let Err(e) = RustPwMan::write_key_slots(&mut jots_file, &mut persister, &raw_data, labels.len() > 0, &pw[..]) {
+            eprintln!("Error writing file. {:?}", e);
+            return;
+        }
+
+        println!("Key slots: {}", jots_file.key_slot_labels().join(", "));
+    }
+
+    // Files which already use key slots only get a new header. Other files are converted, i.e. reencrypted.
+    fn write_key_slots(jots_file: &mut jots::Jots, persister: &mut SendSyncPersister, raw_data: &Vec<u8>, had_slots: bool, pw: &str) -> std::io::Result<()> {
+        return match (had_slots, &jots_file.key_slots) {
+            (false, _) | (_, None) => jots_file.persist(persister, pw),
+            (true, Some(slots)) => {
+                match fcrypt::replace_key_slots(raw_data, slots) {
+                    Ok(d) => persister.persist(&d),
+                    Err(e) => Err(e)
+                }
+            }
+        };
+    }
+
+    fn perform_share_command(&mut self, share_matches: &clap::ArgMatches) {
+        let file_name = match share_matches.get_one::<String>(ARG_INPUT_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine input file") // Should not happen
+        };
+
+        let count = *share_matches.get_one::<u8>(ARG_SHARES).unwrap_or(&DEFAULT_SHARES);
+        let threshold = *share_matches.get_one::<u8>(ARG_THRESHOLD).unwrap_or(&DEFAULT_THRESHOLD);
+
+        if (threshold < 2) || (count < threshold) {
+            eprintln!("The threshold has to be at least 2 and must not exceed the number of shares");
+            return;
+        }
+
+        let mut persister = persist::FilePersister::new(&file_name);
+
+        let raw_data = match persister.retrieve() {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            }
+        };
+
+        let info = match fcrypt::container_info(&raw_data) {
+            Ok(i) => i,
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            }
+        };
+
+        // A key file is needed for all key slots or for none of them. The shares alone would therefore not be
+        // sufficient to open such a file.
+        if info.key_file_required {
+            eprintln!("Recovery shares can not be used for files which require a key file");
+            return;
+        }
+
+        // Files which do not name their cipher are read with the cipher from the environment
+        let cr_gen = Box::new(move |k: fcrypt::KeyDeriver, i: fcrypt::KdfId| -> Box<dyn fcrypt::Cryptor>  {
+            return make_cryptor("", k, i);
+        });
+
+        let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+
+        let pw = match RustPwMan::enter_password() {
+            Err(e) => {
+                eprintln!("Error reading password: {:?}", e);
+                return;
+            },
+            Ok(p) => p
+        };
+
+        println!();
+
+        if let Err(e) = jots_file.retrieve(&mut persister, &pw[..]) {
+            eprintln!("Error reading file. {:?}", e);
+            return;
+        }
+
+        // The recovery slot uses the KDF from the command line or the config file
+        self.set_pbkdf_from_command_line(share_matches);
+
+        if let Err(e) = self.set_kdf_params_from_command_line(share_matches) {
+            eprintln!("{}", e);
+            return;
+        }
+
+        let secret = fcrypt::generate_recovery_secret();
+
+        let shares = match fcrypt::split_recovery_secret(&secret, threshold, count) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Unable to create recovery shares. {}", e);
+                return;
+            }
+        };
+
+        if let Err(e) = jots_file.set_recovery_secret(&secret, self.default_deriver_id, self.default_kdf_params()) {
+            eprintln!("Unable to add recovery key slot. {}", e);
+            return;
+        }
+
+        if let Err(e) = RustPwMan::write_key_slots(&mut jots_file, &mut persister, &raw_data, info.key_slots.len() > 0, &pw[..]) {
+            eprintln!("Error writing file. {:?}", e);
+            return;
+        }
+
+        let as_qr = share_matches.get_flag(ARG_QR);
+
+        println!("Any {} of the following {} shares open the key slot '{}'. Give each share to a different person.", threshold, count, fcrypt::RECOVERY_KEY_SLOT);
+        println!("Shares which were created before for this file are no longer valid.");
+
+        for (i, share) in shares.iter().enumerate() {
+            println!();
+            println!("Share {} of {}", i + 1, count);
+            println!("{}", share);
+
+            if as_qr {
+                match fcrypt::share_to_qr(share) {
+                    Ok(q) => println!("{}", q),
+                    Err(e) => eprintln!("{}", e)
+                }
+            }
+        }
+    }
+
+    // Shares are read from the given files, one share per line. If no file is given they are read from stdin until an
+    // empty line is entered.
+    fn read_shares(matches: &clap::ArgMatches) -> std::io::Result<Vec<String>> {
+        let mut shares: Vec<String> = Vec::new();
+        let share_files: Vec<&String> = matches.get_many::<String>(ARG_SHARE).map(|v| v.collect()).unwrap_or_default();
+
+        for f in share_files {
+            let content = std::fs::read_to_string(f)?;
+            shares.extend(content.lines().map(|l| l.trim()).filter(|l| l.len() > 0).map(String::from));
+        }
+
+        if shares.len() > 0 {
+            return Ok(shares);
+        }
+
+        println!("Enter one share per line. Finish with an empty line.");
+
+        for line in std::io::stdin().lines() {
+            let l = String::from(line?.trim());
+
+            if l.len() == 0 {
+                break;
+            }
+
+            shares.push(l);
+        }
+
+        return Ok(shares);
+    }
+
+    fn perform_recover_command(&mut self, recover_matches: &clap::ArgMatches) {
+        let file_name = match recover_matches.get_one::<String>(ARG_INPUT_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine input file") // Should not happen
+        };
+
+        let label = match recover_matches.get_one::<String>(ARG_LABEL) {
+            Some(l) => l.clone(),
+            None => String::from(jots::DEFAULT_KEY_SLOT)
+        };
+
+        if label == fcrypt::RECOVERY_KEY_SLOT {
+            eprintln!("The recovery key slot can only be changed with the {} command", COMMAND_SHARE);
+            return;
+        }
+
+        let mut persister = persist::FilePersister::new(&file_name);
+
+        let raw_data = match persister.retrieve() {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return;
+            }
+        };
+
+        let shares = match RustPwMan::read_shares(recover_matches) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("Error reading shares. {:?}", e);
+                return;
+            }
+        };
+
+        let secret = match fcrypt::combine_shares(&shares) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("{}", e);
+                return;
+            }
+        };
+
+        // Files which do not name their cipher are read with the cipher from the environment
+        let cr_gen = Box::new(move |k: fcrypt::KeyDeriver, i: fcrypt::KdfId| -> Box<dyn fcrypt::Cryptor>  {
+            return make_cryptor("", k, i);
+        });
+
+        let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+
+        let recovery_pw = fcrypt::recovery_password(&secret);
+
+        if let Err(e) = jots_file.retrieve(&mut persister, &recovery_pw[..]) {
+            eprintln!("Unable to open file with the recovery shares. {:?}", e);
+            return;
+        }
+
+        // The new slot uses the KDF from the command line or the config file
+        self.set_pbkdf_from_command_line(recover_matches);
+
+        if let Err(e) = self.set_kdf_params_from_command_line(recover_matches) {
+            eprintln!("{}", e);
+            return;
+        }
+
+        println!("Enter new password for key slot '{}'", label);
+
+        let new_pw = match RustPwMan::enter_password_verified(self.default_deriver_id) {
+            Err(e) => {
+                eprintln!("Error reading password: {:?}", e);
+                return;
+            },
+            Ok(p) => p
+        };
+
+        println!();
+
+        if let Err(e) = jots_file.replace_key_slot(&label, &new_pw[..], self.default_deriver_id, self.default_kdf_params()) {
+            eprintln!("Unable to set key slot. {}", e);
+            return;
+        }
+
+        // A file which can be opened with the recovery shares always uses key slots
+        if let Err(e) = RustPwMan::write_key_slots(&mut jots_file, &mut persister, &raw_data, true, &recovery_pw[..]) {
+            eprintln!("Error writing file. {:?}", e);
+            return;
+        }
+
+        println!("The file can now be opened with the new password of key slot '{}'", label);
+    }
+
+    fn perform_keygen_command(&mut self, keygen_matches: &clap::ArgMatches) {
+        let file_name = match keygen_matches.get_one::<String>(ARG_OUTPUT_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine output file") // Should not happen
+        };
+
+        let label = match keygen_matches.get_one::<String>(ARG_LABEL) {
+            Some(l) => l.clone(),
+            None => file_name.clone()
+        };
+
+        if let Err(e) = fcrypt::generate_key_pair(&file_name, &label) {
+            eprintln!("Error creating key pair. {:?}", e);
+            return;
+        }
+
+        println!("Private key written to {}", file_name);
+        println!("Public key written to {}", fcrypt::public_key_file_name(&file_name));
+        println!("The private key is not encrypted. Everyone who can read {} can open all files which are encrypted for it.", file_name);
+    }
+
+    #[cfg(feature = "pwmanclient")]
+    fn cached_password(persister: &SendSyncPersister) -> Option<SecretString> {
+        return match persister.get_canonical_path() {
+            Ok(store_id) => modtui::cached_password(store_id),
+            Err(_) => None
+        };
+    }
+
+    #[cfg(not(feature = "pwmanclient"))]
+    fn cached_password(_persister: &SendSyncPersister) -> Option<SecretString> {
+        return None;
+    }
+
+    // Checks an encrypted file and returns one of the EXIT_VERIFY_* codes. The header is checked without a password.
+    // After that the file is decrypted and its contents are parsed, unless only the header is to be checked.
+    fn perform_verify_command(&mut self, verify_matches: &clap::ArgMatches) -> i32 {
+        let file_name = match verify_matches.get_one::<String>(ARG_INPUT_FILE) {
+            Some(f) => f.clone(),
+            None => panic!("Unable to determine input file") // Should not happen
+        };
+
+        let mut persister = persist::FilePersister::new(&file_name);
+
+        let raw_data = match persister.retrieve() {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Error reading file. {:?}", e);
+                return EXIT_VERIFY_ERROR;
+            }
+        };
+
+        let info = match fcrypt::container_info(&raw_data) {
+            Ok(i) => i,
+            Err(e) => {
+                eprintln!("Header invalid. {:?}", e);
+                return EXIT_VERIFY_HEADER;
+            }
+        };
+
+        let cipher_name = match info.cipher_id {
+            Some(c) => String::from(c.to_str()),
+            None => String::from("not stored in file")
+        };
+
+        let deprecated = if info.kdf_id.is_deprecated() { " (deprecated)" } else { "" };
+
+        println!("File:       {}", file_name);
+        println!("Format:     {} version {}", info.format.to_str(), info.version);
+        println!("Cipher:     {}", cipher_name);
+        println!("KDF:        {} {}{}", info.kdf_id.to_str(), info.kdf_params.to_string(), deprecated);
+        println!("Key file:   {}", if info.key_file_required { "required" } else { "not required" });
+
+        if info.key_slots.len() > 0 {
+            println!("Key slots:  {}", info.key_slots.join(", "));
+        }
+
+        if info.recipients.len() > 0 {
+            println!("Recipients: {}", info.recipients.join(", "));
+        }
+
+        if verify_matches.get_flag(ARG_HEADER_ONLY) {
+            return EXIT_VERIFY_OK;
+        }
+
+        // Files which do not name their cipher are read with the given cipher or the one from the environment
+        let algo_id = match verify_matches.get_one::<String>(ARG_CIPHER) {
+            Some(s) => String::from(s.as_str()),
+            None => String::from("")
+        };
+
+        let cr_gen = Box::new(move |k: fcrypt::KeyDeriver, i: fcrypt::KdfId| -> Box<dyn fcrypt::Cryptor>  {
+            return make_cryptor(algo_id.as_str(), k, i);
+        });
+
+        let mut jots_file = jots::Jots::new(self.default_deriver, self.default_deriver_id, cr_gen);
+
+        if let Err(e) = RustPwMan::set_key_file_from_command_line(verify_matches, &mut jots_file) {
+            eprintln!("Error reading key file. {:?}", e);
+            return EXIT_VERIFY_ERROR;
+        }
+
+        let has_private_key = match RustPwMan::set_private_key_from_command_line(verify_matches, &mut jots_file) {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("Error reading private key. {:?}", e);
+                return EXIT_VERIFY_ERROR;
+            }
         };
 
-        match fcrypt::check_password(&pw) {
-            Some(e) => {
-                eprintln!("Password illegal: {:?}", e);
-                return;
-            },
-            None => ()
+        if !has_private_key && (info.recipients.len() > 0) {
+            eprintln!("A private key is required to decrypt this file. Use --{}.", ARG_PRIVATE_KEY);
+            return EXIT_VERIFY_ERROR;
         }
 
-        println!();
+        let mut pw = SecretString::from("");
 
-        match jots_file.from_enc_file(&file_in, &pw[..]) {
+        // Scripts pass the password in a file or on stdin. A password is only requested if a terminal is present.
+        if !has_private_key {
+            let pw_source = match verify_matches.get_one::<String>(ARG_PW_FILE) {
+                Some(f) => match RustPwMan::read_password_file(f) {
+                    Ok(p) => Some(p),
+                    Err(e) => {
+                        eprintln!("Error reading password file. {:?}", e);
+                        return EXIT_VERIFY_ERROR;
+                    }
+                },
+                None => match RustPwMan::cached_password(&persister) {
+                    Some(p) => Some(p),
+                    None if std::io::stdin().is_terminal() => match RustPwMan::enter_password() {
+                        Ok(p) => {
+                            println!();
+                            Some(p)
+                        },
+                        Err(e) => {
+                            eprintln!("Error reading password. {:?}", e);
+                            return EXIT_VERIFY_ERROR;
+                        }
+                    },
+                    None => None
+                }
+            };
+
+            pw = match pw_source {
+                Some(p) if p.len() > 0 => p,
+                _ => {
+                    eprintln!("No password available. Use --{} in order to pass a password to a script.", ARG_PW_FILE);
+                    return EXIT_VERIFY_NO_PASSWORD;
+                }
+            };
+        }
+
+        let plain_data = match jots_file.decrypt_other(&raw_data, &pw[..]) {
+            Ok(d) => d,
             Err(e) => {
-                eprintln!("Error reading file. {:?}", e);
-                return;
-            },
-            Ok(_) => ()
+                eprintln!("Decryption failed. {:?}", e);
+                return EXIT_VERIFY_DECRYPTION;
+            }
         };
 
-        let file = match File::create(&file_out) {
+        let (entries, trash_items) = match jots::Jots::check_payload(plain_data.as_slice()) {
+            Ok(c) => c,
             Err(e) => {
-                eprintln!("Error creating file. {:?}", e);
-                return;
-            },
-            Ok(f) => f
+                eprintln!("Contents invalid. {:?}", e);
+                return EXIT_VERIFY_PAYLOAD;
+            }
         };
 
-        let w = BufWriter::new(file);
+        println!("Entries:    {}", entries);
+        println!("Trash:      {}", trash_items);
+        println!("OK");
 
-        match jots_file.to_writer(w) {
-            Err(e) => {
-                eprintln!("Error writing file. {:?}", e);
-                return;
-            },
-            Ok(_) => ()
-        };
+        return EXIT_VERIFY_OK;
     }
 
     #[allow(unused_variables)]
@@ -424,6 +1424,12 @@ impl RustPwMan {
     fn perform_gui_command(&mut self, gui_matches: &clap::ArgMatches) {
         self.set_pbkdf_from_command_line(gui_matches);
 
+        if let Err(e) = self.set_kdf_params_from_command_line(gui_matches) {
+            eprintln!("{}", e);
+            return;
+        }
+
+
         let a:Option<&String> = gui_matches.get_one(ARG_INPUT_FILE);
         let u = self.webdav_user.clone();
         let mut p = self.webdav_pw.clone();
@@ -458,8 +1464,22 @@ impl RustPwMan {
 
                 let persist_closure = self.make_persist_creator(&u, &p, &s, &data_file_name);
 
-                modtui::tuimain::main(data_file_name, self.default_sec_level, self.default_deriver, self.default_deriver_id,
-                                      self.default_pw_gen, self.paste_command.clone(), self.copy_command.clone(), persist_closure, cr_gen_gen, gui_matches.get_flag(ARG_EXPORT));
+                let key_file: Option<String> = gui_matches.get_one::<String>(ARG_KEY_FILE).cloned();
+
+                let private_key = match gui_matches.get_one::<String>(ARG_PRIVATE_KEY) {
+                    Some(f) => match fcrypt::read_private_key(f) {
+                        Ok(k) => Some(k),
+                        Err(e) => {
+                            eprintln!("Error reading private key. {:?}", e);
+                            return;
+                        }
+                    },
+                    None => None
+                };
+
+                modtui::tuimain::main(data_file_name, self.default_sec_level, self.default_deriver, self.default_deriver_id, self.default_kdf_params(),
+                                      self.default_pw_gen, self.paste_command.clone(), self.copy_command.clone(), persist_closure, cr_gen_gen, gui_matches.get_flag(ARG_EXPORT), self.trash_days,
+                                      key_file, private_key);
             },
             None => {
                 eprintln!("Password file name missing");
@@ -469,8 +1489,8 @@ impl RustPwMan {
     }
 
     fn perform_obfuscate_command(&mut self) {
-        let pw1 = rpassword::prompt_password("WebDAV password       : ").unwrap();
-        let pw2 = rpassword::prompt_password("Again for verification: ").unwrap();
+        let pw1 = SecretString::new(rpassword::prompt_password("WebDAV password       : ").unwrap());
+        let pw2 = SecretString::new(rpassword::prompt_password("Again for verification: ").unwrap());
 
         if pw1 != pw2 {
             eprintln!("Passwords differ");
@@ -480,36 +1500,29 @@ impl RustPwMan {
         println!("{}", obfuscate(&pw1, OBFUSCATION_ENV_VAR));
     }
 
-    fn perform_config_command(&mut self, config_matches: &clap::ArgMatches) {
-        let config_file_name: std::path::PathBuf;
-        let a: Option<&String> = config_matches.get_one(ARG_CONFIG_FILE);
+    // The config file can be given on the command line. Otherwise the default config file is used.
+    fn config_file_from_command_line(matches: &clap::ArgMatches) -> Option<std::path::PathBuf> {
+        let a: Option<&String> = matches.get_one(ARG_CONFIG_FILE);
 
-        match a {
-            Some(f_name) => {
-                config_file_name = std::path::PathBuf::from(f_name);
-            },
-            None => {
-                config_file_name = match RustPwMan::get_cfg_file_name() {
-                    Some(p) => p,
-                    None => {
-                        eprintln!("Unable to determine config file!");
-                        return;
-                    }
-                };
-            }
-        }
+        return match a {
+            Some(f_name) => Some(std::path::PathBuf::from(f_name)),
+            None => RustPwMan::get_cfg_file_name()
+        };
+    }
 
+    // Loads the config file which is to be changed. If it does not exist yet the current settings are returned.
+    fn load_config_for_update(&self, config_file_name: &std::path::PathBuf) -> Option<tomlconfig::RustPwManSerialize> {
         let mut file_was_loaded = false;
 
-        let loaded_config = match tomlconfig::load(&config_file_name, &mut file_was_loaded) {
-            Ok(c) => c,
+        return match tomlconfig::load(config_file_name, &mut file_was_loaded) {
+            Ok(c) => Some(c),
             Err(_) => {
                 if file_was_loaded {
                     eprintln!("A config file was found but it seems to be corrupt!");
-                    return;
+                    return None;
                 }
 
-                tomlconfig::RustPwManSerialize {
+                Some(tomlconfig::RustPwManSerialize {
                     seclevel: self.default_sec_level,
                     pbkdf: self.default_deriver_id.to_string(),
                     pwgen: self.default_pw_gen.to_string(),
@@ -517,17 +1530,109 @@ impl RustPwMan {
                     copy_cmd: String::from(crate::modtui::DEFAULT_COPY_CMD),
                     webdav_user: self.webdav_user.clone(),
                     webdav_pw: self.webdav_pw.clone(),
-                    webdav_server: self.webdav_server.clone()
-                }
+                    webdav_server: self.webdav_server.clone(),
+                    trash_days: self.trash_days,
+                    argon2_params: self.argon2_params.clone(),
+                    scrypt_params: self.scrypt_params.clone()
+                })
+            }
+        };
+    }
+
+    fn perform_config_command(&mut self, config_matches: &clap::ArgMatches) {
+        let config_file_name = match RustPwMan::config_file_from_command_line(config_matches) {
+            Some(p) => p,
+            None => {
+                eprintln!("Unable to determine config file!");
+                return;
             }
         };
 
+        let loaded_config = match self.load_config_for_update(&config_file_name) {
+            Some(c) => c,
+            None => return
+        };
+
         let sec_level = self.verify_sec_level(loaded_config.seclevel);
         let pw_gen_strategy = self.str_to_gen_strategy(&loaded_config.pwgen);
         let (_, pbkdf_id) = self.str_to_deriver(&loaded_config.pbkdf);
 
         tuiconfig::config_main(config_file_name, sec_level, pw_gen_strategy, pbkdf_id, &loaded_config.clip_cmd, &loaded_config.copy_cmd,
-                               &loaded_config.webdav_user, &loaded_config.webdav_pw, &loaded_config.webdav_server);
+                               &loaded_config.webdav_user, &loaded_config.webdav_pw, &loaded_config.webdav_server, loaded_config.trash_days,
+                               &loaded_config.argon2_params, &loaded_config.scrypt_params);
+    }
+
+    fn perform_bench_command(&mut self, bench_matches: &clap::ArgMatches) {
+        let target_ms = *bench_matches.get_one::<u64>(ARG_TARGET).unwrap_or(&DEFAULT_BENCH_TARGET_MS);
+        let max_mem_mib = *bench_matches.get_one::<u32>(ARG_MAX_MEM).unwrap_or(&DEFAULT_BENCH_MAX_MEM_MIB);
+        let target = std::time::Duration::from_millis(target_ms);
+
+        let kdf_ids = match bench_matches.get_one::<String>(ARG_KDF) {
+            Some(k) => vec![self.str_to_deriver(k).1],
+            None => fcrypt::KdfId::get_known_ids()
+        };
+
+        println!("Target time {} ms, memory limit {} MiB", target_ms, max_mem_mib);
+
+        let mut results: Vec<fcrypt::Calibration> = Vec::new();
+
+        for kdf_id in kdf_ids {
+            println!();
+            println!("{}", kdf_id.to_str());
+
+            let c = fcrypt::calibrate(kdf_id, target, max_mem_mib.saturating_mul(1024));
+
+            for (params, d) in &c.measurements {
+                println!("  {:<16} {:>6} ms", params.to_string(), d.as_millis());
+            }
+
+            if kdf_id.is_deprecated() {
+                println!("  Deprecated. No parameters can be set.");
+                continue;
+            }
+
+            if let Some(d) = c.recommended_duration() {
+                if d > target {
+                    println!("  Even the default parameters take longer than the target time");
+                }
+            }
+
+            println!("  Recommended: {}", c.recommended.to_string());
+            results.push(c);
+        }
+
+        if !bench_matches.get_flag(ARG_WRITE_CONFIG) {
+            return;
+        }
+
+        let config_file_name = match RustPwMan::config_file_from_command_line(bench_matches) {
+            Some(p) => p,
+            None => {
+                eprintln!("Unable to determine config file!");
+                return;
+            }
+        };
+
+        let mut config = match self.load_config_for_update(&config_file_name) {
+            Some(c) => c,
+            None => return
+        };
+
+        for c in &results {
+            match c.kdf_id {
+                fcrypt::KdfId::Argon2 => config.argon2_params = c.recommended.to_string(),
+                fcrypt::KdfId::Scrypt => config.scrypt_params = c.recommended.to_string(),
+                fcrypt::KdfId::Sha256 => ()
+            }
+        }
+
+        if let Some(e) = tomlconfig::save(&config_file_name, config) {
+            eprintln!("Error writing config file. {:?}", e);
+            return;
+        }
+
+        println!();
+        println!("Recommended parameters written to {}", config_file_name.display());
     }
 
     fn perform_generate_command(&mut self) {
@@ -551,6 +1656,50 @@ pub fn add_kdf_param() -> clap::Arg {
     return arg.value_parser(possible_values);
 }
 
+pub fn add_kdf_params_param() -> clap::Arg {
+    return Arg::new(ARG_KDF_PARAMS)
+        .long(ARG_KDF_PARAMS)
+        .required(false)
+        .num_args(1)
+        .help("KDF cost parameters for new files of the form m,t,p");
+}
+
+pub fn add_key_file_param() -> clap::Arg {
+    return Arg::new(ARG_KEY_FILE)
+        .long(ARG_KEY_FILE)
+        .required(false)
+        .num_args(1)
+        .help("Key file which is needed in addition to the password");
+}
+
+pub fn add_private_key_param() -> clap::Arg {
+    return Arg::new(ARG_PRIVATE_KEY)
+        .long(ARG_PRIVATE_KEY)
+        .required(false)
+        .num_args(1)
+        .help("Private key file which is used instead of a password. It is not protected by a password");
+}
+
+pub fn add_force_param() -> clap::Arg {
+    return Arg::new(ARG_FORCE)
+        .long(ARG_FORCE)
+        .required(false)
+        .action(ArgAction::SetTrue)
+        .help("Allow the use of a deprecated KDF");
+}
+
+pub fn add_format_param() -> clap::Arg {
+    let arg = Arg::new(ARG_FORMAT)
+        .long(ARG_FORMAT)
+        .required(false)
+        .num_args(1)
+        .help("Format of the encrypted file. The binary format is smaller than JSON.");
+
+    let possible_values: Vec<&str> = fcrypt::ContainerFormat::get_known_ids().iter().map(|f| f.to_str()).collect();
+
+    return arg.value_parser(possible_values);
+}
+
 pub fn add_cipher_param() -> clap::Arg {
     let arg = Arg::new(ARG_CIPHER)
         .long(ARG_CIPHER)
@@ -590,7 +1739,17 @@ fn main() {
                     .num_args(1)
                     .help("Encrypted output file"))
                 .arg(add_kdf_param())
-                .arg(add_cipher_param()))
+                .arg(add_kdf_params_param())
+                .arg(add_cipher_param())
+                .arg(add_format_param())
+                .arg(add_force_param())
+                .arg(add_key_file_param())
+                .arg(Arg::new(ARG_RECIPIENT)
+                    .long(ARG_RECIPIENT)
+                    .required(false)
+                    .num_args(1)
+                    .action(ArgAction::Append)
+                    .help("Public key file of a recipient. Can be repeated. No password is used in this case.")))
         .subcommand(
             Command::new(COMMAND_DECRYPT)
                 .about("Decrypt file")
@@ -607,7 +1766,9 @@ fn main() {
                     .num_args(1)
                     .help("Name of plaintext file"))
                 .arg(add_kdf_param())
-                .arg(add_cipher_param()))
+                .arg(add_cipher_param())
+                .arg(add_key_file_param())
+                .arg(add_private_key_param()))
         .subcommand(
             Command::new(COMMAND_GUI)
                 .about("Open file in TUI")
@@ -618,7 +1779,10 @@ fn main() {
                     .num_args(1)
                     .help("Name of encrypted data file"))
                 .arg(add_kdf_param())
+                .arg(add_kdf_params_param())
                 .arg(add_cipher_param())
+                .arg(add_key_file_param())
+                .arg(add_private_key_param())
                 .arg(Arg::new(ARG_EXPORT)
                     .long(ARG_EXPORT)
                     .required(false)
@@ -637,7 +1801,227 @@ fn main() {
                 .about("Generate passwords"))
         .subcommand(
             Command::new(COMMAND_OBFUSCATE)
-                .about("Obfuscate WebDAV password")
+                .about("Obfuscate WebDAV password"))
+        .subcommand(
+            Command::new(COMMAND_AGE_REPORT)
+                .about("List entries whose secrets have not changed for a given number of days")
+                .arg(Arg::new(ARG_INPUT_FILE)
+                    .short('i')
+                    .long(ARG_INPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted data file"))
+                .arg(Arg::new(ARG_DAYS)
+                    .short('d')
+                    .long(ARG_DAYS)
+                    .required(false)
+                    .num_args(1)
+                    .value_parser(clap::value_parser!(u64))
+                    .default_value("365")
+                    .help("Minimum number of days since the last change of a secret"))
+                .arg(add_kdf_param())
+                .arg(add_cipher_param())
+                .arg(add_key_file_param()))
+        .subcommand(
+            Command::new(COMMAND_MERGE)
+                .about("Merge two versions of a data file which were derived from a common base version")
+                .arg(Arg::new(ARG_BASE_FILE)
+                    .short('b')
+                    .long(ARG_BASE_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted common base version"))
+                .arg(Arg::new(ARG_INPUT_FILE)
+                    .short('i')
+                    .long(ARG_INPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted local version"))
+                .arg(Arg::new(ARG_REMOTE_FILE)
+                    .short('r')
+                    .long(ARG_REMOTE_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted remote version"))
+                .arg(Arg::new(ARG_OUTPUT_FILE)
+                    .short('o')
+                    .long(ARG_OUTPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted merge result"))
+                .arg(add_kdf_param())
+                .arg(add_cipher_param())
+                .arg(add_key_file_param()))
+        .subcommand(
+            Command::new(COMMAND_REKEY)
+                .about("Reencrypt a data file with another cipher, KDF or password")
+                .arg(Arg::new(ARG_INPUT_FILE)
+                    .short('i')
+                    .long(ARG_INPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted data file"))
+                .arg(Arg::new(ARG_NEW_PASSWORD)
+                    .short('n')
+                    .long(ARG_NEW_PASSWORD)
+                    .required(false)
+                    .action(ArgAction::SetTrue)
+                    .help("Also change the password"))
+                .arg(add_kdf_param())
+                .arg(add_kdf_params_param())
+                .arg(add_cipher_param())
+                .arg(add_format_param())
+                .arg(add_force_param())
+                .arg(add_key_file_param()))
+        .subcommand(
+            Command::new(COMMAND_KEY_SLOT)
+                .about("List, add or revoke key slots which allow to open a data file with different passwords")
+                .arg(Arg::new(ARG_INPUT_FILE)
+                    .short('i')
+                    .long(ARG_INPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted data file"))
+                .arg(Arg::new(ARG_ADD_SLOT)
+                    .short('a')
+                    .long(ARG_ADD_SLOT)
+                    .required(false)
+                    .num_args(1)
+                    .help("Label of a new key slot"))
+                .arg(Arg::new(ARG_REVOKE_SLOT)
+                    .short('r')
+                    .long(ARG_REVOKE_SLOT)
+                    .required(false)
+                    .num_args(1)
+                    .conflicts_with(ARG_ADD_SLOT)
+                    .help("Label of the key slot to revoke"))
+                .arg(add_kdf_param())
+                .arg(add_kdf_params_param())
+                .arg(add_key_file_param()))
+        .subcommand(
+            Command::new(COMMAND_KEYGEN)
+                .about("Generate a key pair for opening files without a password. The private key is stored unencrypted")
+                .arg(Arg::new(ARG_OUTPUT_FILE)
+                    .short('o')
+                    .long(ARG_OUTPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of the private key file. It is not encrypted. The public key is written to the same name with the suffix .pub"))
+                .arg(Arg::new(ARG_LABEL)
+                    .short('l')
+                    .long(ARG_LABEL)
+                    .required(false)
+                    .num_args(1)
+                    .help("Name of the key owner. Defaults to the file name"))
+        )
+        .subcommand(
+            Command::new(COMMAND_VERIFY)
+                .about("Check that an encrypted file, e.g. a backup, can be decrypted")
+                .arg(Arg::new(ARG_INPUT_FILE)
+                    .short('i')
+                    .long(ARG_INPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted file"))
+                .arg(Arg::new(ARG_HEADER_ONLY)
+                    .short('H')
+                    .long(ARG_HEADER_ONLY)
+                    .required(false)
+                    .action(ArgAction::SetTrue)
+                    .help("Only check the header. No password is needed."))
+                .arg(Arg::new(ARG_PW_FILE)
+                    .long(ARG_PW_FILE)
+                    .required(false)
+                    .num_args(1)
+                    .help("File whose first line is the password. Use - to read it from stdin"))
+                .arg(add_cipher_param())
+                .arg(add_key_file_param())
+                .arg(add_private_key_param())
+        )
+        .subcommand(
+            Command::new(COMMAND_BENCH)
+                .about("Time the KDFs on this machine and recommend parameters for a target unlock time")
+                .arg(Arg::new(ARG_TARGET)
+                    .short('t')
+                    .long(ARG_TARGET)
+                    .required(false)
+                    .num_args(1)
+                    .value_parser(clap::value_parser!(u64).range(1..))
+                    .help("Target time for deriving a key in milliseconds. Default is 1000"))
+                .arg(Arg::new(ARG_MAX_MEM)
+                    .short('m')
+                    .long(ARG_MAX_MEM)
+                    .required(false)
+                    .num_args(1)
+                    .value_parser(clap::value_parser!(u32).range(1..))
+                    .help("Maximum memory a KDF may use in MiB. Default is 1024"))
+                .arg(add_kdf_param())
+                .arg(Arg::new(ARG_WRITE_CONFIG)
+                    .short('w')
+                    .long(ARG_WRITE_CONFIG)
+                    .required(false)
+                    .action(ArgAction::SetTrue)
+                    .help("Write the recommended parameters to the config file"))
+                .arg(Arg::new(ARG_CONFIG_FILE)
+                    .short('c')
+                    .long(ARG_CONFIG_FILE)
+                    .num_args(1)
+                    .help("Name of config file. Default is .rustpwman"))
+        )
+        .subcommand(
+            Command::new(COMMAND_SHARE)
+                .about("Split a recovery key for a data file into shares of which a given number are needed to open it")
+                .arg(Arg::new(ARG_INPUT_FILE)
+                    .short('i')
+                    .long(ARG_INPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted file"))
+                .arg(Arg::new(ARG_SHARES)
+                    .short('n')
+                    .long(ARG_SHARES)
+                    .required(false)
+                    .num_args(1)
+                    .value_parser(clap::value_parser!(u8).range(2..))
+                    .help("Number of shares to create. Default is 5"))
+                .arg(Arg::new(ARG_THRESHOLD)
+                    .short('k')
+                    .long(ARG_THRESHOLD)
+                    .required(false)
+                    .num_args(1)
+                    .value_parser(clap::value_parser!(u8).range(2..))
+                    .help("Number of shares which are needed for recovery. Default is 3"))
+                .arg(Arg::new(ARG_QR)
+                    .long(ARG_QR)
+                    .required(false)
+                    .action(ArgAction::SetTrue)
+                    .help("Also print each share as a QR code"))
+                .arg(add_kdf_param())
+                .arg(add_kdf_params_param()))
+        .subcommand(
+            Command::new(COMMAND_RECOVER)
+                .about("Set a new password for a data file using recovery shares")
+                .arg(Arg::new(ARG_INPUT_FILE)
+                    .short('i')
+                    .long(ARG_INPUT_FILE)
+                    .required(true)
+                    .num_args(1)
+                    .help("Name of encrypted file"))
+                .arg(Arg::new(ARG_SHARE)
+                    .short('s')
+                    .long(ARG_SHARE)
+                    .required(false)
+                    .num_args(1)
+                    .action(ArgAction::Append)
+                    .help("File which contains recovery shares. Can be repeated. Shares are read from stdin if not given."))
+                .arg(Arg::new(ARG_LABEL)
+                    .short('l')
+                    .long(ARG_LABEL)
+                    .required(false)
+                    .num_args(1)
+                    .help("Key slot which gets the new password. Default is the slot default"))
+                .arg(add_kdf_param())
+                .arg(add_kdf_params_param())
         );
 
     let mut rustpwman = RustPwMan::new();
@@ -667,6 +2051,34 @@ fn main() {
                 (COMMAND_OBFUSCATE, _) => {
                     rustpwman.perform_obfuscate_command();
                 },
+                (COMMAND_AGE_REPORT, report_matches) => {
+                    rustpwman.perform_age_report_command(report_matches);
+                },
+                (COMMAND_MERGE, merge_matches) => {
+                    rustpwman.perform_merge_command(merge_matches);
+                },
+                (COMMAND_REKEY, rekey_matches) => {
+                    rustpwman.perform_rekey_command(rekey_matches);
+                },
+                (COMMAND_KEY_SLOT, slot_matches) => {
+                    rustpwman.perform_key_slot_command(slot_matches);
+                },
+                (COMMAND_KEYGEN, keygen_matches) => {
+                    rustpwman.perform_keygen_command(keygen_matches);
+                },
+                (COMMAND_VERIFY, verify_matches) => {
+                    let code = rustpwman.perform_verify_command(verify_matches);
+                    std::process::exit(code);
+                },
+                (COMMAND_BENCH, bench_matches) => {
+                    rustpwman.perform_bench_command(bench_matches);
+                },
+                (COMMAND_SHARE, share_matches) => {
+                    rustpwman.perform_share_command(share_matches);
+                },
+                (COMMAND_RECOVER, recover_matches) => {
+                    rustpwman.perform_recover_command(recover_matches);
+                },
                 (&_, _) => panic!("Can not happen")
             }
         },
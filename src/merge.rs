@@ -0,0 +1,326 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use crate::jots::{Entry, HistoryItem, TrashItem, MAX_HISTORY_ITEMS, new_entry_id};
+
+// A change that could not be merged automatically. A side which is None has deleted the entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub description: String,
+    pub local: Option<(String, Entry)>,
+    pub remote: Option<(String, Entry)>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolution {
+    Local,
+    Remote,
+    Both
+}
+
+pub struct MergeResult {
+    pub merged: HashMap<String, Entry>,
+    pub trash: Vec<TrashItem>,
+    pub conflicts: Vec<Conflict>
+}
+
+impl MergeResult {
+    pub fn new() -> MergeResult {
+        return MergeResult {
+            merged: HashMap::new(),
+            trash: Vec::new(),
+            conflicts: Vec::new()
+        };
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        return self.conflicts.len() == 0;
+    }
+
+    // Resolves the first remaining conflict
+    pub fn resolve(&mut self, r: Resolution) {
+        if self.conflicts.len() == 0 {
+            return;
+        }
+
+        let c = self.conflicts.remove(0);
+
+        match r {
+            Resolution::Local => self.insert_resolved(c.local, "local"),
+            Resolution::Remote => self.insert_resolved(c.remote, "remote"),
+            Resolution::Both => {
+                self.insert_resolved(c.local, "local");
+                self.insert_resolved(c.remote, "remote");
+            }
+        }
+    }
+
+    // Resolves all remaining conflicts in the same way
+    pub fn resolve_all(&mut self, r: Resolution) {
+        while !self.is_resolved() {
+            self.resolve(r);
+        }
+    }
+
+    fn insert_resolved(&mut self, value: Option<(String, Entry)>, origin: &str) {
+        let (name, mut entry) = match value {
+            Some(v) => v,
+            None => return
+        };
+
+        if let Some(existing) = self.merged.get(&name) {
+            if *existing == entry {
+                return;
+            }
+        }
+
+        let mut new_name = name.clone();
+        let mut count = 1;
+
+        while self.merged.contains_key(&new_name) {
+            new_name = if count == 1 { format!("{} ({})", name, origin) } else { format!("{} ({} {})", name, origin, count) };
+            count += 1;
+        }
+
+        // When both versions of an entry are kept they become separate entries
+        if self.merged.values().any(|e| e.id == entry.id) {
+            entry.id = new_entry_id();
+        }
+
+        self.merged.insert(new_name, entry);
+    }
+
+    fn insert_merged(&mut self, name: String, entry: Entry) {
+        match self.merged.remove(&name) {
+            None => { self.merged.insert(name, entry); },
+            Some(existing) => {
+                if existing == entry {
+                    self.merged.insert(name, entry);
+                    return;
+                }
+
+                // Two different entries ended up with the same name, e.g. an entry was renamed on one side while
+                // another entry with that name was added on the other side
+                self.conflicts.push(Conflict {
+                    description: format!("The merged versions contain two different entries named '{}'", &name),
+                    local: Some((name.clone(), existing)),
+                    remote: Some((name, entry))
+                });
+            }
+        }
+    }
+}
+
+// Returns the merged value if at most one side has changed it or if both sides have made the same change
+fn merge_value<T: PartialEq + Clone>(base: &T, local: &T, remote: &T) -> Option<T> {
+    if local == base {
+        return Some(remote.clone());
+    }
+
+    if (remote == base) || (local == remote) {
+        return Some(local.clone());
+    }
+
+    return None;
+}
+
+fn merge_history(local: &Vec<HistoryItem>, remote: &Vec<HistoryItem>) -> Vec<HistoryItem> {
+    let mut res = local.clone();
+
+    for i in remote {
+        if !res.contains(i) {
+            res.push(i.clone());
+        }
+    }
+
+    res.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    res.truncate(MAX_HISTORY_ITEMS);
+
+    return res;
+}
+
+// Merges an entry field by field. None is returned if the same field was changed in different ways.
+pub fn merge_entry(base: &Entry, local: &Entry, remote: &Entry) -> Option<Entry> {
+    return Some(Entry {
+        id: base.id.clone(),
+        text: merge_value(&base.text, &local.text, &remote.text)?,
+        user_name: merge_value(&base.user_name, &local.user_name, &remote.user_name)?,
+        password: merge_value(&base.password, &local.password, &remote.password)?,
+        url: merge_value(&base.url, &local.url, &remote.url)?,
+        custom_fields: merge_value(&base.custom_fields, &local.custom_fields, &remote.custom_fields)?,
+        group: merge_value(&base.group, &local.group, &remote.group)?,
+        tags: merge_value(&base.tags, &local.tags, &remote.tags)?,
+        attachments: merge_value(&base.attachments, &local.attachments, &remote.attachments)?,
+        created: base.created,
+        modified: std::cmp::max(local.modified, remote.modified),
+        secret_changed: std::cmp::max(local.secret_changed, remote.secret_changed),
+        history: merge_history(&local.history, &remote.history)
+    });
+}
+
+// Determines under which name an entry of the base version can be found in another version. Renaming an entry
+// does not change its id, so this is used to identify renamed entries. If no entry with the same id exists, e.g.
+// because an older program version has renamed the entry, it is identified by its contents. The keys in used have
+// already been assigned to another entry.
+fn locate(key: &String, base_entry: &Entry, base: &HashMap<String, Entry>, other: &HashMap<String, Entry>, used: &HashSet<String>) -> Option<(String, Entry)> {
+    if let Some(e) = other.get(key) {
+        return Some((key.clone(), e.clone()));
+    }
+
+    let unassigned: Vec<(&String, &Entry)> = other.iter()
+        .filter(|(k, _)| !base.contains_key(*k) && !used.contains(*k))
+        .collect();
+
+    let mut candidates: Vec<&String> = unassigned.iter()
+        .filter(|(_, e)| !base_entry.id.is_empty() && (e.id == base_entry.id))
+        .map(|(k, _)| *k)
+        .collect();
+
+    if candidates.len() == 0 {
+        candidates = unassigned.iter()
+            .filter(|(_, e)| e.same_contents(base_entry))
+            .map(|(k, _)| *k)
+            .collect();
+    }
+
+    if candidates.len() != 1 {
+        return None;
+    }
+
+    return Some((candidates[0].clone(), other[candidates[0]].clone()));
+}
+
+fn sorted_keys(h: &HashMap<String, Entry>) -> Vec<String> {
+    let mut res: Vec<String> = h.keys().map(|k| k.clone()).collect();
+    res.sort();
+
+    return res;
+}
+
+fn merge_trash(local: &Vec<TrashItem>, remote: &Vec<TrashItem>) -> Vec<TrashItem> {
+    let mut res = local.clone();
+
+    for i in remote {
+        if !res.iter().any(|t| (t.key == i.key) && (t.deleted == i.deleted)) {
+            res.push(i.clone());
+        }
+    }
+
+    res.sort_by(|a, b| a.deleted.cmp(&b.deleted));
+
+    return res;
+}
+
+// Performs a three way merge of the entries of a local and a remote version which were both derived from base
+pub fn merge(base: &HashMap<String, Entry>, local: &HashMap<String, Entry>, remote: &HashMap<String, Entry>,
+             local_trash: &Vec<TrashItem>, remote_trash: &Vec<TrashItem>) -> MergeResult {
+    let mut res = MergeResult::new();
+    res.trash = merge_trash(local_trash, remote_trash);
+
+    let mut used_local: HashSet<String> = HashSet::new();
+    let mut used_remote: HashSet<String> = HashSet::new();
+
+    for k in sorted_keys(base) {
+        let b = &base[&k];
+        let l = locate(&k, b, base, local, &used_local);
+        let r = locate(&k, b, base, remote, &used_remote);
+
+        if let Some((name, _)) = &l {
+            used_local.insert(name.clone());
+        }
+
+        if let Some((name, _)) = &r {
+            used_remote.insert(name.clone());
+        }
+
+        match (l, r) {
+            (None, None) => (),
+            (Some(l), None) => {
+                if (l.0 != k) || (l.1 != *b) {
+                    res.conflicts.push(Conflict {
+                        description: format!("Entry '{}' was changed locally and deleted remotely", &k),
+                        local: Some(l),
+                        remote: None
+                    });
+                }
+            },
+            (None, Some(r)) => {
+                if (r.0 != k) || (r.1 != *b) {
+                    res.conflicts.push(Conflict {
+                        description: format!("Entry '{}' was deleted locally and changed remotely", &k),
+                        local: None,
+                        remote: Some(r)
+                    });
+                }
+            },
+            (Some(l), Some(r)) => {
+                let name = merge_value(&k, &l.0, &r.0);
+                let entry = merge_entry(b, &l.1, &r.1);
+
+                match (name, entry) {
+                    (Some(n), Some(e)) => res.insert_merged(n, e),
+                    _ => {
+                        res.conflicts.push(Conflict {
+                            description: format!("Entry '{}' was changed in both versions", &k),
+                            local: Some(l),
+                            remote: Some(r)
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    // Entries which have been added on one or both sides
+    for k in sorted_keys(local) {
+        if base.contains_key(&k) || used_local.contains(&k) {
+            continue;
+        }
+
+        let l = local[&k].clone();
+
+        match remote.get(&k) {
+            Some(r) if !base.contains_key(&k) && !used_remote.contains(&k) => {
+                used_remote.insert(k.clone());
+
+                if l.same_contents(r) && (l.group == r.group) && (l.tags == r.tags) && (l.attachments == r.attachments) {
+                    let mut e = l.clone();
+                    e.history = merge_history(&l.history, &r.history);
+                    res.insert_merged(k, e);
+                } else {
+                    res.conflicts.push(Conflict {
+                        description: format!("Entry '{}' was added in both versions with different values", &k),
+                        local: Some((k.clone(), l)),
+                        remote: Some((k, r.clone()))
+                    });
+                }
+            },
+            _ => res.insert_merged(k, l)
+        }
+    }
+
+    for k in sorted_keys(remote) {
+        if base.contains_key(&k) || used_remote.contains(&k) {
+            continue;
+        }
+
+        let r = remote[&k].clone();
+        res.insert_merged(k, r);
+    }
+
+    return res;
+}
@@ -16,12 +16,23 @@ mod cache;
 mod pwgenerate;
 mod load;
 mod rename;
+mod folder;
+mod tags;
+mod history;
+mod attach;
+mod trash;
+pub mod merge;
+mod journal;
+pub mod report;
 mod delete;
 mod save;
 mod add;
 mod clear;
 mod edit;
 mod pw;
+mod rekey;
+mod slots;
+mod upgrade;
 mod pwentry;
 mod init;
 mod tuiundo;
@@ -45,6 +56,9 @@ const PW_WIDTH: usize = 35;
 pub const DEFAULT_PASTE_CMD: &str = "xsel -ob";
 pub const DEFAULT_COPY_CMD: &str = "xsel -ib";
 
+#[cfg(feature = "pwmanclient")]
+pub use cache::cached_password;
+
 use crate::persist::SendSyncPersister;
 use cursive::theme::ColorStyle;
 use cursive::traits::*;
@@ -57,17 +71,21 @@ use cursive::theme;
 use cursive::theme::Effects;
 use cursive::theme::Effect;
 use std::sync::{Arc, Mutex};
+use std::collections::HashSet;
 
 use std::sync::mpsc::Sender;
 use std::io::{Error, ErrorKind};
 
 use crate::pwgen::GenerationStrategy;
 use crate::jots;
+use crate::tagfilter::TagFilter;
+use crate::timestamp;
+use crate::secret::SecretString;
 
 
 pub struct AppState {
     store: jots::Jots,
-    password: Option<String>,
+    password: Option<SecretString>,
     store_id: String,
     default_security_level: usize,
     default_generator: GenerationStrategy,
@@ -77,6 +95,8 @@ pub struct AppState {
     last_custom_selection: String,
     pw_is_chached: bool,
     entry_queue: Vec<String>,
+    expanded_groups: HashSet<String>,
+    tag_filter: Option<TagFilter>,
 }
 
 impl AppState {
@@ -93,7 +113,9 @@ impl AppState {
             persister: p,
             last_custom_selection: String::from(""),
             pw_is_chached: is_pw_cached,
-            entry_queue: Vec::new()
+            entry_queue: Vec::new(),
+            expanded_groups: HashSet::new(),
+            tag_filter: None
         }
     }
 
@@ -109,6 +131,10 @@ impl AppState {
             }
         };
 
+        if self.store.stored_file_changed(&mut self.persister)? {
+            return Err(Error::new(ErrorKind::Other, format!("File '{}' has been changed since it was read. Use 'Merge with saved version' before saving.", &self.store_id)));
+        }
+
         return self.store.persist(&mut self.persister, pw.as_str());
     }
 }
@@ -142,6 +168,7 @@ fn pwman_quit_with_state(s: &mut Cursive, sender: Arc<Sender<String>>, message:
     let msg2 = message.clone();
     let sndr = sender.clone();
     let sndr2 = sender.clone();
+    let state_for_discard = app_state.clone();
 
     if dirty_bit {
         s.add_layer(
@@ -149,6 +176,12 @@ fn pwman_quit_with_state(s: &mut Cursive, sender: Arc<Sender<String>>, message:
                 .title("Rustpwman")
                 .button("Yes", move |s: &mut Cursive| {
                     s.pop_layer();
+
+                    // The changes are discarded deliberately, so they must not be replayed later
+                    if let Some(state) = &state_for_discard {
+                        state.lock().unwrap().store.discard_journal();
+                    }
+
                     do_quit(s, sndr.clone(), msg.clone());
                 })
                 .button("No", move |s| {
@@ -202,85 +235,244 @@ fn show_message(siv: &mut Cursive, msg: &str) {
     );
 }
 
-fn display_entry(siv: &mut Cursive, state: Arc<Mutex<AppState>>, entry_name: &String, do_select: bool) {
-    if entry_name == "" {
-        return;
+// Items of the entry list. Groups are shown as a collapsible tree with their entries below them.
+#[derive(Clone, PartialEq, Debug)]
+enum EntryListItem {
+    Group(String),
+    Entry(String)
+}
+
+fn parent_group(group: &str) -> &str {
+    return match group.rsplit_once(jots::GROUP_SEPARATOR) {
+        Some((parent, _)) => parent,
+        None => ""
+    };
+}
+
+fn add_tree_level(visible: &Vec<(String, String)>, groups: &Vec<String>, expanded: &HashSet<String>, parent: &str, depth: usize, items: &mut Vec<(String, EntryListItem)>) {
+    let indent = "  ".repeat(depth);
+
+    for g in groups.iter().filter(|i| parent_group(i) == parent) {
+        let name = match g.rsplit_once(jots::GROUP_SEPARATOR) {
+            Some((_, n)) => n,
+            None => g.as_str()
+        };
+
+        if expanded.contains(g) {
+            items.push((format!("{}[-] {}", indent, name), EntryListItem::Group(g.clone())));
+            add_tree_level(visible, groups, expanded, g, depth + 1, items);
+        } else {
+            items.push((format!("{}[+] {}", indent, name), EntryListItem::Group(g.clone())));
+        }
     }
 
-    let entry_text: String;
-    let pos: usize;
+    for (name, group) in visible {
+        if group == parent {
+            items.push((format!("{}{}", indent, name), EntryListItem::Entry(name.clone())));
+        }
+    }
+}
+
+fn is_visible(state: &AppState, entry_name: &String) -> bool {
+    let filter = match &state.tag_filter {
+        Some(f) => f,
+        None => return true
+    };
+
+    return match state.store.contents.get(entry_name) {
+        Some(e) => filter.matches(&e.tags),
+        None => false
+    };
+}
+
+fn fill_entry_list(siv: &mut Cursive, state: Arc<Mutex<AppState>>) {
+    let mut items: Vec<(String, EntryListItem)> = Vec::new();
 
     {
         let h = state.lock().unwrap();
-        let store = &(*h).store;
+        let mut visible: Vec<(String, String)> = Vec::new();
 
-        let positions = 0..store.into_iter().count();
-        let find_res = store.into_iter().zip(positions).find(|i| entry_name == (*i).0 );
+        for i in &h.store {
+            if is_visible(&h, i) {
+                visible.push((i.clone(), h.store.contents[i].group.clone()));
+            }
+        }
+
+        let groups = jots::collect_groups(visible.iter().map(|i| &i.1));
+        add_tree_level(&visible, &groups, &h.expanded_groups, "", 0, &mut items);
+    }
+
+    siv.call_on_name(SELECT_VIEW, |view: &mut SelectView<EntryListItem>| {
+        view.clear();
+        view.add_all(items);
+    });
+}
+
+// Expands all groups which contain the given entry. Returns true if the tree has changed.
+fn expand_groups_of(state: Arc<Mutex<AppState>>, entry_name: &String) -> bool {
+    let mut h = state.lock().unwrap();
+    let mut changed = false;
+
+    let mut group = match h.store.get_entry(entry_name) {
+        Some(e) => e.group,
+        None => return false
+    };
+
+    while group != "" {
+        changed |= h.expanded_groups.insert(group.clone());
+        group = String::from(parent_group(&group));
+    }
+
+    return changed;
+}
+
+fn select_list_item(siv: &mut Cursive, item: &EntryListItem) {
+    let pos = siv.call_on_name(SELECT_VIEW, |view: &mut SelectView<EntryListItem>| {
+        view.iter().position(|i| i.1 == item)
+    });
+
+    let pos = match pos {
+        Some(Some(p)) => p,
+        _ => {
+            show_message(siv, "Unable to set selection");
+            return;
+        }
+    };
+
+    match siv.call_on_name(SELECT_VIEW, |view: &mut SelectView<EntryListItem>| { view.set_selection(pos) }) {
+        Some(cb) => cb(siv),
+        None => {
+            show_message(siv, "Unable to set selection");
+            return;
+        }
+    }
+}
+
+fn toggle_group(siv: &mut Cursive, state: Arc<Mutex<AppState>>, group: &String) {
+    {
+        let mut h = state.lock().unwrap();
 
-        pos = find_res.unwrap().1;
-        entry_text = store.get(entry_name).unwrap();
+        if !h.expanded_groups.remove(group) {
+            h.expanded_groups.insert(group.clone());
+        }
+    }
+
+    fill_entry_list(siv, state.clone());
+    select_list_item(siv, &EntryListItem::Group(group.clone()));
+}
+
+fn display_group(siv: &mut Cursive, group: &String) {
+    siv.call_on_name(TEXT_AREA_MAIN, |view: &mut TextArea| { view.set_content("Press Enter to open or close this folder"); });
+    siv.call_on_name(TEXT_AREA_TITLE, |view: &mut TextArea| { view.set_content(format!("{}{}", group, jots::GROUP_SEPARATOR)); });
+}
+
+// Formats an entry for the main view. In contrast to the formatted entry used for copying and printing
+// this also contains information about the entry itself.
+fn format_entry_for_display(entry: &jots::Entry) -> String {
+    let mut res = entry.to_formatted_string();
+    let mut info: Vec<String> = Vec::new();
+
+    if entry.tags.len() != 0 {
+        info.push(format!("Tags: {}", entry.tags.join(", ")));
+    }
+
+    if entry.attachments.len() != 0 {
+        let names: Vec<&str> = entry.attachments.iter().map(|a| a.name.as_str()).collect();
+        info.push(format!("Attachments: {}", names.join(", ")));
+    }
+
+    if entry.history.len() != 0 {
+        info.push(format!("Previous versions: {}", entry.history.len()));
+    }
+
+    if !timestamp::is_unknown(&entry.created) {
+        info.push(format!("Created: {}", timestamp::format(entry.created)));
+    }
+
+    if !timestamp::is_unknown(&entry.modified) {
+        info.push(format!("Modified: {}", timestamp::format(entry.modified)));
+    }
+
+    if !timestamp::is_unknown(&entry.secret_changed) {
+        info.push(format!("Secret changed: {}", timestamp::format(entry.secret_changed)));
+    }
+
+    if info.len() != 0 {
+        res.push_str("\n\n--------\n");
+        res.push_str(&info.join("\n"));
+    }
+
+    return res;
+}
+
+fn display_entry(siv: &mut Cursive, state: Arc<Mutex<AppState>>, entry_name: &String, do_select: bool) {
+    if entry_name == "" {
+        return;
     }
 
     if do_select {
-        match siv.call_on_name(SELECT_VIEW, |view: &mut SelectView| { view.set_selection(pos) }) {
-            Some(cb) => cb(siv),
-            None => {
-                show_message(siv, "Unable to set selection");
-                return;
+        let mut must_refill = expand_groups_of(state.clone(), entry_name);
+
+        {
+            // An entry that is explicitly selected is always shown even if it does not match the current filter
+            let mut h = state.lock().unwrap();
+
+            if !is_visible(&h, entry_name) {
+                h.tag_filter = None;
+                must_refill = true;
             }
         }
+
+        if must_refill {
+            fill_entry_list(siv, state.clone());
+            visualize_if_modified(siv, state.clone());
+        }
+
+        select_list_item(siv, &EntryListItem::Entry(entry_name.clone()));
     } else {
+        let entry_text = match state.lock().unwrap().store.get_entry(entry_name) {
+            Some(e) => format_entry_for_display(&e),
+            None => return
+        };
+
         siv.call_on_name(TEXT_AREA_MAIN, |view: &mut TextArea| { view.set_content(entry_text.clone()); });
         siv.call_on_name(TEXT_AREA_TITLE, |view: &mut TextArea| { view.set_content(entry_name.clone()); });
     }
 }
 
 fn redraw_tui(siv: &mut Cursive, state: Arc<Mutex<AppState>>) {
-    let mut count = 0;
-    let mut initial_entry = String::from("");
-
-    siv.call_on_name(SELECT_VIEW, |view: &mut SelectView| { view.clear(); } );
     siv.call_on_name(TEXT_AREA_MAIN, |view: &mut TextArea| { view.set_content(""); });
     siv.call_on_name(TEXT_AREA_TITLE, |view: &mut TextArea| { view.set_content(""); });
 
-    {
-        let h = state.lock().unwrap();
-        let store = &(*h).store;
-
-        for i in store {
-            if count == 0 {
-                 initial_entry = i.clone();
-            }
+    fill_entry_list(siv, state.clone());
 
-            siv.call_on_name(SELECT_VIEW, |view: &mut SelectView| { view.add_item(i.clone(), i.clone()); } );
+    let initial_item = match siv.call_on_name(SELECT_VIEW, |view: &mut SelectView<EntryListItem>| { view.get_item(0).map(|i| i.1.clone()) }) {
+        Some(Some(i)) => i,
+        _ => return
+    };
 
-            count += 1;
-        }
+    match initial_item {
+        EntryListItem::Entry(name) => display_entry(siv, state.clone(), &name, true),
+        EntryListItem::Group(_) => select_list_item(siv, &initial_item)
     }
-
-    display_entry(siv, state.clone(), &initial_entry, true);
 }
 
 fn get_selected_entry_name(s: &mut Cursive) -> Option<String> {
-    let id_opt = match s.call_on_name(SELECT_VIEW, |view: &mut SelectView| { view.selected_id() }) {
-        Some(i) => i,
-        None => None
-    };
-
-    if let Some(id) = id_opt {
-        let help = s.call_on_name(SELECT_VIEW, |view: &mut SelectView| -> Option<String> {
-            match view.get_item(id) {
-                Some(t) => Some(String::from(t.0)),
-                _ => None
-            }
-        });
-
-        return match help {
-            Some(Some(egal)) => Some(egal),
-            _ => None
+    let help = s.call_on_name(SELECT_VIEW, |view: &mut SelectView<EntryListItem>| -> Option<String> {
+        match view.selection() {
+            Some(item) => {
+                match &*item {
+                    EntryListItem::Entry(name) => Some(name.clone()),
+                    EntryListItem::Group(_) => None
+                }
+            },
+            None => None
         }
-    } else {
-        return None;
+    });
+
+    return match help {
+        Some(Some(egal)) => Some(egal),
+        _ => None
     }
 }
 
@@ -305,12 +497,21 @@ fn get_special_styles() -> (theme::Style, theme::Style) {
 }
 
 fn visualize_if_modified(siv: &mut Cursive, state: Arc<Mutex<AppState>>) {
-    let is_dirty = state.lock().unwrap().store.is_dirty();
+    let (is_dirty, filter_text) = {
+        let h = state.lock().unwrap();
+
+        let filter_text = match &h.tag_filter {
+            Some(f) => format!(" [{}]", f.get_expression()),
+            None => String::from("")
+        };
+
+        (h.store.is_dirty(), filter_text)
+    };
 
     if is_dirty {
-        siv.call_on_name("EntrySelectPanel", |view: &mut Panel<NamedView<ScrollView<ResizedView<OnEventView<NamedView<SelectView>>>>>>| { view.set_title("Entries *"); } );
+        siv.call_on_name("EntrySelectPanel", |view: &mut Panel<NamedView<ScrollView<ResizedView<OnEventView<NamedView<SelectView<EntryListItem>>>>>>>| { view.set_title(format!("Entries *{}", filter_text)); } );
     } else {
-        siv.call_on_name("EntrySelectPanel", |view: &mut Panel<NamedView<ScrollView<ResizedView<OnEventView<NamedView<SelectView>>>>>>| { view.set_title("Entries"); } );
+        siv.call_on_name("EntrySelectPanel", |view: &mut Panel<NamedView<ScrollView<ResizedView<OnEventView<NamedView<SelectView<EntryListItem>>>>>>>| { view.set_title(format!("Entries{}", filter_text)); } );
     }
 }
 
@@ -328,7 +529,7 @@ fn quit_and_print(s: &mut Cursive, state: Arc<Mutex<AppState>>, sndr: Arc<Sender
         let h = state.lock().unwrap();
         let store = &(*h).store;
 
-        let value = match store.get(&key) {
+        let value = match store.get_formatted(&key) {
             Some(v) => v,
             None => { show_message(s, "Unable to read entry value"); return }
         };
@@ -387,9 +588,10 @@ fn wrapper3<T : Clone>(ctx: AppCtx, f: fn(&mut Cursive, state: Arc<Mutex<AppStat
 
 
 fn main_window(s: &mut Cursive, shared_state: Arc<Mutex<AppState>>, sndr: Arc<Sender<String>>) {
-    let select_view = SelectView::new();
+    let select_view = SelectView::<EntryListItem>::new();
     let ctx = AppCtx::new(shared_state.clone(), sndr.clone());
     let state_for_callback = shared_state.clone();
+    let state_for_toggle = shared_state.clone();
     let state_for_fill_tui = shared_state.clone();
 
     let menu_bar = s.menubar();
@@ -404,12 +606,16 @@ fn main_window(s: &mut Cursive, shared_state: Arc<Mutex<AppState>>, sndr: Arc<Se
         .leaf("Save File", wrapper(ctx.clone(), save::storage))
         .delimiter()
         .leaf("Change password ...", wrapper(ctx.clone(), pw::change))
+        .leaf("Reencrypt file ...", wrapper(ctx.clone(), rekey::rekey))
+        .leaf("Key slots ...", wrapper(ctx.clone(), slots::show))
         .leaf("Cache password", wrapper(ctx.clone(), cache::password))
         .leaf("Clear cached password", wrapper(ctx.clone(), cache::uncache_password))
         .delimiter()
         .leaf("About ...", info::about)
         .leaf("Info ...", wrapper(ctx.clone(), info::show))
         .leaf("Undo changes ...", wrapper(ctx.clone(), tuiundo::undo))
+        .leaf("Redo last change      F5", wrapper(ctx.clone(), tuiundo::redo))
+        .leaf("Merge with saved version ...", wrapper(ctx.clone(), merge::stored))
         .delimiter()
         .leaf("Quit and print        F4", wrapper2(ctx.clone(), quit_and_print))
         .leaf("Quit                  F3", wrapper2(ctx.clone(), quit_without_print)
@@ -419,10 +625,10 @@ fn main_window(s: &mut Cursive, shared_state: Arc<Mutex<AppState>>, sndr: Arc<Se
     // compilation when constructing the file_tree but I came to the opinion that in Rust conditional compilation is tied to
     // attributes which in turn does not seem to work when chaining values together as is done above.
     #[cfg(not(feature = "pwmanclient"))]
-    file_tree.remove(3);  // remove cache item when building without the pwmanclient feature
+    file_tree.remove(5);  // remove cache item when building without the pwmanclient feature
 
     #[cfg(not(feature = "pwmanclient"))]
-    file_tree.remove(3);  // remove cache clear item when building without the pwmanclient feature
+    file_tree.remove(5);  // remove cache clear item when building without the pwmanclient feature
 
     menu_bar.add_subtree(
         "File", file_tree
@@ -434,8 +640,18 @@ fn main_window(s: &mut Cursive, shared_state: Arc<Mutex<AppState>>, sndr: Arc<Se
             .leaf("Add Entry ...", wrapper(ctx.clone(), add::entry))
             .leaf("Delete Entry ...", wrapper(ctx.clone(), delete::entry))
             .leaf("Rename Entry ...", wrapper(ctx.clone(), rename::entry))
+            .leaf("Move to Folder ...", wrapper(ctx.clone(), folder::entry))
+            .leaf("Edit Tags ...", wrapper(ctx.clone(), tags::entry))
+            .leaf("History ...", wrapper(ctx.clone(), history::entry))
+            .leaf("Attachments ...", wrapper(ctx.clone(), attach::entry))
             .leaf("Clear Entry ...", wrapper(ctx.clone(), clear::entry))
             .leaf("Load Entry ...", wrapper(ctx.clone(), load::entry))
+            .delimiter()
+            .leaf("Filter by Tags ...", wrapper(ctx.clone(), tags::filter))
+            .leaf("Clear Filter", wrapper(ctx.clone(), tags::clear_filter))
+            .delimiter()
+            .leaf("Password Age Report ...", wrapper(ctx.clone(), report::password_age))
+            .leaf("Recycle Bin ...", wrapper(ctx.clone(), trash::show))
         )
         .add_subtree("Queue",
             Tree::new()
@@ -450,12 +666,21 @@ fn main_window(s: &mut Cursive, shared_state: Arc<Mutex<AppState>>, sndr: Arc<Se
     s.add_global_callback(Key::Esc, |s| s.select_menubar());
     s.add_global_callback(Key::F3, wrapper2(ctx.clone(), quit_without_print));
     s.add_global_callback(Key::F4, wrapper2(ctx.clone(), quit_and_print));
+    s.add_global_callback(Key::F5, wrapper(ctx.clone(), tuiundo::redo));
 
     let mut event_wrapped_select_view = OnEventView::new(
         select_view
-        .h_align(HAlign::Center)
+        .h_align(HAlign::Left)
         .on_select(move |s, item| {
-            display_entry(s, state_for_callback.clone(), item, false)
+            match item {
+                EntryListItem::Entry(name) => display_entry(s, state_for_callback.clone(), name, false),
+                EntryListItem::Group(group) => display_group(s, group)
+            }
+        })
+        .on_submit(move |s, item| {
+            if let EntryListItem::Group(group) = item {
+                toggle_group(s, state_for_toggle.clone(), group);
+            }
         })
         .autojump()
         .with_name(SELECT_VIEW)
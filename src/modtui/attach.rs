@@ -0,0 +1,225 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, EditView, SelectView, Panel};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::get_selected_entry_name;
+use super::display_entry;
+use super::visualize_if_modified;
+use crate::timestamp;
+
+const ATTACHMENT_LIST: &str = "attachmentlist";
+const EDIT_FILE_NAME: &str = "attachfile";
+const EDIT_ATTACHMENT_NAME: &str = "attachname";
+const NUM_ATTACHMENTS_VISIBLE: usize = 10;
+
+fn fill_attachment_list(s: &mut Cursive, state: Arc<Mutex<AppState>>, entry_name: &String) {
+    let attachments = match state.lock().unwrap().store.get_entry(entry_name) {
+        Some(e) => e.attachments,
+        None => Vec::new()
+    };
+
+    s.call_on_name(ATTACHMENT_LIST, |view: &mut SelectView<String>| {
+        view.clear();
+
+        for i in attachments {
+            let label = format!("{} ({} bytes, added {})", i.name, i.data.len(), timestamp::format(i.added));
+            view.add_item(label, i.name);
+        }
+    });
+}
+
+fn get_selected_attachment(s: &mut Cursive) -> Option<String> {
+    return match s.call_on_name(ATTACHMENT_LIST, |view: &mut SelectView<String>| { view.selection() }) {
+        Some(Some(name)) => Some((*name).clone()),
+        _ => None
+    };
+}
+
+fn refresh(s: &mut Cursive, state: Arc<Mutex<AppState>>, entry_name: &String) {
+    visualize_if_modified(s, state.clone());
+    display_entry(s, state.clone(), entry_name, false);
+    fill_attachment_list(s, state, entry_name);
+}
+
+fn attach(s: &mut Cursive, state_for_attach: Arc<Mutex<AppState>>, entry_name: String) {
+    let res = Dialog::new()
+    .title("Rustpwman attach file")
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(TextView::new("Please enter the name of the file to attach. If no attachment name\nis given the name of the file is used.\n\n"))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Filename  : "))
+                .child(EditView::new()
+                    .with_name(EDIT_FILE_NAME)
+                    .fixed_width(60))
+        )
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Attachment: "))
+                .child(EditView::new()
+                    .with_name(EDIT_ATTACHMENT_NAME)
+                    .fixed_width(60))
+        )
+    )
+    .button("OK", move |s| {
+        let file_name = match s.call_on_name(EDIT_FILE_NAME, |view: &mut EditView| { view.get_content() }) {
+            Some(name) => name,
+            None => { show_message(s, "Unable to read file name"); return }
+        };
+
+        let mut attachment_name = match s.call_on_name(EDIT_ATTACHMENT_NAME, |view: &mut EditView| { view.get_content() }) {
+            Some(name) => String::from(name.trim()),
+            None => { show_message(s, "Unable to read attachment name"); return }
+        };
+
+        if attachment_name == "" {
+            attachment_name = match Path::new(file_name.as_str()).file_name() {
+                Some(n) => n.to_string_lossy().to_string(),
+                None => { show_message(s, "Unable to determine attachment name"); return }
+            };
+        }
+
+        let data = match fs::read(file_name.as_str()) {
+            Ok(d) => d,
+            Err(e) => {
+                show_message(s, &format!("Unable to read file: {:?}", e));
+                return;
+            }
+        };
+
+        let res = state_for_attach.lock().unwrap().store.add_attachment(&entry_name, &attachment_name, &data);
+
+        if let Err(e) = res {
+            show_message(s, &format!("Unable to attach file. {}", e));
+            return;
+        }
+
+        s.pop_layer();
+        refresh(s, state_for_attach.clone(), &entry_name);
+    })
+    .button("Cancel", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+}
+
+fn extract(s: &mut Cursive, state_for_extract: Arc<Mutex<AppState>>, entry_name: String) {
+    let attachment_name = match get_selected_attachment(s) {
+        Some(n) => n,
+        None => { show_message(s, "No attachment selected"); return }
+    };
+
+    let res = Dialog::new()
+    .title("Rustpwman extract attachment")
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(TextView::new(format!("Please enter the name of the file to which '{}' is written.\n\n", attachment_name)))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Filename: "))
+                .child(EditView::new()
+                    .content(attachment_name.clone())
+                    .with_name(EDIT_FILE_NAME)
+                    .fixed_width(60))
+        )
+    )
+    .button("OK", move |s| {
+        let file_name = match s.call_on_name(EDIT_FILE_NAME, |view: &mut EditView| { view.get_content() }) {
+            Some(name) => name,
+            None => { show_message(s, "Unable to read file name"); return }
+        };
+
+        if Path::new(file_name.as_str()).exists() {
+            show_message(s, "File already exists");
+            return;
+        }
+
+        let data = match state_for_extract.lock().unwrap().store.get_attachment(&entry_name, &attachment_name) {
+            Some(d) => d,
+            None => { show_message(s, "Unable to read attachment"); return }
+        };
+
+        if let Err(e) = fs::write(file_name.as_str(), data) {
+            show_message(s, &format!("Unable to write file: {:?}", e));
+            return;
+        }
+
+        s.pop_layer();
+    })
+    .button("Cancel", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+}
+
+fn delete(s: &mut Cursive, state_for_delete: Arc<Mutex<AppState>>, entry_name: &String) {
+    let attachment_name = match get_selected_attachment(s) {
+        Some(n) => n,
+        None => { show_message(s, "No attachment selected"); return }
+    };
+
+    if !state_for_delete.lock().unwrap().store.delete_attachment(entry_name, &attachment_name) {
+        show_message(s, "Deleting attachment failed");
+        return;
+    }
+
+    refresh(s, state_for_delete, entry_name);
+}
+
+pub fn entry(s: &mut Cursive, state_for_attachments: Arc<Mutex<AppState>>) {
+    let entry_name = match get_selected_entry_name(s) {
+        Some(name) => name,
+        None => {
+            show_message(s, "Unable to determine selected entry"); 
+            return; 
+        }
+    };
+
+    let state_for_attach = state_for_attachments.clone();
+    let state_for_extract = state_for_attachments.clone();
+    let state_for_delete = state_for_attachments.clone();
+    let name_for_attach = entry_name.clone();
+    let name_for_extract = entry_name.clone();
+    let name_for_delete = entry_name.clone();
+
+    let res = Dialog::new()
+    .title(format!("Rustpwman attachments of '{}'", entry_name))
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        Panel::new(SelectView::<String>::new()
+            .with_name(ATTACHMENT_LIST)
+            .scrollable()
+            .fixed_width(70)
+            .fixed_height(NUM_ATTACHMENTS_VISIBLE))
+        .title("Attachments")
+    )
+    .button("Attach ...", move |s| { attach(s, state_for_attach.clone(), name_for_attach.clone()); })
+    .button("Extract ...", move |s| { extract(s, state_for_extract.clone(), name_for_extract.clone()); })
+    .button("Delete", move |s| { delete(s, state_for_delete.clone(), &name_for_delete); })
+    .button("Close", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+    fill_attachment_list(s, state_for_attachments, &entry_name);
+}
@@ -30,6 +30,8 @@ use crate::pwman_client_win::UDSClientWin;
 use crate::pwman_client::PWManClient;
 #[cfg(feature = "pwmanclient")]
 use crate::pwman_client::SendSyncPwManClient;
+#[cfg(feature = "pwmanclient")]
+use crate::secret::SecretString;
 
 
 use cursive::Cursive;
@@ -52,6 +54,17 @@ pub fn make_pwman_client(file_name: String) -> std::io::Result<SendSyncPwManClie
     }
 }
 
+// Returns the password which pwman has cached for the given store, if there is one
+#[cfg(feature = "pwmanclient")]
+pub fn cached_password(store_id: String) -> Option<SecretString> {
+    let client = match make_pwman_client(store_id) {
+        Ok(c) => c,
+        Err(_) => return None
+    };
+
+    return client.get_password().ok();
+}
+
 #[cfg(feature = "pwmanclient")]
 pub fn password(s: &mut Cursive, state_for_write_cache: Arc<Mutex<AppState>>) {
     let pw_option = state_for_write_cache.lock().unwrap().password.clone();
@@ -63,7 +76,7 @@ pub fn password(s: &mut Cursive, state_for_write_cache: Arc<Mutex<AppState>>) {
         }
     };
 
-    let password : String;
+    let password : SecretString;
     let client: Box<dyn PWManClient>;
 
     if let Some(p) = pw_option {
@@ -137,7 +150,7 @@ pub fn uncache_password(s: &mut Cursive, _state_for_write_cache: Arc<Mutex<AppSt
 }
 
 #[cfg(feature = "pwmanclient")]
-pub fn confirmation_dialog(sndr: Arc<Sender<String>>, password: String, client: SendSyncPwManClient, ok_cb_with_state: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>) -> Dialog {
+pub fn confirmation_dialog(sndr: Arc<Sender<String>>, password: SecretString, client: SendSyncPwManClient, ok_cb_with_state: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>) -> Dialog {
     let sender = sndr.clone();
     let sender2 = sndr.clone();
 
@@ -48,8 +48,15 @@ pub fn entry(s: &mut Cursive, state_temp_clear: Arc<Mutex<AppState>>) {
             )
             .button("Cancel", |s| { s.pop_layer(); })            
             .button("OK", move |s| {
-                let empty = String::from("Empty entry\n");
-                state_temp_clear.lock().unwrap().store.modify(&name, &empty);
+                {
+                    let mut state = state_temp_clear.lock().unwrap();
+
+                    if let Some(mut empty) = state.store.get_entry(&name) {
+                        empty.clear_contents(&String::from("Empty entry\n"));
+                        state.store.modify_entry(&name, &empty);
+                    }
+                }
+
                 s.pop_layer();
                 visualize_if_modified(s, state_temp_clear.clone());
                 display_entry(s, state_temp_clear.clone(), &name, true);
@@ -31,7 +31,7 @@ pub fn entry(s: &mut Cursive, state_for_copy_entry: Arc<Mutex<AppState>>, show_c
         }
     };
 
-    let mut h = match state_for_copy_entry.lock().unwrap().store.get(&entry_name) {
+    let mut h = match state_for_copy_entry.lock().unwrap().store.get_formatted(&entry_name) {
         Some(c) => c,
         None => { show_message(s, "Unable to read value of entry"); return }
     };
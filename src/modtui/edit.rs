@@ -16,7 +16,7 @@ limitations under the License. */
 use std::sync::{Arc, Mutex};
 
 use cursive::Cursive;
-use cursive::views::{Dialog, LinearLayout, TextView, TextArea, Panel};
+use cursive::views::{Dialog, LinearLayout, TextView, TextArea, Panel, EditView};
 use cursive::traits::*;
 use cursive::theme::{self, Effects};
 use cursive::theme::{Effect, PaletteColor};
@@ -29,8 +29,14 @@ use super::display_entry;
 use super::visualize_if_modified;
 use super::pwgenerate;
 use crate::clip;
+use crate::jots::{CustomField, FIELD_USER_NAME, FIELD_PASSWORD, FIELD_URL};
 
 const TEXT_AREA_NAME: &str = "textareaedit";
+const EDIT_USER_NAME: &str = "edituser";
+const EDIT_PASSWORD: &str = "editpassword";
+const EDIT_URL: &str = "editurl";
+const TEXT_AREA_CUSTOM: &str = "textareacustom";
+const FIELD_EDIT_WIDTH: usize = 68;
 
 pub fn insert_into_entry(s: &mut Cursive, new_pw: String) {
     let mut entry_text = match s.call_on_name(TEXT_AREA_NAME, |view: &mut TextArea| { String::from(view.get_content()) }) {
@@ -60,6 +66,26 @@ pub fn insert_into_entry(s: &mut Cursive, new_pw: String) {
 
 }
 
+pub fn set_password_field(s: &mut Cursive, new_pw: String) {
+    match s.call_on_name(EDIT_PASSWORD, |view: &mut EditView| { view.set_content(new_pw) }) {
+        Some(_) => (),
+        None => { show_message(s, "Unable to set password field"); return }
+    };
+}
+
+fn field_row(label: &str, name: &str, content: &String) -> LinearLayout {
+    return LinearLayout::horizontal()
+        .child(TextView::new(format!("{:10}: ", label)))
+        .child(EditView::new()
+            .content(content.clone())
+            .with_name(name)
+            .fixed_width(FIELD_EDIT_WIDTH));
+}
+
+fn read_field(s: &mut Cursive, name: &str) -> Option<String> {
+    return s.call_on_name(name, |view: &mut EditView| { String::from(view.get_content().as_str()) });
+}
+
 pub fn entry(s: &mut Cursive, state_for_edit_entry: Arc<Mutex<AppState>>, entry_name_external: Option<Arc<String>>) {
     let entry_to_edit: String;
     let mut show_scroll_message = false;
@@ -77,7 +103,7 @@ pub fn entry(s: &mut Cursive, state_for_edit_entry: Arc<Mutex<AppState>>, entry_
         }
     }
 
-    let content = match state_for_edit_entry.lock().unwrap().store.get(&entry_to_edit) {
+    let content = match state_for_edit_entry.lock().unwrap().store.get_entry(&entry_to_edit) {
         Some(c) => c,
         None => { show_message(s, "Unable to read value of entry"); return }
     };
@@ -101,36 +127,75 @@ pub fn entry(s: &mut Cursive, state_for_edit_entry: Arc<Mutex<AppState>>, entry_
         LinearLayout::vertical()
         .child(
             LinearLayout::horizontal()
-            .child(TextView::new("Please enter new text for entry "))
+            .child(TextView::new("Please enter new values for entry "))
             .child(TextView::new(entry_to_edit.as_str())
                 .style(name_style))
         )
         .child(TextView::new("\n"))
+        .child(field_row(FIELD_USER_NAME, EDIT_USER_NAME, &content.user_name))
+        .child(field_row(FIELD_PASSWORD, EDIT_PASSWORD, &content.password))
+        .child(field_row(FIELD_URL, EDIT_URL, &content.url))
+        .child(TextView::new("\n"))
+        .child(
+            Panel::new(
+                TextArea::new()
+                .content(CustomField::to_lines(&content.custom_fields))
+                .with_name(TEXT_AREA_CUSTOM)
+                .fixed_width(80)
+                .fixed_height(4))
+            .title("Custom fields (one 'name: value' per line)")
+        )
         .child(
             LinearLayout::horizontal()
                 .child(
                 Panel::new(
                     TextArea::new()
-                    .content(content)
+                    .content(content.text.clone())
                     .with_name(TEXT_AREA_NAME)
                     .fixed_width(80)
-                    .min_height(25))
-                .title("Text of entry"))
+                    .min_height(15))
+                .title("Notes"))
         )
     )
     .button("OK", move |s| {
-        let entry_text = match s.call_on_name(TEXT_AREA_NAME, |view: &mut TextArea| { String::from(view.get_content()) }) {
-            Some(text_val) => {
-                if text_val.len() == 0 {
-                    show_message(s, "Entry text is empty"); 
-                    return;
-                }
-                text_val
-            },
+        let mut new_value = content.clone();
+
+        new_value.text = match s.call_on_name(TEXT_AREA_NAME, |view: &mut TextArea| { String::from(view.get_content()) }) {
+            Some(text_val) => text_val,
             None => { show_message(s, "Unable to read entry text"); return }
         }; 
 
-        state_for_edit_entry.lock().unwrap().store.modify(&entry_to_edit, &entry_text);
+        let custom_text = match s.call_on_name(TEXT_AREA_CUSTOM, |view: &mut TextArea| { String::from(view.get_content()) }) {
+            Some(text_val) => text_val,
+            None => { show_message(s, "Unable to read custom fields"); return }
+        };
+
+        new_value.custom_fields = match CustomField::parse_lines(&custom_text) {
+            Ok(f) => f,
+            Err(e) => { show_message(s, &e); return }
+        };
+
+        new_value.user_name = match read_field(s, EDIT_USER_NAME) {
+            Some(v) => v,
+            None => { show_message(s, "Unable to read user name"); return }
+        };
+
+        new_value.password = match read_field(s, EDIT_PASSWORD) {
+            Some(v) => v,
+            None => { show_message(s, "Unable to read password"); return }
+        };
+
+        new_value.url = match read_field(s, EDIT_URL) {
+            Some(v) => v,
+            None => { show_message(s, "Unable to read URL"); return }
+        };
+
+        if (new_value.text.len() == 0) && !new_value.has_fields() {
+            show_message(s, "Entry is empty"); 
+            return;
+        }
+
+        state_for_edit_entry.lock().unwrap().store.modify_entry(&entry_to_edit, &new_value);
         visualize_if_modified(s, state_for_edit_entry.clone());
         display_entry(s, state_for_edit_entry.clone(), &entry_to_edit, true);
 
@@ -140,7 +205,7 @@ pub fn entry(s: &mut Cursive, state_for_edit_entry: Arc<Mutex<AppState>>, entry_
             show_message(s, "Entry created successfully. It has been selected\n but you may need to scroll to it manually.");            
         }
     })
-    .button("Insert Password ...", move |s: &mut Cursive| {
+    .button("Generate Password ...", move |s: &mut Cursive| {
         pwgenerate::generate_password(s, state_for_gen_pw.clone());
     })
     .button("Paste clipboard", move |s: &mut Cursive| {
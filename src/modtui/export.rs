@@ -29,6 +29,8 @@ use super::{main_window, AppState};
 use super::pwman_quit;
 use super::show_message;
 use crate::jots;
+#[cfg(feature = "pwmanclient")]
+use crate::secret::SecretString;
 
 const EDIT_OUT_NAME: &str = "outname";
 
@@ -91,7 +93,7 @@ fn success_message(siv: &mut Cursive, msg: &str, shared_state: Arc<Mutex<AppStat
 }
 
 #[cfg(feature = "pwmanclient")]
-fn check_cached_password(p: &SendSyncPersister, ref_pw: &Option<String>) -> bool {
+fn check_cached_password(p: &SendSyncPersister, ref_pw: &Option<SecretString>) -> bool {
     // At this point this operation should not fail
     let ref_pw = match ref_pw {
         Some(p) => p,
@@ -136,7 +138,7 @@ fn create_html(data: &jots::Jots) -> String {
     for key in data {
         let mut line = String::from("<tr>\n");
 
-        let text = match data.get(key) {
+        let text = match data.get_formatted(key) {
             Some(t) => t,
             None => { return res }
         };
@@ -0,0 +1,107 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, EditView, SelectView, Panel};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::get_selected_entry_name;
+use super::display_entry;
+use super::fill_entry_list;
+use super::visualize_if_modified;
+
+const FOLDER_EDIT_NAME: &str = "folderedit";
+const NUM_FOLDERS_VISIBLE: usize = 8;
+
+pub fn entry(s: &mut Cursive, state_for_move_entry: Arc<Mutex<AppState>>) {
+    let entry_name = match get_selected_entry_name(s) {
+        Some(name) => name,
+        None => {
+            show_message(s, "Unable to determine selected entry"); 
+            return; 
+        }
+    };
+
+    let (current_group, known_groups) = {
+        let state = state_for_move_entry.lock().unwrap();
+
+        let group = match state.store.get_entry(&entry_name) {
+            Some(e) => e.group,
+            None => {
+                show_message(s, "Unable to read value of entry");
+                return;
+            }
+        };
+
+        (group, state.store.get_groups())
+    };
+
+    let mut folder_list = SelectView::<String>::new();
+    folder_list.add_item("<top level>", String::from(""));
+
+    for i in known_groups {
+        folder_list.add_item(i.clone(), i);
+    }
+
+    let folder_list = folder_list.on_select(|s, group: &String| {
+        s.call_on_name(FOLDER_EDIT_NAME, |view: &mut EditView| { view.set_content(group.clone()); });
+    });
+
+    let res = Dialog::new()
+    .title("Rustpwman move entry to folder")
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(TextView::new(format!("Please enter the folder for '{}'. Use '/' to separate\nsub folders and leave it empty to move the entry to the top level.\n\n", entry_name)))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Folder: "))
+                .child(EditView::new()
+                    .content(current_group)
+                    .with_name(FOLDER_EDIT_NAME)
+                    .fixed_width(50))
+        )
+        .child(TextView::new("\n"))
+        .child(
+            Panel::new(folder_list
+                .scrollable()
+                .fixed_height(NUM_FOLDERS_VISIBLE))
+            .title("Existing folders")
+        )
+    )
+    .button("OK", move |s| {
+        let new_group = match s.call_on_name(FOLDER_EDIT_NAME, |view: &mut EditView| {view.get_content()}) {
+            Some(g) => g,
+            None => { show_message(s, "Unable to read folder name"); return }
+        };
+
+        if !state_for_move_entry.lock().unwrap().store.move_to_group(&entry_name, &new_group) {
+            show_message(s, "Moving entry failed"); 
+            return;            
+        }
+
+        visualize_if_modified(s, state_for_move_entry.clone());
+        fill_entry_list(s, state_for_move_entry.clone());
+        s.pop_layer();
+        display_entry(s, state_for_move_entry.clone(), &entry_name, true);
+    })
+    .button("Cancel", |s| { s.pop_layer(); });                
+    
+    s.add_layer(res);
+}
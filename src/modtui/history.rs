@@ -0,0 +1,141 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, SelectView, Panel, Checkbox};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::get_selected_entry_name;
+use super::display_entry;
+use super::visualize_if_modified;
+use crate::diff;
+use crate::jots::Entry;
+use crate::timestamp;
+
+const VERSION_LIST: &str = "versionlist";
+const VERSION_TEXT: &str = "versiontext";
+const SHOW_DIFF: &str = "showdiff";
+const NUM_VERSIONS_VISIBLE: usize = 15;
+
+// Shows either the selected version or its differences to the current value of the entry
+fn show_version(s: &mut Cursive, entry: &Entry) {
+    let index = match s.call_on_name(VERSION_LIST, |view: &mut SelectView<usize>| { view.selection() }) {
+        Some(Some(i)) => *i,
+        _ => return
+    };
+
+    let show_diff = match s.call_on_name(SHOW_DIFF, |view: &mut Checkbox| { view.is_checked() }) {
+        Some(c) => c,
+        None => false
+    };
+
+    let old_text = entry.history[index].value.to_formatted_string();
+
+    let text = if show_diff {
+        diff::diff_lines(&old_text, &entry.to_formatted_string()).join("\n")
+    } else {
+        old_text
+    };
+
+    s.call_on_name(VERSION_TEXT, |view: &mut TextView| { view.set_content(text); });
+}
+
+pub fn entry(s: &mut Cursive, state_for_history: Arc<Mutex<AppState>>) {
+    let entry_name = match get_selected_entry_name(s) {
+        Some(name) => name,
+        None => {
+            show_message(s, "Unable to determine selected entry"); 
+            return; 
+        }
+    };
+
+    let entry = match state_for_history.lock().unwrap().store.get_entry(&entry_name) {
+        Some(e) => e,
+        None => {
+            show_message(s, "Unable to read value of entry");
+            return;
+        }
+    };
+
+    if entry.history.len() == 0 {
+        show_message(s, "There are no previous versions of this entry");
+        return;
+    }
+
+    let mut version_list = SelectView::<usize>::new();
+
+    for (index, item) in entry.history.iter().enumerate() {
+        version_list.add_item(timestamp::format(item.timestamp), index);
+    }
+
+    let entry_for_select = entry.clone();
+    let entry_for_check = entry.clone();
+
+    let version_list = version_list.on_select(move |s, _: &usize| {
+        show_version(s, &entry_for_select);
+    });
+
+    let res = Dialog::new()
+    .title(format!("Rustpwman previous versions of '{}'", entry_name))
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(
+            LinearLayout::horizontal()
+            .child(Panel::new(version_list
+                    .with_name(VERSION_LIST)
+                    .scrollable()
+                    .fixed_height(NUM_VERSIONS_VISIBLE))
+                .title("Saved at"))
+            .child(Panel::new(TextView::new("")
+                    .with_name(VERSION_TEXT)
+                    .scrollable()
+                    .fixed_width(70)
+                    .fixed_height(NUM_VERSIONS_VISIBLE))
+                .title("Value"))
+        )
+        .child(TextView::new("\n"))
+        .child(
+            LinearLayout::horizontal()
+            .child(Checkbox::new()
+                .on_change(move |s, _| { show_version(s, &entry_for_check); })
+                .with_name(SHOW_DIFF))
+            .child(TextView::new(" Show differences to current value ('-' removed, '+' added)"))
+        )
+    )
+    .button("Restore", move |s| {
+        let index = match s.call_on_name(VERSION_LIST, |view: &mut SelectView<usize>| { view.selection() }) {
+            Some(Some(i)) => *i,
+            _ => { show_message(s, "No version selected"); return }
+        };
+
+        if !state_for_history.lock().unwrap().store.restore_version(&entry_name, index) {
+            show_message(s, "Restoring version failed"); 
+            return;
+        }
+
+        visualize_if_modified(s, state_for_history.clone());
+        s.pop_layer();
+        display_entry(s, state_for_history.clone(), &entry_name, true);
+    })
+    .button("Close", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+    show_version(s, &entry);
+}
@@ -14,10 +14,10 @@ limitations under the License. */
 
 
 use std::sync::mpsc::Sender;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use cursive::Cursive;
-use cursive::views::{Dialog, LinearLayout, TextView, EditView, DialogFocus};
+use cursive::views::{Dialog, LinearLayout, TextView, EditView, DialogFocus, Checkbox};
 use cursive::traits::*;
 use cursive::event::EventResult;
 use cursive::view::Selector::Name;
@@ -30,9 +30,11 @@ use crate::fcrypt;
 static PW_EDIT1: &str = "pwedit1";
 static PW_EDIT2: &str = "pwedit2";
 static DLG_INIT: &str = "pwinit";
+static KEY_FILE_EDIT: &str = "keyfileedit";
+static KEY_FILE_CREATE: &str = "keyfilecreate";
 
-fn verify_passwords(s: &mut Cursive, ok_cb: &Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>) {
-    verify_passwords_with_names(s, ok_cb, PW_EDIT1, PW_EDIT2, DLG_INIT);
+fn verify_passwords(s: &mut Cursive, ok_cb: &Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, kdf_id: fcrypt::KdfId) {
+    verify_passwords_with_names(s, ok_cb, PW_EDIT1, PW_EDIT2, DLG_INIT, kdf_id);
 }
 
 pub fn show_pw_select_error(siv: &mut Cursive, msg: &str, edit1: &'static str, edit2: &'static str, dlg: &'static str) {
@@ -62,7 +64,7 @@ pub fn show_pw_select_error(siv: &mut Cursive, msg: &str, edit1: &'static str, e
     );
 }
 
-fn verify_passwords_with_names(s: &mut Cursive, ok_cb: &Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, edit1: &'static str, edit2: &'static str, dlg: &'static str) {
+fn verify_passwords_with_names(s: &mut Cursive, ok_cb: &Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, edit1: &'static str, edit2: &'static str, dlg: &'static str, kdf_id: fcrypt::KdfId) {
     let pw1_text = match s.call_on_name(edit1, |view: &mut EditView| {view.get_content()}) {
         Some(s) => s,
         None => { show_message(s, "Unable to read password"); return }
@@ -83,7 +85,7 @@ fn verify_passwords_with_names(s: &mut Cursive, ok_cb: &Box<dyn Fn(&mut Cursive,
         return;
     }
 
-    if let Some(err) = fcrypt::check_password(&pw1_text) {
+    if let Some(err) = fcrypt::check_password(&pw1_text, kdf_id) {
         show_pw_select_error(s, &format!("Password incorrect: {:?}", err), edit1, edit2, dlg);
         return;        
     }
@@ -91,11 +93,51 @@ fn verify_passwords_with_names(s: &mut Cursive, ok_cb: &Box<dyn Fn(&mut Cursive,
     ok_cb(s, &pw2_text, false);
 }
 
-pub fn dialog(sndr: Arc<Sender<String>>, ok_cb: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>) -> impl View {
+// The key file which is selected in this dialog is stored in key_file before ok_cb is called. The new password is
+// checked against the limit of the KDF which is used for the new file.
+pub fn dialog(sndr: Arc<Sender<String>>, ok_cb: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, key_file: Arc<Mutex<Option<String>>>, kdf_id: fcrypt::KdfId) -> impl View {
     let sender = sndr.clone();
-    
+    let ok_cb = Arc::new(ok_cb);
+    let key_file_start = match &*key_file.lock().unwrap() {
+        Some(f) => f.clone(),
+        None => String::from("")
+    };
+
     let verify = move |s: &mut Cursive| {
-        verify_passwords(s, &ok_cb);
+        let key_file_text = match s.call_on_name(KEY_FILE_EDIT, |view: &mut EditView| {view.get_content()}) {
+            Some(s) => s,
+            None => { show_message(s, "Unable to read key file name"); return }
+        };
+
+        let create_key_file = match s.call_on_name(KEY_FILE_CREATE, |view: &mut Checkbox| {view.is_checked()}) {
+            Some(c) => c,
+            None => { show_message(s, "Unable to determine whether a key file should be created"); return }
+        };
+
+        let key_file_name = key_file_text.trim().to_string();
+
+        if create_key_file && (key_file_name == "") {
+            show_message(s, "Enter the name of the key file to create");
+            return;
+        }
+
+        let ok_cb_inner = ok_cb.clone();
+        let key_file_inner = key_file.clone();
+
+        // The key file is only created after the passwords have been verified
+        let with_key_file: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync> = Box::new(move |s, pw, is_cached| {
+            if create_key_file {
+                if let Err(e) = fcrypt::create_key_file(&key_file_name) {
+                    show_message(s, &format!("Unable to create key file: {:?}", e));
+                    return;
+                }
+            }
+
+            *key_file_inner.lock().unwrap() = if key_file_name == "" { None } else { Some(key_file_name.clone()) };
+            ok_cb_inner(s, pw, is_cached);
+        });
+
+        verify_passwords(s, &with_key_file, kdf_id);
     };
 
     let res = Dialog::new()
@@ -122,6 +164,20 @@ pub fn dialog(sndr: Arc<Sender<String>>, ok_cb: Box<dyn Fn(&mut Cursive, &String
                         .with_name(PW_EDIT2)
                         .fixed_width(PW_WIDTH))
             )
+            .child(TextView::new("\nOptionally select a key file which is needed in\naddition to the password.\n\n"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Key file       : "))
+                    .child(EditView::new()
+                        .content(key_file_start)
+                        .with_name(KEY_FILE_EDIT)
+                        .fixed_width(PW_WIDTH))
+            )
+            .child(
+                LinearLayout::horizontal()
+                    .child(Checkbox::new().with_name(KEY_FILE_CREATE))
+                    .child(TextView::new(" Create new key file"))
+            )
         )
         .button("OK", verify)
         .button("Cancel", move |s| pwman_quit(s, sender.clone(), String::from("")))
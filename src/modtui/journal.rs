@@ -0,0 +1,93 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+use std::fs;
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use super::AppState;
+use super::show_message;
+use super::redraw_tui;
+use super::visualize_if_modified;
+use crate::journal;
+
+const FAILED_JOURNAL_SUFFIX: &str = ".failed";
+
+fn replay(s: &mut Cursive, state_for_replay: Arc<Mutex<AppState>>, file_name: &String, password: &String) {
+    let res = state_for_replay.lock().unwrap().store.replay_journal(file_name, password);
+
+    match res {
+        Ok(num_changes) => {
+            visualize_if_modified(s, state_for_replay.clone());
+            redraw_tui(s, state_for_replay.clone());
+            show_message(s, &format!("{} change(s) have been replayed. Save the file in order to keep them.", num_changes));
+        },
+        Err(e) => {
+            // Keep the journal as the next change would overwrite it
+            let failed_name = format!("{}{}", file_name, FAILED_JOURNAL_SUFFIX);
+            let _ = fs::rename(file_name, &failed_name);
+            show_message(s, &format!("Unable to replay journal: {:?}\n\nIt has been moved to '{}'", e, failed_name));
+        }
+    }
+}
+
+// Starts to record all changes in a journal. If a journal of an earlier session exists the user
+// is asked whether the changes recorded in it should be replayed.
+pub fn start(s: &mut Cursive, state_for_journal: Arc<Mutex<AppState>>) {
+    let (file_name, password, res) = {
+        let mut state = state_for_journal.lock().unwrap();
+
+        let pw = match &state.password {
+            Some(p) => p.clone(),
+            None => return
+        };
+
+        let file_name = journal::file_name_for(&state.store_id);
+        let res = state.store.enable_journal(&file_name, &pw);
+
+        (file_name, pw, res)
+    };
+
+    match res {
+        Ok(true) => (),
+        // Stores without a real password do not use a journal
+        Ok(false) => return,
+        Err(e) => {
+            show_message(s, &format!("Unable to start journal: {:?}", e));
+            return;
+        }
+    }
+
+    if !journal::exists(&file_name) {
+        return;
+    }
+
+    let file_name_discard = file_name.clone();
+    let state_for_replay = state_for_journal.clone();
+
+    let res = Dialog::text("Unsaved changes of an earlier session have been found. Do you want to replay them?")
+        .title("Rustpwman")
+        .button("Yes", move |s| {
+            s.pop_layer();
+            replay(s, state_for_replay.clone(), &file_name, &password);
+        })
+        .button("No", move |s| {
+            s.pop_layer();
+            journal::remove(&file_name_discard);
+        });
+
+    s.add_layer(res);
+}
@@ -0,0 +1,175 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, Panel};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::redraw_tui;
+use super::visualize_if_modified;
+use crate::jots::Entry;
+use crate::merge::{MergeResult, Resolution};
+use crate::theme::get_theme;
+
+const NUM_LINES_VISIBLE: usize = 15;
+
+pub type MergeDoneCallback = Arc<dyn Fn(&mut Cursive, Arc<Mutex<MergeResult>>, bool) + Send + Sync>;
+
+fn version_panel(title: &str, value: &Option<(String, Entry)>) -> Box<dyn View> {
+    let (panel_title, text) = match value {
+        Some((name, entry)) => (format!("{}: {}", title, name), entry.to_formatted_string()),
+        None => (format!("{}: deleted", title), String::from(""))
+    };
+
+    return Box::new(Panel::new(TextView::new(text)
+            .scrollable()
+            .fixed_size((45, NUM_LINES_VISIBLE)))
+        .title(panel_title));
+}
+
+fn on_resolve(s: &mut Cursive, result: Arc<Mutex<MergeResult>>, r: Resolution, total: usize, on_done: MergeDoneCallback) {
+    result.lock().unwrap().resolve(r);
+    s.pop_layer();
+    show_next_conflict(s, result, total, on_done);
+}
+
+fn show_next_conflict(s: &mut Cursive, result: Arc<Mutex<MergeResult>>, total: usize, on_done: MergeDoneCallback) {
+    let (conflict, remaining) = {
+        let r = result.lock().unwrap();
+
+        if r.is_resolved() {
+            drop(r);
+            on_done(s, result.clone(), true);
+            return;
+        }
+
+        (r.conflicts[0].clone(), r.conflicts.len())
+    };
+
+    let result_local = result.clone();
+    let result_remote = result.clone();
+    let result_both = result.clone();
+    let result_cancel = result.clone();
+    let done_local = on_done.clone();
+    let done_remote = on_done.clone();
+    let done_both = on_done.clone();
+    let done_cancel = on_done.clone();
+
+    let res = Dialog::new()
+    .title(format!("Rustpwman merge conflict {} of {}", total - remaining + 1, total))
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(TextView::new(format!("{}. Please select the version to keep.\n\n", conflict.description)))
+        .child(
+            LinearLayout::horizontal()
+            .child(version_panel("Local", &conflict.local))
+            .child(version_panel("Remote", &conflict.remote))
+        )
+    )
+    .button("Keep local", move |s| { on_resolve(s, result_local.clone(), Resolution::Local, total, done_local.clone()); })
+    .button("Keep remote", move |s| { on_resolve(s, result_remote.clone(), Resolution::Remote, total, done_remote.clone()); })
+    .button("Keep both", move |s| { on_resolve(s, result_both.clone(), Resolution::Both, total, done_both.clone()); })
+    .button("Cancel", move |s| {
+        s.pop_layer();
+        done_cancel(s, result_cancel.clone(), false);
+    });
+
+    s.add_layer(res);
+}
+
+// Lets the user resolve all conflicts of result one after another. on_done is called when all conflicts
+// are resolved or the user has cancelled the merge.
+pub fn resolve_conflicts(s: &mut Cursive, result: Arc<Mutex<MergeResult>>, on_done: MergeDoneCallback) {
+    let total = result.lock().unwrap().conflicts.len();
+    show_next_conflict(s, result, total, on_done);
+}
+
+pub fn stored(s: &mut Cursive, state_for_merge: Arc<Mutex<AppState>>) {
+    let (merge_result, remote_contents, remote_hash) = {
+        let mut guard = state_for_merge.lock().unwrap();
+        let state = &mut *guard;
+
+        let pw = match &state.password {
+            Some(p) => p.clone(),
+            None => {
+                drop(guard);
+                show_message(s, "No password available");
+                return;
+            }
+        };
+
+        match state.store.retrieve_other(&mut state.persister, &pw) {
+            Ok((contents, trash, hash)) => (state.store.merge_with(&contents, &trash), contents, Some(hash)),
+            Err(e) => {
+                drop(guard);
+                show_message(s, &format!("Unable to read saved version: {:?}", e));
+                return;
+            }
+        }
+    };
+
+    let num_conflicts = merge_result.conflicts.len();
+    let result = Arc::new(Mutex::new(merge_result));
+
+    let on_done: MergeDoneCallback = Arc::new(move |s: &mut Cursive, result: Arc<Mutex<MergeResult>>, completed: bool| {
+        if !completed {
+            show_message(s, "Merge cancelled");
+            return;
+        }
+
+        let merged = std::mem::replace(&mut *result.lock().unwrap(), MergeResult::new());
+
+        if !state_for_merge.lock().unwrap().store.apply_merge(merged, &remote_contents, &remote_hash) {
+            show_message(s, "Unable to apply merge result");
+            return;
+        }
+
+        visualize_if_modified(s, state_for_merge.clone());
+        redraw_tui(s, state_for_merge.clone());
+        show_message(s, &format!("Merge successful. {} conflict(s) were resolved.\nSave the file in order to store the merged version.", num_conflicts));
+    });
+
+    resolve_conflicts(s, result, on_done);
+}
+
+// Lets the user resolve the conflicts of result in a TUI of its own. This is used by the merge command.
+// Returns false if the user has cancelled the merge.
+pub fn merge_main(result: Arc<Mutex<MergeResult>>) -> bool {
+    let mut siv = cursive::default();
+    let completed = Arc::new(Mutex::new(false));
+    let completed_done = completed.clone();
+
+    let on_done: MergeDoneCallback = Arc::new(move |s: &mut Cursive, _result: Arc<Mutex<MergeResult>>, c: bool| {
+        *completed_done.lock().unwrap() = c;
+        s.quit();
+    });
+
+    match get_theme() {
+        Ok(t) => siv.set_theme(t),
+        Err(_) => ()
+    }
+
+    resolve_conflicts(&mut siv, result, on_done);
+    siv.run();
+
+    let res = *completed.lock().unwrap();
+
+    return res;
+}
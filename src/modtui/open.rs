@@ -18,6 +18,7 @@ use cursive::Cursive;
 use super::AppState;
 use super::show_message;
 use super::pwentry::show_pw_error;
+use crate::secret::SecretString;
 
 pub fn storage(s: &mut Cursive, password: &String, state: AppState) -> Option<AppState> {
     let mut state = state;
@@ -48,7 +49,7 @@ pub fn storage(s: &mut Cursive, password: &String, state: AppState) -> Option<Ap
         }
     }
 
-    state.password = Some(password.clone());
+    state.password = Some(SecretString::new(password.clone()));
 
     return Some(state);
 }
\ No newline at end of file
@@ -24,21 +24,34 @@ use super::show_message;
 use super::init::show_pw_select_error;
 use super::PW_WIDTH;
 use super::save;
+use crate::fcrypt;
+use crate::secret::SecretString;
 #[cfg(feature = "pwmanclient")]
 use super::cache;
 
 static PW_EDIT1_CH: &str = "pwchedit1";
 static PW_EDIT2_CH: &str = "pwchedit2";
 static DLG_PW_CH: &str = "pwchangedlg";
+static KDF_PARAMS_EDIT_CH: &str = "pwchkdfparams";
 
 
 pub fn change(s: &mut Cursive, state_for_pw_change: Arc<Mutex<AppState>>) {
+    if state_for_pw_change.lock().unwrap().store.recipients.len() > 0 {
+        show_message(s, "This file is encrypted for public keys and does not use a password");
+        return;
+    }
+
+    let (kdf_id, kdf_params) = {
+        let store = &state_for_pw_change.lock().unwrap().store;
+        (store.kdf_id, store.kdf_params)
+    };
+
     let res = Dialog::new()
         .title("Rustpwman change password")
         .padding_lrtb(2, 2, 1, 1)
         .content(
             LinearLayout::vertical()
-            .child(TextView::new("Enter a new password.\n\n"))
+            .child(TextView::new(format!("Enter a new password. KDF parameters for {} have the form m,t,p.\n\n", kdf_id.to_str())))
             .child(
                 LinearLayout::horizontal()
                     .child(TextView::new("New Password   : "))
@@ -56,6 +69,15 @@ pub fn change(s: &mut Cursive, state_for_pw_change: Arc<Mutex<AppState>>) {
                         .with_name(PW_EDIT2_CH)
                         .fixed_width(PW_WIDTH))
             )
+            .child(TextView::new("\n"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("KDF Parameters : "))
+                    .child(EditView::new()
+                        .content(kdf_params.to_string())
+                        .with_name(KDF_PARAMS_EDIT_CH)
+                        .fixed_width(PW_WIDTH))
+            )
         )
         .button("OK", move |s| {
             let pw1_text = match s.call_on_name("pwchedit1", |view: &mut EditView| {view.get_content()}) {
@@ -78,9 +100,34 @@ pub fn change(s: &mut Cursive, state_for_pw_change: Arc<Mutex<AppState>>) {
                 return;
             }
 
-            let new_pw: String = (&pw1_text).to_string();
+            if let Some(err) = fcrypt::check_password(&pw1_text, kdf_id) {
+                show_pw_select_error(s, &format!("Password incorrect: {}", err), PW_EDIT1_CH, PW_EDIT2_CH, DLG_PW_CH);
+                return;
+            }
+
+            let params_text = match s.call_on_name(KDF_PARAMS_EDIT_CH, |view: &mut EditView| {view.get_content()}) {
+                Some(s) => s,
+                None => { show_message(s, "Unable to read KDF parameters"); return }
+            };
+
+            let new_params = match fcrypt::KdfParams::from_str(&params_text) {
+                Some(p) if p.check(kdf_id).is_none() => p,
+                _ => {
+                    show_message(s, &format!("KDF parameters are not valid. Default is {}", kdf_id.default_params().to_string()));
+                    return;
+                }
+            };
+
+            let new_pw = SecretString::new((&pw1_text).to_string());
+
+            {
+                let mut state = state_for_pw_change.lock().unwrap();
+                state.store.set_kdf_params(new_params);
+                // ignore result. The journal is only a safety net and the store is saved right away.
+                let _ = state.store.set_journal_password(&new_pw);
+                state.password = Some(new_pw);
+            }
 
-            state_for_pw_change.lock().unwrap().password = Some(new_pw);
             save::storage(s, state_for_pw_change.clone());
             s.pop_layer();
 
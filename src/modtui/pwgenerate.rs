@@ -27,7 +27,7 @@ use crate::pwgen::GenerationStrategy;
 
 use super::AppState;
 use super::show_message;
-use super::edit::insert_into_entry;
+use super::edit::set_password_field;
 use super::PW_MAX_SEC_LEVEL;
 
 const SLIDER_SEC_NAME: &str = "securityslider";
@@ -190,7 +190,7 @@ fn on_ok_clicked(s: &mut Cursive, state_for_gen_pw: Arc<Mutex<AppState>>, strate
         }
     };    
 
-    insert_into_entry(s, new_pw);
+    set_password_field(s, new_pw);
     s.pop_layer();        
 }
 
@@ -57,7 +57,7 @@ fn get_entries_int(state_for_q_clear: Arc<Mutex<AppState>>, clear: bool) -> Stri
     let mut res = String::from("");
 
     for i in &state.entry_queue {
-        let entry_data = match state.store.get(i) {
+        let entry_data = match state.store.get_formatted(i) {
             Some(t) => t,
             None => {
                 continue;
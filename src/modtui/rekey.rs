@@ -0,0 +1,199 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, EditView, RadioGroup};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::init::show_pw_select_error;
+use super::PW_WIDTH;
+use super::save;
+use crate::fcrypt;
+use crate::secret::SecretString;
+#[cfg(feature = "pwmanclient")]
+use super::cache;
+
+static PW_EDIT1_RK: &str = "pwrekeyedit1";
+static PW_EDIT2_RK: &str = "pwrekeyedit2";
+static DLG_REKEY: &str = "rekeydlg";
+static KDF_PARAMS_EDIT_RK: &str = "rekeykdfparams";
+
+
+pub fn rekey(s: &mut Cursive, state_for_rekey: Arc<Mutex<AppState>>) {
+    let (cipher_id, kdf_id, kdf_params) = {
+        let store = &state_for_rekey.lock().unwrap().store;
+        // A store which was not read from a file uses the cipher which is used for new files
+        let cipher_id = match store.cipher_id {
+            Some(c) => c,
+            None => (store.cr_gen)(store.kdf, store.kdf_id).cipher_id()
+        };
+
+        (cipher_id, store.kdf_id, store.kdf_params)
+    };
+
+    let mut cipher_group: RadioGroup<fcrypt::CipherId> = RadioGroup::new();
+    let mut kdf_group: RadioGroup<fcrypt::KdfId> = RadioGroup::new();
+
+    let mut linear_layout_cipher = LinearLayout::horizontal()
+        .child(TextView::new("Cipher         : "));
+
+    let mut linear_layout_kdf = LinearLayout::horizontal()
+        .child(TextView::new("KDF            : "));
+
+    for i in &fcrypt::CipherId::get_known_ids() {
+        let mut b = cipher_group.button(*i, i.to_str());
+
+        if *i == cipher_id {
+            b.select();
+        }
+
+        linear_layout_cipher.add_child(b);
+        linear_layout_cipher.add_child(TextView::new(" "));
+    }
+
+    for i in &fcrypt::KdfId::get_known_ids() {
+        let label = if i.is_deprecated() { format!("{} (deprecated)", i.to_str()) } else { i.to_string() };
+        let mut b = kdf_group.button(*i, label);
+
+        if *i == kdf_id {
+            b.select();
+        }
+
+        linear_layout_kdf.add_child(b);
+        linear_layout_kdf.add_child(TextView::new(" "));
+    }
+
+    // Parameters of one KDF make no sense for another one
+    kdf_group.set_on_change(move |s, new_kdf_id| {
+        let new_params = if *new_kdf_id == kdf_id { kdf_params } else { new_kdf_id.default_params() };
+        s.call_on_name(KDF_PARAMS_EDIT_RK, |view: &mut EditView| { view.set_content(new_params.to_string()) });
+    });
+
+    let res = Dialog::new()
+        .title("Rustpwman reencrypt file")
+        .padding_lrtb(2, 2, 1, 1)
+        .content(
+            LinearLayout::vertical()
+            .child(TextView::new("Select cipher and KDF. KDF parameters have the form m,t,p. Leave the\npassword fields empty in order to keep the current password.\n\n"))
+            .child(linear_layout_cipher)
+            .child(TextView::new("\n"))
+            .child(linear_layout_kdf)
+            .child(TextView::new("\n"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("KDF Parameters : "))
+                    .child(EditView::new()
+                        .content(kdf_params.to_string())
+                        .with_name(KDF_PARAMS_EDIT_RK)
+                        .fixed_width(PW_WIDTH))
+            )
+            .child(TextView::new("\n"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("New Password   : "))
+                    .child(EditView::new()
+                        .secret()
+                        .with_name(PW_EDIT1_RK)
+                        .fixed_width(PW_WIDTH))
+            )
+            .child(TextView::new("\n"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Verify Password: "))
+                    .child(EditView::new()
+                        .secret()
+                        .with_name(PW_EDIT2_RK)
+                        .fixed_width(PW_WIDTH))
+            )
+        )
+        .button("OK", move |s| {
+            let pw1_text = match s.call_on_name(PW_EDIT1_RK, |view: &mut EditView| {view.get_content()}) {
+                Some(s) => s,
+                None => { show_message(s, "Unable to read password"); return }
+            };
+
+            let pw2_text = match s.call_on_name(PW_EDIT2_RK, |view: &mut EditView| {view.get_content()}) {
+                Some(s) => s,
+                None => { show_message(s, "Unable to read password"); return }
+            };
+
+            if pw1_text != pw2_text {
+                show_pw_select_error(s, "Passwords not equal!", PW_EDIT1_RK, PW_EDIT2_RK, DLG_REKEY);
+                return;
+            }
+
+            let params_text = match s.call_on_name(KDF_PARAMS_EDIT_RK, |view: &mut EditView| {view.get_content()}) {
+                Some(s) => s,
+                None => { show_message(s, "Unable to read KDF parameters"); return }
+            };
+
+            let new_cipher_id = *cipher_group.selection();
+            let new_kdf_id = *kdf_group.selection();
+
+            if new_kdf_id.is_deprecated() && (new_kdf_id != kdf_id) {
+                show_message(s, &format!("The KDF {} is deprecated and can not be selected", new_kdf_id.to_str()));
+                return;
+            }
+
+            let new_params = match fcrypt::KdfParams::from_str(&params_text) {
+                Some(p) if p.check(new_kdf_id).is_none() => p,
+                _ => {
+                    show_message(s, &format!("KDF parameters are not valid. Default is {}", new_kdf_id.default_params().to_string()));
+                    return;
+                }
+            };
+
+            let pw_changed = pw1_text.len() != 0;
+
+            // The current password has to be checked as well because the new KDF may have a lower limit
+            let pw_to_check = match (pw_changed, &state_for_rekey.lock().unwrap().password) {
+                (false, Some(p)) => SecretString::new(p.to_string()),
+                _ => SecretString::new((&pw1_text).to_string())
+            };
+
+            if let Some(err) = fcrypt::check_password(&pw_to_check, new_kdf_id) {
+                show_pw_select_error(s, &format!("Password incorrect: {}", err), PW_EDIT1_RK, PW_EDIT2_RK, DLG_REKEY);
+                return;
+            }
+
+            {
+                let mut state = state_for_rekey.lock().unwrap();
+                state.store.rekey(new_cipher_id, new_kdf_id, new_params);
+
+                if pw_changed {
+                    let new_pw = SecretString::new((&pw1_text).to_string());
+                    // ignore result. The journal is only a safety net and the store is saved right away.
+                    let _ = state.store.set_journal_password(&new_pw);
+                    state.password = Some(new_pw);
+                }
+            }
+
+            save::storage(s, state_for_rekey.clone());
+            s.pop_layer();
+
+            #[cfg(feature = "pwmanclient")]
+            if pw_changed {
+                cache::uncache_password(s, state_for_rekey.clone());
+            }
+        })
+        .button("Cancel", |s| { s.pop_layer(); })
+        .with_name(DLG_REKEY);
+
+    s.add_layer(res);
+}
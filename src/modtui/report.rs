@@ -0,0 +1,103 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, EditView, SelectView, Panel};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::display_entry;
+use crate::timestamp;
+
+const DAYS_EDIT_NAME: &str = "reportdays";
+const REPORT_LIST: &str = "reportlist";
+const NUM_ENTRIES_VISIBLE: usize = 15;
+pub const DEFAULT_MAX_AGE_DAYS: u64 = 365;
+
+// Formats one line of the password age report
+pub fn format_report_line(name: &String, changed: timestamp::Timestamp, now: timestamp::Timestamp) -> String {
+    if timestamp::is_unknown(&changed) {
+        return format!("{}: never recorded", name);
+    }
+
+    return format!("{}: {} ({} days)", name, timestamp::format(changed), timestamp::days_between(changed, now));
+}
+
+fn fill_report(s: &mut Cursive, state_for_report: Arc<Mutex<AppState>>) {
+    let days_str = match s.call_on_name(DAYS_EDIT_NAME, |view: &mut EditView| {view.get_content()}) {
+        Some(d) => d,
+        None => { show_message(s, "Unable to read number of days"); return }
+    };
+
+    let days = match days_str.trim().parse::<u64>() {
+        Ok(d) => d,
+        Err(_) => { show_message(s, "Number of days is not valid"); return }
+    };
+
+    let now = timestamp::now();
+    let stale = state_for_report.lock().unwrap().store.get_stale_entries(now, days);
+
+    s.call_on_name(REPORT_LIST, |view: &mut SelectView<String>| {
+        view.clear();
+
+        for (name, changed) in stale {
+            view.add_item(format_report_line(&name, changed, now), name);
+        }
+    });
+}
+
+pub fn password_age(s: &mut Cursive, state_for_report: Arc<Mutex<AppState>>) {
+    let state_for_show = state_for_report.clone();
+    let state_for_submit = state_for_report.clone();
+
+    let report_list = SelectView::<String>::new()
+    .on_submit(move |s, name: &String| {
+        s.pop_layer();
+        display_entry(s, state_for_submit.clone(), name, true);
+    });
+
+    let res = Dialog::new()
+    .title("Rustpwman password age report")
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(TextView::new("Entries whose secrets have not been changed for at least the given number of days.\nPress Enter to show an entry.\n\n"))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Days: "))
+                .child(EditView::new()
+                    .content(DEFAULT_MAX_AGE_DAYS.to_string())
+                    .with_name(DAYS_EDIT_NAME)
+                    .fixed_width(10))
+        )
+        .child(TextView::new("\n"))
+        .child(
+            Panel::new(report_list
+                .with_name(REPORT_LIST)
+                .scrollable()
+                .fixed_width(70)
+                .fixed_height(NUM_ENTRIES_VISIBLE))
+            .title("Entries")
+        )
+    )
+    .button("Show", move |s| { fill_report(s, state_for_show.clone()); })
+    .button("Close", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+    fill_report(s, state_for_report);
+}
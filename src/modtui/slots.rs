@@ -0,0 +1,184 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, EditView, SelectView, Panel};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::init::show_pw_select_error;
+use super::PW_WIDTH;
+use super::save;
+
+const SLOT_LIST: &str = "keyslotlist";
+const SLOT_LABEL_EDIT: &str = "keyslotlabel";
+const PW_EDIT1_SLOT: &str = "keyslotpw1";
+const PW_EDIT2_SLOT: &str = "keyslotpw2";
+const DLG_ADD_SLOT: &str = "keyslotadddlg";
+const NUM_ITEMS_VISIBLE: usize = 10;
+
+fn fill_slot_list(s: &mut Cursive, state: Arc<Mutex<AppState>>) {
+    let (labels, unlocked) = {
+        let store = &state.lock().unwrap().store;
+        (store.key_slot_labels(), store.key_slots.as_ref().and_then(|k| k.unlocked.clone()))
+    };
+
+    s.call_on_name(SLOT_LIST, |view: &mut SelectView<String>| {
+        view.clear();
+
+        for l in labels {
+            if unlocked.as_ref() == Some(&l) {
+                view.add_item(format!("{} (current)", l), l);
+            } else {
+                view.add_item(l.clone(), l);
+            }
+        }
+    });
+}
+
+fn add(s: &mut Cursive, state_for_add: Arc<Mutex<AppState>>) {
+    let (kdf_id, kdf_params) = {
+        let store = &state_for_add.lock().unwrap().store;
+        (store.kdf_id, store.kdf_params)
+    };
+
+    let res = Dialog::new()
+        .title("Rustpwman add key slot")
+        .padding_lrtb(2, 2, 1, 1)
+        .content(
+            LinearLayout::vertical()
+            .child(TextView::new(format!("The new key slot uses {} with the parameters {}.\n\n", kdf_id.to_str(), kdf_params.to_string())))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Label          : "))
+                    .child(EditView::new()
+                        .with_name(SLOT_LABEL_EDIT)
+                        .fixed_width(PW_WIDTH))
+            )
+            .child(TextView::new("\n"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Password       : "))
+                    .child(EditView::new()
+                        .secret()
+                        .with_name(PW_EDIT1_SLOT)
+                        .fixed_width(PW_WIDTH))
+            )
+            .child(TextView::new("\n"))
+            .child(
+                LinearLayout::horizontal()
+                    .child(TextView::new("Verify Password: "))
+                    .child(EditView::new()
+                        .secret()
+                        .with_name(PW_EDIT2_SLOT)
+                        .fixed_width(PW_WIDTH))
+            )
+        )
+        .button("OK", move |s| {
+            let label = match s.call_on_name(SLOT_LABEL_EDIT, |view: &mut EditView| {view.get_content()}) {
+                Some(l) => l.trim().to_string(),
+                None => { show_message(s, "Unable to read label"); return }
+            };
+
+            let pw1_text = match s.call_on_name(PW_EDIT1_SLOT, |view: &mut EditView| {view.get_content()}) {
+                Some(s) => s,
+                None => { show_message(s, "Unable to read password"); return }
+            };
+
+            let pw2_text = match s.call_on_name(PW_EDIT2_SLOT, |view: &mut EditView| {view.get_content()}) {
+                Some(s) => s,
+                None => { show_message(s, "Unable to read password"); return }
+            };
+
+            if pw1_text != pw2_text {
+                show_pw_select_error(s, "Passwords not equal!", PW_EDIT1_SLOT, PW_EDIT2_SLOT, DLG_ADD_SLOT);
+                return;
+            }
+
+            if pw1_text.len() == 0 {
+                show_pw_select_error(s, "Password is empty", PW_EDIT1_SLOT, PW_EDIT2_SLOT, DLG_ADD_SLOT);
+                return;
+            }
+
+            let res = state_for_add.lock().unwrap().store.add_key_slot(&label, &pw1_text, kdf_id, kdf_params);
+
+            if let Err(e) = res {
+                show_message(s, &format!("Unable to add key slot: {}", e));
+                return;
+            }
+
+            s.pop_layer();
+            save::storage(s, state_for_add.clone());
+            fill_slot_list(s, state_for_add.clone());
+        })
+        .button("Cancel", |s| { s.pop_layer(); })
+        .with_name(DLG_ADD_SLOT);
+
+    s.add_layer(res);
+}
+
+fn revoke(s: &mut Cursive, state_for_revoke: Arc<Mutex<AppState>>) {
+    let label = match s.call_on_name(SLOT_LIST, |view: &mut SelectView<String>| { view.selection() }) {
+        Some(Some(l)) => (*l).clone(),
+        _ => { show_message(s, "No key slot selected"); return }
+    };
+
+    let res = Dialog::new()
+        .title("Rustpwman revoke key slot")
+        .padding_lrtb(2, 2, 1, 1)
+        .content(TextView::new(format!("Key slot '{}' will be revoked. Continue?", label)))
+        .button("OK", move |s| {
+            let res = state_for_revoke.lock().unwrap().store.revoke_key_slot(&label);
+            s.pop_layer();
+
+            if let Err(e) = res {
+                show_message(s, &format!("Unable to revoke key slot: {}", e));
+                return;
+            }
+
+            save::storage(s, state_for_revoke.clone());
+            fill_slot_list(s, state_for_revoke.clone());
+        })
+        .button("Cancel", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+}
+
+pub fn show(s: &mut Cursive, state_for_slots: Arc<Mutex<AppState>>) {
+    let state_for_add = state_for_slots.clone();
+    let state_for_revoke = state_for_slots.clone();
+
+    let res = Dialog::new()
+        .title("Rustpwman key slots")
+        .padding_lrtb(2, 2, 1, 1)
+        .content(
+            LinearLayout::vertical()
+            .child(TextView::new("Each key slot allows to open the file with its own password.\nThe file does not use key slots if the list is empty.\n\n"))
+            .child(Panel::new(SelectView::<String>::new()
+                    .with_name(SLOT_LIST)
+                    .scrollable()
+                    .fixed_height(NUM_ITEMS_VISIBLE))
+                .title("Key slots"))
+        )
+        .button("Add ...", move |s| { add(s, state_for_add.clone()); })
+        .button("Revoke ...", move |s| { revoke(s, state_for_revoke.clone()); })
+        .button("Close", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+    fill_slot_list(s, state_for_slots);
+}
@@ -0,0 +1,151 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, EditView};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::get_selected_entry_name;
+use super::display_entry;
+use super::redraw_tui;
+use super::visualize_if_modified;
+use crate::tagfilter;
+use crate::tagfilter::TagFilter;
+
+const TAGS_EDIT_NAME: &str = "tagsedit";
+const FILTER_EDIT_NAME: &str = "filteredit";
+
+fn known_tags_text(state: Arc<Mutex<AppState>>) -> String {
+    let tags = state.lock().unwrap().store.get_tags();
+
+    if tags.len() == 0 {
+        return String::from("No tags are in use yet.");
+    }
+
+    return format!("Tags in use: {}", tags.join(", "));
+}
+
+pub fn entry(s: &mut Cursive, state_for_tags: Arc<Mutex<AppState>>) {
+    let entry_name = match get_selected_entry_name(s) {
+        Some(name) => name,
+        None => {
+            show_message(s, "Unable to determine selected entry"); 
+            return; 
+        }
+    };
+
+    let current_tags = match state_for_tags.lock().unwrap().store.get_entry(&entry_name) {
+        Some(e) => e.tags.join(", "),
+        None => { show_message(s, "Unable to read value of entry"); return }
+    };
+
+    let res = Dialog::new()
+    .title("Rustpwman edit tags")
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(TextView::new(format!("Please enter the tags of '{}' separated by commas or spaces.\n\n", entry_name)))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Tags: "))
+                .child(EditView::new()
+                    .content(current_tags)
+                    .with_name(TAGS_EDIT_NAME)
+                    .fixed_width(60))
+        )
+        .child(TextView::new("\n"))
+        .child(TextView::new(known_tags_text(state_for_tags.clone())))
+    )
+    .button("OK", move |s| {
+        let tags_text = match s.call_on_name(TAGS_EDIT_NAME, |view: &mut EditView| {view.get_content()}) {
+            Some(t) => t,
+            None => { show_message(s, "Unable to read tags"); return }
+        };
+
+        let tags: Vec<String> = tags_text.split(|c: char| c.is_whitespace() || (c == tagfilter::TAG_SEPARATOR))
+            .filter(|t| *t != "")
+            .map(|t| String::from(t))
+            .collect();
+
+        if !state_for_tags.lock().unwrap().store.set_tags(&entry_name, &tags) {
+            show_message(s, &tagfilter::invalid_tag_message());
+            return;            
+        }
+
+        s.pop_layer();
+        visualize_if_modified(s, state_for_tags.clone());
+        redraw_tui(s, state_for_tags.clone());
+        display_entry(s, state_for_tags.clone(), &entry_name, true);
+    })
+    .button("Cancel", |s| { s.pop_layer(); });                
+    
+    s.add_layer(res);
+}
+
+pub fn filter(s: &mut Cursive, state_for_filter: Arc<Mutex<AppState>>) {
+    let current_expression = match &state_for_filter.lock().unwrap().tag_filter {
+        Some(f) => f.get_expression().clone(),
+        None => String::from("")
+    };
+
+    let res = Dialog::new()
+    .title("Rustpwman filter by tags")
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::vertical()
+        .child(TextView::new("Only entries which match the tag expression are shown. All terms\nseparated by spaces have to match. Use '|' to separate alternatives\nand '!' to negate a tag, e.g. 'prod customer-x|customer-y !shared'.\n\n"))
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Expression: "))
+                .child(EditView::new()
+                    .content(current_expression)
+                    .with_name(FILTER_EDIT_NAME)
+                    .fixed_width(54))
+        )
+        .child(TextView::new("\n"))
+        .child(TextView::new(known_tags_text(state_for_filter.clone())))
+    )
+    .button("OK", move |s| {
+        let expression = match s.call_on_name(FILTER_EDIT_NAME, |view: &mut EditView| {view.get_content()}) {
+            Some(t) => t,
+            None => { show_message(s, "Unable to read tag expression"); return }
+        };
+
+        let tag_filter = match TagFilter::parse(&expression) {
+            Ok(f) => f,
+            Err(e) => { show_message(s, &e); return }
+        };
+
+        state_for_filter.lock().unwrap().tag_filter = Some(tag_filter);
+
+        s.pop_layer();
+        visualize_if_modified(s, state_for_filter.clone());
+        redraw_tui(s, state_for_filter.clone());
+    })
+    .button("Cancel", |s| { s.pop_layer(); });                
+    
+    s.add_layer(res);
+}
+
+pub fn clear_filter(s: &mut Cursive, state_for_filter: Arc<Mutex<AppState>>) {
+    state_for_filter.lock().unwrap().tag_filter = None;
+
+    visualize_if_modified(s, state_for_filter.clone());
+    redraw_tui(s, state_for_filter.clone());
+}
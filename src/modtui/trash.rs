@@ -0,0 +1,141 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::{Dialog, LinearLayout, TextView, SelectView, Panel};
+use cursive::traits::*;
+
+use super::AppState;
+use super::show_message;
+use super::display_entry;
+use super::fill_entry_list;
+use super::visualize_if_modified;
+use crate::timestamp;
+
+const TRASH_LIST: &str = "trashlist";
+const TRASH_TEXT: &str = "trashtext";
+const NUM_ITEMS_VISIBLE: usize = 15;
+
+fn fill_trash_list(s: &mut Cursive, state: Arc<Mutex<AppState>>) {
+    let trash = state.lock().unwrap().store.trash.clone();
+
+    s.call_on_name(TRASH_LIST, |view: &mut SelectView<usize>| {
+        view.clear();
+
+        for (index, item) in trash.iter().enumerate() {
+            view.add_item(format!("{} ({})", item.key, timestamp::format(item.deleted)), index);
+        }
+    });
+
+    show_item(s, state);
+}
+
+fn show_item(s: &mut Cursive, state: Arc<Mutex<AppState>>) {
+    let text = match s.call_on_name(TRASH_LIST, |view: &mut SelectView<usize>| { view.selection() }) {
+        Some(Some(i)) => {
+            match state.lock().unwrap().store.trash.get(*i) {
+                Some(item) => item.value.to_formatted_string(),
+                None => String::from("")
+            }
+        },
+        _ => String::from("")
+    };
+
+    s.call_on_name(TRASH_TEXT, |view: &mut TextView| { view.set_content(text); });
+}
+
+fn get_selected_index(s: &mut Cursive) -> Option<usize> {
+    return match s.call_on_name(TRASH_LIST, |view: &mut SelectView<usize>| { view.selection() }) {
+        Some(Some(i)) => Some(*i),
+        _ => None
+    };
+}
+
+fn restore(s: &mut Cursive, state_for_restore: Arc<Mutex<AppState>>) {
+    let index = match get_selected_index(s) {
+        Some(i) => i,
+        None => { show_message(s, "No entry selected"); return }
+    };
+
+    let key = match state_for_restore.lock().unwrap().store.trash.get(index) {
+        Some(item) => item.key.clone(),
+        None => { show_message(s, "No entry selected"); return }
+    };
+
+    if !state_for_restore.lock().unwrap().store.restore_from_trash(index) {
+        show_message(s, &format!("Unable to restore entry. An entry named '{}' already exists.", key));
+        return;
+    }
+
+    visualize_if_modified(s, state_for_restore.clone());
+    fill_entry_list(s, state_for_restore.clone());
+    s.pop_layer();
+    display_entry(s, state_for_restore.clone(), &key, true);
+}
+
+fn purge(s: &mut Cursive, state_for_purge: Arc<Mutex<AppState>>) {
+    let index = match get_selected_index(s) {
+        Some(i) => i,
+        None => { show_message(s, "No entry selected"); return }
+    };
+
+    if !state_for_purge.lock().unwrap().store.purge_from_trash(index) {
+        show_message(s, "Purging entry failed");
+        return;
+    }
+
+    visualize_if_modified(s, state_for_purge.clone());
+    fill_trash_list(s, state_for_purge);
+}
+
+pub fn show(s: &mut Cursive, state_for_trash: Arc<Mutex<AppState>>) {
+    if state_for_trash.lock().unwrap().store.trash.len() == 0 {
+        show_message(s, "The recycle bin is empty");
+        return;
+    }
+
+    let state_for_select = state_for_trash.clone();
+    let state_for_restore = state_for_trash.clone();
+    let state_for_purge = state_for_trash.clone();
+
+    let trash_list = SelectView::<usize>::new()
+    .on_select(move |s, _: &usize| { show_item(s, state_for_select.clone()); });
+
+    let res = Dialog::new()
+    .title("Rustpwman recycle bin")
+    .padding_lrtb(2, 2, 1, 1)
+    .content(
+        LinearLayout::horizontal()
+        .child(Panel::new(trash_list
+                .with_name(TRASH_LIST)
+                .scrollable()
+                .fixed_height(NUM_ITEMS_VISIBLE))
+            .title("Deleted entries"))
+        .child(Panel::new(TextView::new("")
+                .with_name(TRASH_TEXT)
+                .scrollable()
+                .fixed_width(60)
+                .fixed_height(NUM_ITEMS_VISIBLE))
+            .title("Value"))
+    )
+    .button("Restore", move |s| { restore(s, state_for_restore.clone()); })
+    .button("Purge", move |s| { purge(s, state_for_purge.clone()); })
+    .button("Close", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+    fill_trash_list(s, state_for_trash);
+}
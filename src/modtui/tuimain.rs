@@ -39,6 +39,8 @@ use super::pwentry::{self, show_pw_error};
 use super::cache;
 use super::init;
 use super::export;
+use super::journal;
+use super::upgrade;
 #[cfg(feature = "writebackup")]
 use crate::RustPwMan;
 
@@ -55,10 +57,15 @@ pub fn write_backup_file(data: &Vec<u8>) -> std::io::Result<()> {
     return fs::write(backup_file, data);
 }
 
-pub fn main(data_file_name: String, default_sec_bits: usize, derive_func: KeyDeriver, deriver_id: fcrypt::KdfId, default_pw_gen: GenerationStrategy,
-            paste_cmd: String, copy_cmd: String, make_default: persist::PersistCreator, crypt_gen: Box<dyn Fn() -> CryptorGen + Send + Sync>, export: bool) {
+pub fn main(data_file_name: String, default_sec_bits: usize, derive_func: KeyDeriver, deriver_id: fcrypt::KdfId, kdf_params: fcrypt::KdfParams, default_pw_gen: GenerationStrategy,
+            paste_cmd: String, copy_cmd: String, make_default: persist::PersistCreator, crypt_gen: Box<dyn Fn() -> CryptorGen + Send + Sync>, export: bool,
+            trash_days: u64, key_file: Option<String>, private_key: Option<fcrypt::PrivateKey>) {
     let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
 
+    // The key file can also be selected when a new data file is created
+    let key_file_name = Arc::new(Mutex::new(key_file));
+    let key_file_for_init = key_file_name.clone();
+
     let capture_file_name = data_file_name.clone();
     let mut siv = cursive::default();
 
@@ -67,11 +74,32 @@ pub fn main(data_file_name: String, default_sec_bits: usize, derive_func: KeyDer
     let sender_main = sender.clone();
 
     let p = make_default(&data_file_name);
+    let open_with_private_key = private_key.is_some();
+    // Files which use a deprecated KDF can be upgraded to Argon2id with the configured parameters
+    let upgrade_params = if deriver_id == fcrypt::KdfId::Argon2 { kdf_params } else { fcrypt::KdfId::Argon2.default_params() };
 
     // stuff to run after successfull password entry
     let pw_callback = Box::new(move |s: &mut Cursive, password: &String, pw_cached: bool| {
         let p_cb = make_default(&capture_file_name);
         let mut jots_store = jots::Jots::new(derive_func, deriver_id, crypt_gen());
+        jots_store.trash_retention_days = trash_days;
+        jots_store.kdf_params = kdf_params;
+
+        if let Some(f) = &*key_file_name.lock().unwrap() {
+            match fcrypt::key_file_hash(f) {
+                Ok(h) => jots_store.key_file = Some(h),
+                Err(e) => {
+                    show_pw_error(s, &format!("Unable to read key file: '{:?}'", e));
+                    return;
+                }
+            }
+        }
+
+        // A new file is encrypted for the owner of the private key. Existing files keep their recipients.
+        if let Some(k) = &private_key {
+            jots_store.private_key = Some(k.clone());
+            jots_store.recipients = vec![k.recipient()];
+        }
 
         #[cfg(feature = "writebackup")]
         {
@@ -94,22 +122,27 @@ pub fn main(data_file_name: String, default_sec_bits: usize, derive_func: KeyDer
             s.pop_layer(); // Close password, file init or confirmation dialog
             if !export {
                 main_window(s, shared_state.clone(), sender_main.clone());
+                journal::start(s, shared_state.clone());
+                upgrade::check(s, shared_state.clone(), upgrade_params);
             } else {
                 export::window(s, shared_state.clone(), sender_main.clone());
             }
         }
     });
 
-    if !export {
+    if open_with_private_key {
+        // No password is needed
+        pw_callback(&mut siv, &String::from(""), false);
+    } else if !export {
         // Add a layer for the password entry dialog
         #[cfg(feature = "pwmanclient")]
-        setup_password_entry_with_pwman(&mut siv, sender, pw_callback, &p);
+        setup_password_entry_with_pwman(&mut siv, sender, pw_callback, &p, key_file_for_init, deriver_id);
 
         #[cfg(not(feature = "pwmanclient"))]
-        setup_password_entry_without_pwman(&mut siv, sender, pw_callback, &p);
+        setup_password_entry_without_pwman(&mut siv, sender, pw_callback, &p, key_file_for_init, deriver_id);
     } else {
         // force user to enter the password
-        setup_password_entry_without_pwman(&mut siv, sender, pw_callback, &p);
+        setup_password_entry_without_pwman(&mut siv, sender, pw_callback, &p, key_file_for_init, deriver_id);
     }
 
     match get_theme() {
@@ -143,7 +176,8 @@ fn show_unable_to_check_error(siv: &mut Cursive, msg: &str, sender: Arc<Sender<S
 }
 
 #[cfg(feature = "pwmanclient")]
-fn setup_password_entry_with_pwman(siv: &mut Cursive, sender: Arc<Sender<String>>, pw_callback: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, p: &SendSyncPersister) {
+fn setup_password_entry_with_pwman(siv: &mut Cursive, sender: Arc<Sender<String>>, pw_callback: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, p: &SendSyncPersister,
+                                   key_file: Arc<Mutex<Option<String>>>, kdf_id: fcrypt::KdfId) {
     let does_exist = match p.does_exist() {
         Ok(b) => b,
         Err(_) => {
@@ -180,12 +214,13 @@ fn setup_password_entry_with_pwman(siv: &mut Cursive, sender: Arc<Sender<String>
             }
         };
     } else {
-        let d = init::dialog(sender.clone(), pw_callback);
+        let d = init::dialog(sender.clone(), pw_callback, key_file, kdf_id);
         siv.add_layer(d);
     }
 }
 
-fn setup_password_entry_without_pwman(siv: &mut Cursive, sender: Arc<Sender<String>>, pw_callback: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, p: &SendSyncPersister) {
+fn setup_password_entry_without_pwman(siv: &mut Cursive, sender: Arc<Sender<String>>, pw_callback: Box<dyn Fn(&mut Cursive, &String, bool) + Send + Sync>, p: &SendSyncPersister,
+                                      key_file: Arc<Mutex<Option<String>>>, kdf_id: fcrypt::KdfId) {
     let does_exist = match p.does_exist() {
         Ok(b) => b,
         Err(_) => {
@@ -198,7 +233,7 @@ fn setup_password_entry_without_pwman(siv: &mut Cursive, sender: Arc<Sender<Stri
         let d = pwentry::dialog(sender.clone(), pw_callback);
         siv.add_layer(d);
     } else {
-        let d = init::dialog(sender.clone(), pw_callback);
+        let d = init::dialog(sender.clone(), pw_callback, key_file, kdf_id);
         siv.add_layer(d);
     }
 }
@@ -15,7 +15,7 @@ limitations under the License. */
 
 use std::sync::{Arc, Mutex};
 use cursive::Cursive;
-use cursive::views::{Dialog, TextView, Panel, ListView};
+use cursive::views::{Dialog, TextView, Panel, ListView, LinearLayout};
 use cursive::traits::*;
 
 
@@ -27,6 +27,31 @@ use super::AppState;
 const NUM_SCROLL_ELEMENTS: usize = 10;
 const LIST_VIEW: &str = "undolist";
 const SCROLL_VIEW: &str = "undoscroll";
+const REDO_LIST_VIEW: &str = "redolist";
+const REDO_SCROLL_VIEW: &str = "redoscroll";
+
+fn fill_list(s: &mut Cursive, name: &str, comments: Vec<String>) {
+    s.call_on_name(name, |view: &mut ListView| {
+        view.clear();
+
+        for i in comments.into_iter() {
+            view.add_child("", TextView::new(i.as_str()));
+        }
+    });
+}
+
+// The list of actions to redo is shown in the order in which they are redone
+fn update_lists(s: &mut Cursive, state: Arc<Mutex<AppState>>) {
+    let (comments, mut redo_comments) = {
+        let undoer = &state.lock().unwrap().store.undoer;
+        (undoer.get_comments(), undoer.get_redo_comments())
+    };
+
+    redo_comments.reverse();
+
+    fill_list(s, LIST_VIEW, comments);
+    fill_list(s, REDO_LIST_VIEW, redo_comments);
+}
 
 fn handle_undo(s: &mut Cursive, state_for_undo: Arc<Mutex<AppState>>) {
     if !state_for_undo.lock().unwrap().store.is_dirty() {
@@ -38,12 +63,11 @@ fn handle_undo(s: &mut Cursive, state_for_undo: Arc<Mutex<AppState>>) {
 
     visualize_if_modified(s, state_for_undo.clone());
     redraw_tui(s, state_for_undo.clone());
+    update_lists(s, state_for_undo.clone());
 
     if !res.1 {
         show_message(s, "Failed to undo last change");
     }
-
-    s.call_on_name(LIST_VIEW, |view: &mut ListView| { view.remove_child(view.len()-1) });
 }
 
 fn handle_undo_all(s: &mut Cursive, state_for_undo_all: Arc<Mutex<AppState>>) {
@@ -57,36 +81,69 @@ fn handle_undo_all(s: &mut Cursive, state_for_undo_all: Arc<Mutex<AppState>>) {
     }   
 }
 
-pub fn undo(s: &mut Cursive, state_for_undo: Arc<Mutex<AppState>>) {
-    if !state_for_undo.lock().unwrap().store.is_dirty() {
-        show_message(s, "Nothing to undo");
+// Also used to redo the last undone change without opening the undo dialog. In that case the lists do not exist.
+pub fn redo(s: &mut Cursive, state_for_redo: Arc<Mutex<AppState>>) {
+    if !state_for_redo.lock().unwrap().store.can_redo() {
+        show_message(s, "Nothing to redo");
         return;
     }
 
-    let state_for_undo_all = state_for_undo.clone();
+    let res = state_for_redo.lock().unwrap().store.redo();
 
-    let comments = state_for_undo.lock().unwrap().store.undoer.get_comments();
+    visualize_if_modified(s, state_for_redo.clone());
+    redraw_tui(s, state_for_redo.clone());
+    update_lists(s, state_for_redo.clone());
 
-    let mut list_view = ListView::new();
-    
-    for i in comments.into_iter() {
-        list_view.add_child("", TextView::new(i.as_str()))
+    if !res.1 {
+        show_message(s, "Failed to redo last undone change");
     }
+}
 
-    let named_list_view = list_view
-    .with_name(LIST_VIEW);
-    
-    let named_scroll_view = named_list_view
+fn handle_redo_all(s: &mut Cursive, state_for_redo_all: Arc<Mutex<AppState>>) {
+    if !state_for_redo_all.lock().unwrap().store.can_redo() {
+        show_message(s, "Nothing to redo");
+        return;
+    }
+
+    while state_for_redo_all.lock().unwrap().store.can_redo() {
+        redo(s, state_for_redo_all.clone());
+    }
+}
+
+fn make_list_panel(list_name: &str, scroll_name: &str, title: &str) -> Panel<impl View> {
+    let named_scroll_view = ListView::new()
+    .with_name(list_name)
     .scrollable()
-    .with_name(SCROLL_VIEW)
+    .with_name(scroll_name)
     .fixed_height(NUM_SCROLL_ELEMENTS);
 
+    return Panel::new(named_scroll_view)
+        .title(title);
+}
+
+pub fn undo(s: &mut Cursive, state_for_undo: Arc<Mutex<AppState>>) {
+    let nothing_to_do = {
+        let store = &state_for_undo.lock().unwrap().store;
+        !store.is_dirty() && !store.can_redo()
+    };
+
+    if nothing_to_do {
+        show_message(s, "Nothing to undo");
+        return;
+    }
+
+    let state_for_undo_all = state_for_undo.clone();
+    let state_for_redo = state_for_undo.clone();
+    let state_for_redo_all = state_for_undo.clone();
+    let state_for_lists = state_for_undo.clone();
+
     let res = Dialog::new()
     .title("Rustpwman undo actions")
     .padding_lrtb(1, 1, 1, 1)
     .content(
-        Panel::new(named_scroll_view)
-        .title("Actions to undo")        
+        LinearLayout::vertical()
+        .child(make_list_panel(LIST_VIEW, SCROLL_VIEW, "Actions to undo"))
+        .child(make_list_panel(REDO_LIST_VIEW, REDO_SCROLL_VIEW, "Actions to redo"))
     )
     .button("Undo", move |s| {    
         handle_undo(s, state_for_undo.clone())
@@ -94,10 +151,16 @@ pub fn undo(s: &mut Cursive, state_for_undo: Arc<Mutex<AppState>>) {
     .button("Undo all", move |s| {    
         handle_undo_all(s, state_for_undo_all.clone())
     })    
+    .button("Redo", move |s| {
+        redo(s, state_for_redo.clone())
+    })
+    .button("Redo all", move |s| {
+        handle_redo_all(s, state_for_redo_all.clone())
+    })
     .button("Cancel", move |s| { 
         s.pop_layer(); 
     });                
     
-    s.add_layer(res);      
+    s.add_layer(res);
+    update_lists(s, state_for_lists);
 }
-
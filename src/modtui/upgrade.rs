@@ -0,0 +1,58 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::sync::{Arc, Mutex};
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use super::AppState;
+use super::show_message;
+use super::visualize_if_modified;
+use crate::fcrypt;
+
+// Warns the user if the file which was opened uses a deprecated KDF and offers to switch to Argon2id. The file
+// is reencrypted the next time it is saved. Files for recipients do not derive their key from a password.
+pub fn check(s: &mut Cursive, state_for_upgrade: Arc<Mutex<AppState>>, params: fcrypt::KdfParams) {
+    let kdf_id = {
+        let store = &state_for_upgrade.lock().unwrap().store;
+
+        if !store.kdf_id.is_deprecated() || (store.recipients.len() > 0) {
+            return;
+        }
+
+        store.kdf_id
+    };
+
+    let msg = format!("This file uses the deprecated KDF {}, which offers almost no protection against guessing the password. \
+                       Do you want to switch to {} ({})? The file is reencrypted the next time it is saved.",
+                       kdf_id.to_str(), fcrypt::KdfId::Argon2.to_str(), params.to_string());
+
+    let res = Dialog::text(msg)
+        .title("Rustpwman deprecated KDF")
+        .button("Upgrade", move |s| {
+            s.pop_layer();
+
+            if !state_for_upgrade.lock().unwrap().store.upgrade_kdf(params) {
+                show_message(s, &format!("KDF parameters {} can not be used", params.to_string()));
+                return;
+            }
+
+            visualize_if_modified(s, state_for_upgrade.clone());
+        })
+        .button("Later", |s| { s.pop_layer(); });
+
+    s.add_layer(res);
+}
@@ -17,9 +17,12 @@ use std::fs::File;
 use std::fs;
 use std::io::BufReader;
 use std::io::BufWriter;
+use std::io::Write;
 use std::io::{Error, ErrorKind};
 use std::path::PathBuf;
 
+const TEMP_SUFFIX: &str = ".tmp";
+
 pub type PersistCreator = Box<dyn Fn(&String) -> SendSyncPersister + Send + Sync>;
 pub type SendSyncPersister = Box<dyn Persister + Send + Sync>;
 
@@ -51,11 +54,25 @@ impl Persister for FilePersister {
         //return Err(Error::new(ErrorKind::Other, "Connection failed"));
     }
 
+    // The data is first written to a temporary file which then replaces the data file. This makes sure that
+    // the data file is never left in a partially written state.
     fn persist(&mut self, data: &Vec<u8>) -> std::io::Result<()> {
-        let file = File::create(&self.file_name)?;
-        let mut w = BufWriter::new(file);
-
-        std::io::copy(&mut data.as_slice(), &mut w).unwrap();
+        let temp_file_name = format!("{}{}", &self.file_name, TEMP_SUFFIX);
+
+        {
+            let file = File::create(&temp_file_name)?;
+            let mut w = BufWriter::new(file);
+
+            std::io::copy(&mut data.as_slice(), &mut w)?;
+            w.flush()?;
+            w.get_ref().sync_all()?;
+        }
+
+        if let Err(e) = fs::rename(&temp_file_name, &self.file_name) {
+            // ignore result as the original error is more relevant
+            let _ = fs::remove_file(&temp_file_name);
+            return Err(e);
+        }
 
         return Ok(());
     }
@@ -17,6 +17,8 @@ limitations under the License. */
 use std::io::{Error, ErrorKind};
 use md5::{Md5, Digest};
 use serde::{Serialize, Deserialize};
+use zeroize::Zeroize;
+use crate::secret::{SecretBytes, SecretString};
 
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -58,7 +60,7 @@ impl PWRequest {
     }    
 
     pub fn send(self: &Self, w: &mut dyn std::io::Write) -> std::io::Result<()> {
-        let uds_request_text = serde_json::to_string(self)?;
+        let uds_request_text = SecretString::new(serde_json::to_string(self)?);
         let uds_request_bytes = uds_request_text.as_bytes();
 
         if uds_request_bytes.len() > 65535 {
@@ -74,6 +76,13 @@ impl PWRequest {
     }
 }
 
+// Requests and responses may contain a password
+impl Drop for PWRequest {
+    fn drop(&mut self) {
+        self.pw_data.zeroize();
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct PWResponse {
     #[serde(rename(deserialize = "ResultCode"))]
@@ -89,7 +98,7 @@ impl PWResponse {
         let mut pw_response_len: [u8; 2] = [0, 0];
         r.read_exact(&mut pw_response_len)?;
         let response_len : usize = (pw_response_len[0] as usize) * 256 + (pw_response_len[1]) as usize;
-        let mut pw_response: Vec<u8> = vec![0; response_len];
+        let mut pw_response = SecretBytes::new(vec![0; response_len]);
         r.read_exact(&mut pw_response)?;
     
     
@@ -104,6 +113,12 @@ impl PWResponse {
     }
 }
 
+impl Drop for PWResponse {
+    fn drop(&mut self) {
+        self.result_data.zeroize();
+    }
+}
+
 pub trait ReaderWriter: std::io::Read + std::io::Write {}
 impl<T> ReaderWriter for T where T: std::io::Read + std::io::Write {}
 
@@ -131,20 +146,20 @@ pub trait PWManClient {
     fn connect(self: &Self) -> std::io::Result<Box<dyn ReaderWriter>>;
     fn get_pw_file_id(self: &Self) -> &String;
 
-    fn transact(self: &Self, request: &PWRequest) -> std::io::Result<String>  {
+    fn transact(self: &Self, request: &PWRequest) -> std::io::Result<SecretString>  {
         let mut stream = self.connect()?;
 
         request.send(&mut stream)?;    
-        let response = PWResponse::receive(&mut stream)?;
+        let mut response = PWResponse::receive(&mut stream)?;
     
         if response.result_code != 0 {
             return Err(Error::new(ErrorKind::Other, format!("Server returned error code: {}", response.result_code)));
         }
     
-        return Ok(response.result_data);
+        return Ok(SecretString::new(std::mem::take(&mut response.result_data)));
     }
 
-    fn get_password(self: &Self) -> std::io::Result<String> {
+    fn get_password(self: &Self) -> std::io::Result<SecretString> {
         let request = PWRequest::new_get_request(self.get_pw_file_id());
         return self.transact(&request)
     }    
@@ -0,0 +1,184 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use std::ops::{Deref, DerefMut};
+use zeroize::Zeroize;
+
+// Holds a value which contains secret data, e.g. a password or decrypted data. The value is overwritten
+// when it is dropped. Copies which are made from the contained value are not protected.
+pub struct Secret<T: Zeroize>(T);
+
+pub type SecretString = Secret<String>;
+pub type SecretBytes = Secret<Vec<u8>>;
+
+impl<T: Zeroize> Secret<T> {
+    pub fn new(value: T) -> Secret<T> {
+        return Secret(value);
+    }
+}
+
+impl<T: Zeroize> Deref for Secret<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        return &self.0;
+    }
+}
+
+impl<T: Zeroize> DerefMut for Secret<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        return &mut self.0;
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroize + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Secret<T> {
+        return Secret(self.0.clone());
+    }
+}
+
+impl<T: Zeroize + PartialEq> PartialEq<T> for Secret<T> {
+    fn eq(&self, other: &T) -> bool {
+        return self.0 == *other;
+    }
+}
+
+impl<T: Zeroize + PartialEq> PartialEq for Secret<T> {
+    fn eq(&self, other: &Secret<T>) -> bool {
+        return self.0 == other.0;
+    }
+}
+
+// Never print the contained value
+impl<T: Zeroize> std::fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "Secret(***)");
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(s: &str) -> SecretString {
+        return Secret(String::from(s));
+    }
+}
+
+// A derived key. In addition to being overwritten when it is dropped the memory which holds the key is
+// locked, i.e. it is not written to swap space, where the platform allows this. As locking works on whole
+// pages unlocking a key may also unlock another key which happens to lie in the same page.
+pub struct SecretKey {
+    key: Vec<u8>,
+    locked: bool
+}
+
+impl SecretKey {
+    pub fn new(key: Vec<u8>) -> SecretKey {
+        let locked = lock_memory(key.as_ptr(), key.len());
+
+        return SecretKey {
+            key: key,
+            locked: locked
+        };
+    }
+
+    #[cfg(test)]
+    pub fn is_locked(&self) -> bool {
+        return self.locked;
+    }
+}
+
+impl Deref for SecretKey {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        return &self.key;
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        // Zeroizing the Vec itself would also truncate it, but the memory has to be unlocked with its original length
+        self.key.as_mut_slice().zeroize();
+
+        if self.locked {
+            #[cfg(test)]
+            LAST_UNLOCK_LEN.with(|l| l.set(self.key.len()));
+
+            unlock_memory(self.key.as_ptr(), self.key.len());
+        }
+    }
+}
+
+// Number of bytes which were unlocked when the last key of this thread was dropped
+#[cfg(test)]
+thread_local! {
+    pub static LAST_UNLOCK_LEN: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "SecretKey(***)");
+    }
+}
+
+#[cfg(unix)]
+fn lock_memory(addr: *const u8, len: usize) -> bool {
+    if len == 0 {
+        return false;
+    }
+
+    return unsafe { libc::mlock(addr as *const libc::c_void, len) == 0 };
+}
+
+#[cfg(unix)]
+fn unlock_memory(addr: *const u8, len: usize) {
+    // ignore result as there is nothing which could be done about it
+    let _ = unsafe { libc::munlock(addr as *const libc::c_void, len) };
+}
+
+#[cfg(windows)]
+extern "system" {
+    fn VirtualLock(addr: *const std::ffi::c_void, len: usize) -> i32;
+    fn VirtualUnlock(addr: *const std::ffi::c_void, len: usize) -> i32;
+}
+
+#[cfg(windows)]
+fn lock_memory(addr: *const u8, len: usize) -> bool {
+    if len == 0 {
+        return false;
+    }
+
+    return unsafe { VirtualLock(addr as *const std::ffi::c_void, len) != 0 };
+}
+
+#[cfg(windows)]
+fn unlock_memory(addr: *const u8, len: usize) {
+    // ignore result as there is nothing which could be done about it
+    let _ = unsafe { VirtualUnlock(addr as *const std::ffi::c_void, len) };
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_memory(_addr: *const u8, _len: usize) -> bool {
+    return false;
+}
+
+#[cfg(not(any(unix, windows)))]
+fn unlock_memory(_addr: *const u8, _len: usize) {
+}
@@ -0,0 +1,103 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+// A tag expression consists of terms which are separated by white space. An entry matches the expression
+// if it matches all terms. A term is a list of alternatives separated by '|' of which at least one has to
+// match. An alternative is a tag name which may be prefixed by '!' to negate it. Tags are compared without
+// regard to case. Example: "prod customer-x|customer-y !shared"
+
+pub const TAG_OR: char = '|';
+pub const TAG_NOT: char = '!';
+pub const TAG_SEPARATOR: char = ',';
+// Characters which can not be part of a tag in addition to white space
+pub const TAG_FORBIDDEN_CHARS: [char; 3] = [TAG_NOT, TAG_OR, TAG_SEPARATOR];
+
+struct TagTerm {
+    negated: bool,
+    tag: String
+}
+
+pub struct TagFilter {
+    expression: String,
+    clauses: Vec<Vec<TagTerm>>
+}
+
+// Checks whether a tag can be used in a tag expression
+pub fn is_valid_tag(tag: &str) -> bool {
+    if tag == "" {
+        return false;
+    }
+
+    return !tag.chars().any(|c| c.is_whitespace() || TAG_FORBIDDEN_CHARS.contains(&c));
+}
+
+// Describes which tags are rejected by is_valid_tag
+pub fn invalid_tag_message() -> String {
+    let chars: Vec<String> = TAG_FORBIDDEN_CHARS.iter().map(|c| format!("'{}'", c)).collect();
+
+    return format!("Tags must not be empty and must not contain white space or the characters {}", chars.join(", "));
+}
+
+impl TagFilter {
+    pub fn parse(expr: &str) -> Result<TagFilter, String> {
+        let mut clauses: Vec<Vec<TagTerm>> = Vec::new();
+
+        for term in expr.split_whitespace() {
+            let mut alternatives: Vec<TagTerm> = Vec::new();
+
+            for alternative in term.split(TAG_OR) {
+                let (negated, tag) = match alternative.strip_prefix(TAG_NOT) {
+                    Some(t) => (true, t),
+                    None => (false, alternative)
+                };
+
+                if !is_valid_tag(tag) {
+                    return Err(format!("'{}' is not a valid tag in expression '{}'", tag, term));
+                }
+
+                alternatives.push(TagTerm { negated: negated, tag: tag.to_lowercase() });
+            }
+
+            clauses.push(alternatives);
+        }
+
+        if clauses.len() == 0 {
+            return Err(String::from("Tag expression is empty"));
+        }
+
+        return Ok(TagFilter {
+            expression: normalize_expression(expr),
+            clauses: clauses
+        });
+    }
+
+    pub fn matches(&self, tags: &Vec<String>) -> bool {
+        let lower_tags: Vec<String> = tags.iter().map(|t| t.to_lowercase()).collect();
+
+        return self.clauses.iter().all(|clause| {
+            clause.iter().any(|term| lower_tags.contains(&term.tag) != term.negated)
+        });
+    }
+
+    pub fn get_expression(&self) -> &String {
+        return &self.expression;
+    }
+}
+
+fn normalize_expression(expr: &str) -> String {
+    let terms: Vec<&str> = expr.split_whitespace().collect();
+
+    return terms.join(" ");
+}
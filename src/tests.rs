@@ -39,11 +39,23 @@ use std::env;
 #[cfg(test)]
 use std::fs::remove_file;
 #[cfg(test)]
+use base64::prelude::*;
+#[cfg(test)]
 use crate::obfuscate;
 #[cfg(test)]
 use crate::pwgen::BaseNGenerator;
 #[cfg(test)]
 use crate::jots::CryptorGen;
+#[cfg(test)]
+use crate::tagfilter::TagFilter;
+#[cfg(test)]
+use crate::diff;
+#[cfg(test)]
+use crate::timestamp;
+#[cfg(test)]
+use crate::merge;
+#[cfg(test)]
+use crate::journal;
 
 
 #[cfg(test)]
@@ -213,6 +225,16 @@ pub fn make_aes192_gcm_cryptor(d: fcrypt::KeyDeriver, i: fcrypt::KdfId) -> Box<d
     return fcrypt::CipherId::Aes192Gcm.make(d, i);
 }
 
+#[cfg(test)]
+pub fn make_xchacha20_cryptor(d: fcrypt::KeyDeriver, i: fcrypt::KdfId) -> Box<dyn fcrypt::Cryptor> {
+    return fcrypt::CipherId::XChaCha20Poly1305.make(d, i);
+}
+
+#[cfg(test)]
+pub fn make_aes256_gcm_siv_cryptor(d: fcrypt::KeyDeriver, i: fcrypt::KdfId) -> Box<dyn fcrypt::Cryptor> {
+    return fcrypt::CipherId::Aes256GcmSiv.make(d, i);
+}
+
 #[test]
 pub fn test_fcrypt_enc_dec_with_json_aes_gcm() {
     test_fcrypt_enc_dec_with_json_generic(Box::new(make_aes_gcm_cryptor));
@@ -229,6 +251,16 @@ pub fn test_fcrypt_enc_dec_with_json_chacha20() {
     test_fcrypt_enc_dec_with_json_generic(Box::new(make_chacha20_cryptor));
 }
 
+#[test]
+pub fn test_fcrypt_enc_dec_with_json_xchacha20() {
+    test_fcrypt_enc_dec_with_json_generic(Box::new(make_xchacha20_cryptor));
+}
+
+#[test]
+pub fn test_fcrypt_enc_dec_with_json_aes_256_gcm_siv() {
+    test_fcrypt_enc_dec_with_json_generic(Box::new(make_aes256_gcm_siv_cryptor));
+}
+
 #[test]
 pub fn test_jots_serialize_deserialize() {
     let mut serialized: Vec<u8> = Vec::new();
@@ -351,8 +383,8 @@ pub fn test_sha256_key_gen() {
     let password: &str = "Dies ist ein Test";
 
     let salt_vec: Vec<u8> = salt.as_bytes().to_vec();
-    let (d, _) = fcrypt::KdfId::Sha256.to_named_func();
-    let key = d(&salt_vec, password);
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let key = d(&salt_vec, password, &i.default_params());
     let res = vec_to_hex(&key);
 
     assert_eq!(res, "8bbb8e596fdeb564b5ded3d60af1cf790a326309ada0045cc61d07fd982876d2");
@@ -423,7 +455,7 @@ fn test_save_load_config() {
     const TEST_CONF_NAME: &str = "config_test_delete_me.toml";
 
     current_dir.push(TEST_CONF_NAME);
-    let c = tomlconfig::RustPwManSerialize::new(15, "egal1", "egal2", "egal42", "egal43", "user", "password", "server");
+    let c = tomlconfig::RustPwManSerialize::new(15, "egal1", "egal2", "egal42", "egal43", "user", "password", "server", 30, "65536,3,1", "");
 
     match tomlconfig::save(&current_dir, c) {
         Some(e) => panic!("{:?}", e),
@@ -445,6 +477,9 @@ fn test_save_load_config() {
     assert_eq!(res_val.clip_cmd, String::from("egal42"));
     assert_eq!(res_val.webdav_user, String::from("user"));
     assert_eq!(res_val.webdav_server, String::from("server"));
+    assert_eq!(res_val.trash_days, 30);
+    assert_eq!(res_val.argon2_params, String::from("65536,3,1"));
+    assert_eq!(res_val.scrypt_params, String::from(""));
 
     remove_file(current_dir.as_os_str().to_str().unwrap()).unwrap();
 }
@@ -452,7 +487,7 @@ fn test_save_load_config() {
 #[test]
 #[allow(suspicious_double_ref_op)]
 fn test_undo_1() {
-    let mut u = undo::UndoRepo::<&str, &str>::new();
+    let mut u = undo::UndoRepo::<HashMap<&str, &str>>::new();
     let mut h = HashMap::<&str, &str>::new();
 
     //--------------
@@ -464,7 +499,12 @@ fn test_undo_1() {
 
         return true;
     });
-    u.push(&String::from("Added schnulli"), undo1);
+    let redo1 = Box::new(move |s: &mut HashMap<&str, &str>| -> bool {
+        s.insert("schnulli", "bulli");
+
+        return true;
+    });
+    u.push(&String::from("Added schnulli"), undo1, redo1);
 
     //--------------
 
@@ -476,7 +516,13 @@ fn test_undo_1() {
         return true;
     });    
 
-    u.push(&String::from("Added kulli"), undo2);
+    let redo2 = Box::new(move |s: &mut HashMap<&str, &str>| -> bool {
+        s.insert("kulli", "wulli");
+
+        return true;
+    });
+
+    u.push(&String::from("Added kulli"), undo2, redo2);
 
     //--------------
 
@@ -486,9 +532,15 @@ fn test_undo_1() {
         return true;
     });
 
+    let redo3 = Box::new(move |s: &mut HashMap<&str, &str>| -> bool {
+        s.insert("kulli", "hawulli");
+
+        return true;
+    });
+
     h.insert("kulli", "hawulli");
 
-    u.push(&String::from("Modified kulli"), undo3);
+    u.push(&String::from("Modified kulli"), undo3, redo3);
 
     //--------------
 
@@ -515,7 +567,40 @@ fn test_undo_1() {
     u.undo_one(&mut h);
     assert_eq!(h.len(), 0);
     assert_eq!(u.is_all_undone(), true);
+    assert_eq!(u.get_redo_comments().len(), 3);
+
+    //--------------
+
+    let (comment, res) = u.redo_one(&mut h);
+    assert!(res);
+    assert_eq!(comment, "Added schnulli");
+    assert_eq!(h.len(), 1);
+
+    u.redo_one(&mut h);
+    u.redo_one(&mut h);
+    val = h.get("kulli").unwrap().clone();
+    assert_eq!(val, "hawulli");
+    assert_eq!(u.is_all_redone(), true);
 
+    u.undo_one(&mut h);
+    val = h.get("kulli").unwrap().clone();
+    assert_eq!(val, "wulli");
+
+    // Recording a new operation discards the operations which could have been redone
+    let undo4 = Box::new(move |s: &mut HashMap<&str, &str>| -> bool {
+        s.remove("wulli");
+
+        return true;
+    });
+    let redo4 = Box::new(move |s: &mut HashMap<&str, &str>| -> bool {
+        s.insert("wulli", "kulli");
+
+        return true;
+    });
+    h.insert("wulli", "kulli");
+    u.push(&String::from("Added wulli"), undo4, redo4);
+    assert_eq!(u.is_all_redone(), true);
+    assert_eq!(u.get_comments().len(), 3);
 }
 
 #[test]
@@ -561,4 +646,1287 @@ fn test_base_n_gen() {
         println!("{}", &pw);
         assert_eq!(gen.get_max_digits(num_bytes), pw.len());    
     }
-}
\ No newline at end of file
+}
+
+#[test]
+pub fn test_jots_structured_fields() {
+    let legacy = r#"[{"Key": "old", "Text": "plain text\n"}]"#;
+    let name = String::from("new");
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j.from_reader(legacy.as_bytes()).unwrap();
+    assert_eq!(j.get(&String::from("old")).unwrap(), "plain text\n");
+    assert_eq!(j.get_formatted(&String::from("old")).unwrap(), "plain text\n");
+
+    let mut e = jots::Entry::new(&String::from("some notes"));
+    e.user_name = String::from("martin");
+    e.password = String::from("secret");
+    e.custom_fields = jots::CustomField::parse_lines("PIN: 1234\n\nRecovery: a:b\n").unwrap();
+    assert!(j.add_entry(&name, &e));
+
+    let mut serialized: Vec<u8> = Vec::new();
+    j.to_writer(&mut serialized).unwrap();
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.from_reader(serialized.as_slice()).unwrap();
+
+    let e2 = j2.get_entry(&name).unwrap();
+    assert_eq!(e2, j.get_entry(&name).unwrap());
+    assert!(e2.same_contents(&e));
+    assert_eq!(e2.custom_fields[1].value, "a:b");
+    assert_eq!(e2.to_formatted_string(), "User name: martin\nPassword : secret\nPIN      : 1234\nRecovery : a:b\n\nsome notes");
+    assert!(jots::CustomField::parse_lines("no separator").is_err());
+}
+
+#[test]
+pub fn test_jots_groups() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let e2 = String::from("e2");
+
+    assert_eq!(jots::normalize_group(" work// prod /"), "work/prod");
+
+    j.add(&e1, &String::from("data1"));
+    j.add(&e2, &String::from("data2"));
+    j.mark_as_clean();
+
+    assert!(j.move_to_group(&e1, "work//prod"));
+    assert!(j.move_to_group(&e2, "private"));
+    assert!(!j.move_to_group(&String::from("missing"), "private"));
+
+    assert_eq!(j.get_entry(&e1).unwrap().group, "work/prod");
+    assert_eq!(j.get_groups(), vec![String::from("private"), String::from("work"), String::from("work/prod")]);
+    assert!(j.is_dirty());
+
+    j.undo();
+    assert_eq!(j.get_entry(&e2).unwrap().group, "");
+    assert_eq!(j.get_groups(), vec![String::from("work"), String::from("work/prod")]);
+}
+
+#[test]
+pub fn test_tag_filter() {
+    let tags = vec![String::from("Prod"), String::from("customer-x")];
+    let no_tags: Vec<String> = Vec::new();
+
+    assert!(TagFilter::parse("prod").unwrap().matches(&tags));
+    assert!(TagFilter::parse("prod  customer-y|customer-x").unwrap().matches(&tags));
+    assert!(!TagFilter::parse("prod customer-y").unwrap().matches(&tags));
+    assert!(!TagFilter::parse("prod !customer-x").unwrap().matches(&tags));
+    assert!(TagFilter::parse("!shared").unwrap().matches(&no_tags));
+    assert!(!TagFilter::parse("shared|prod").unwrap().matches(&no_tags));
+    assert_eq!(TagFilter::parse(" prod   !shared ").unwrap().get_expression(), "prod !shared");
+
+    assert!(TagFilter::parse("   ").is_err());
+    assert!(TagFilter::parse("prod|").is_err());
+    assert!(TagFilter::parse("!!prod").is_err());
+
+    // The message names every character which is rejected
+    for c in crate::tagfilter::TAG_FORBIDDEN_CHARS {
+        assert!(!crate::tagfilter::is_valid_tag(&format!("a{}b", c)));
+        assert!(crate::tagfilter::invalid_tag_message().contains(&format!("'{}'", c)));
+    }
+
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    j.add(&e1, &String::from("data1"));
+
+    assert!(j.set_tags(&e1, &vec![String::from("shared"), String::from("prod"), String::from("shared")]));
+    assert!(!j.set_tags(&e1, &vec![String::from("a|b")]));
+    assert_eq!(j.get_entry(&e1).unwrap().tags, vec![String::from("prod"), String::from("shared")]);
+    assert_eq!(j.get_tags(), vec![String::from("prod"), String::from("shared")]);
+}
+
+#[test]
+pub fn test_jots_history() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+
+    j.add(&e1, &String::from("version 0"));
+    assert!(j.move_to_group(&e1, "work"));
+    assert_eq!(j.get_entry(&e1).unwrap().history.len(), 0);
+
+    for n in 1..(jots::MAX_HISTORY_ITEMS + 3) {
+        j.modify(&e1, &format!("version {}", n));
+    }
+
+    let e = j.get_entry(&e1).unwrap();
+    assert_eq!(e.history.len(), jots::MAX_HISTORY_ITEMS);
+    assert_eq!(e.history[0].value.text, format!("version {}", jots::MAX_HISTORY_ITEMS + 1));
+    assert_eq!(e.history[0].value.history.len(), 0);
+
+    let mut serialized: Vec<u8> = Vec::new();
+    j.to_writer(&mut serialized).unwrap();
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.from_reader(serialized.as_slice()).unwrap();
+    assert_eq!(j2.get_entry(&e1).unwrap(), e);
+
+    assert!(j2.restore_version(&e1, 1));
+    assert!(!j2.restore_version(&e1, jots::MAX_HISTORY_ITEMS));
+    let e2 = j2.get_entry(&e1).unwrap();
+    assert_eq!(e2.text, format!("version {}", jots::MAX_HISTORY_ITEMS));
+    assert_eq!(e2.group, "work");
+    assert_eq!(e2.history[0].value.text, format!("version {}", jots::MAX_HISTORY_ITEMS + 2));
+
+    j2.undo();
+    assert_eq!(j2.get_entry(&e1).unwrap(), e);
+}
+
+#[test]
+pub fn test_diff_lines() {
+    let res = diff::diff_lines("a\nb\nc\nd", "a\nc\nx\nd\ne");
+    assert_eq!(res, vec!["  a", "- b", "  c", "+ x", "  d", "+ e"]);
+    assert_eq!(diff::diff_lines("", "a"), vec!["+ a"]);
+    assert_eq!(diff::diff_lines("a", ""), vec!["- a"]);
+}
+
+#[test]
+pub fn test_jots_timestamps() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let e2 = String::from("e2");
+    let e3 = String::from("e3");
+
+    let legacy = r#"[{"Key": "e1", "Text": "plain text\n"}]"#;
+    j.from_reader(legacy.as_bytes()).unwrap();
+    assert_eq!(j.get_entry(&e1).unwrap().created, 0);
+
+    let mut e = jots::Entry::new(&String::from("notes"));
+    e.password = String::from("secret");
+    assert!(j.add_entry(&e2, &e));
+    j.add(&e3, &String::from("data3"));
+
+    let created = j.get_entry(&e2).unwrap().created;
+    assert!(created > 0);
+    assert_eq!(j.get_entry(&e2).unwrap().modified, created);
+    assert_eq!(j.get_entry(&e2).unwrap().secret_changed, created);
+
+    // Changing the notes of an entry with a password does not change its secret
+    j.contents.get_mut(&e2).unwrap().secret_changed = created - 10 * timestamp::SECONDS_PER_DAY;
+    j.modify(&e2, &String::from("other notes"));
+    assert_eq!(j.get_entry(&e2).unwrap().secret_changed, created - 10 * timestamp::SECONDS_PER_DAY);
+
+    let now = timestamp::now();
+    let stale = j.get_stale_entries(now, 5);
+    assert_eq!(stale.len(), 2);
+    assert_eq!(stale[0].0, e1);
+    assert_eq!(stale[1].0, e2);
+    assert_eq!(j.get_stale_entries(now, 11).len(), 1);
+
+    e.password = String::from("new secret");
+    j.modify_entry(&e2, &e);
+    assert_eq!(j.get_stale_entries(now, 5).len(), 1);
+
+    j.undo();
+    assert_eq!(j.get_stale_entries(now, 5).len(), 2);
+}
+
+#[test]
+pub fn test_jots_attachments() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let data: Vec<u8> = vec![0, 255, 10, 13, 128];
+
+    j.add(&e1, &String::from("data1"));
+    j.add_attachment(&e1, "id_rsa", &data).unwrap();
+    assert!(j.add_attachment(&e1, "id_rsa", &data).unwrap_err().to_string().contains("already exists"));
+    assert!(j.add_attachment(&e1, "  ", &data).unwrap_err().to_string().contains("empty"));
+    assert!(j.add_attachment(&String::from("missing"), "id_rsa", &data).unwrap_err().to_string().contains("does not exist"));
+
+    // Attachments are not part of the versioned contents
+    j.modify(&e1, &String::from("data2"));
+    assert_eq!(j.get_entry(&e1).unwrap().history[0].value.attachments.len(), 0);
+
+    let mut serialized: Vec<u8> = Vec::new();
+    j.to_writer(&mut serialized).unwrap();
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.from_reader(serialized.as_slice()).unwrap();
+    assert_eq!(j2.get_attachment(&e1, "id_rsa").unwrap(), data);
+
+    assert!(j2.restore_version(&e1, 0));
+    assert_eq!(j2.get_attachment(&e1, "id_rsa").unwrap(), data);
+
+    assert!(j2.delete_attachment(&e1, "id_rsa"));
+    assert!(!j2.delete_attachment(&e1, "id_rsa"));
+    assert_eq!(j2.get_attachment(&e1, "id_rsa"), None);
+
+    j2.undo();
+    assert_eq!(j2.get_attachment(&e1, "id_rsa").unwrap(), data);
+}
+
+#[test]
+pub fn test_jots_trash() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let e2 = String::from("e2");
+
+    j.add(&e1, &String::from("data1"));
+    j.add(&e2, &String::from("data2"));
+
+    // An empty trash does not change the format of the payload
+    let mut serialized: Vec<u8> = Vec::new();
+    j.to_writer(&mut serialized).unwrap();
+    assert_eq!(serialized[0], b'[');
+
+    j.delete(&e1);
+    j.delete(&e2);
+    assert_eq!(j.len(), 0);
+    assert_eq!(j.trash.len(), 2);
+
+    j.undo();
+    assert_eq!(j.len(), 1);
+    assert_eq!(j.trash.len(), 1);
+    j.delete(&e2);
+
+    j.add(&e1, &String::from("other data"));
+    assert!(!j.restore_from_trash(0));
+    j.delete(&e1);
+    assert!(j.restore_from_trash(0));
+    assert_eq!(j.get(&e1).unwrap(), "data1");
+    assert_eq!(j.trash.len(), 2);
+
+    serialized.clear();
+    j.to_writer(&mut serialized).unwrap();
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.from_reader(serialized.as_slice()).unwrap();
+    assert_eq!(j2.trash, j.trash);
+    assert_eq!(j2.len(), 1);
+
+    assert!(j2.purge_from_trash(1));
+    assert!(!j2.purge_from_trash(1));
+    assert_eq!(j2.trash[0].key, e2);
+    j2.undo();
+    assert_eq!(j2.trash.len(), 2);
+
+    let now = timestamp::now();
+    j2.trash[0].deleted = now - 40 * timestamp::SECONDS_PER_DAY;
+    assert_eq!(j2.purge_trash_older_than(now, 30), 1);
+    assert_eq!(j2.trash.len(), 1);
+    assert_eq!(j2.purge_trash_older_than(now, 30), 0);
+}
+
+#[test]
+pub fn test_jots_merge() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let e2 = String::from("e2");
+    let e2_new = String::from("e2 renamed");
+    let e3 = String::from("e3");
+    let e4 = String::from("e4");
+    let e5 = String::from("e5");
+
+    j.add(&e1, &String::from("data1"));
+    j.add(&e2, &String::from("data2"));
+    j.add(&e3, &String::from("data3"));
+    j.add(&e4, &String::from("data4"));
+    j.mark_as_clean();
+
+    let mut remote = j.contents.clone();
+    remote.get_mut(&e1).unwrap().text = String::from("remote1");
+    remote.get_mut(&e2).unwrap().text = String::from("remote2");
+    remote.get_mut(&e4).unwrap().text = String::from("remote4");
+    remote.insert(e5.clone(), jots::Entry::new(&String::from("data5")));
+
+    let mut e = j.get_entry(&e1).unwrap();
+    e.password = String::from("secret");
+    j.modify_entry(&e1, &e);
+    assert!(j.rename(&e2, &e2_new));
+    j.delete(&e3);
+    j.modify(&e4, &String::from("local4"));
+
+    let mut res = j.merge_with(&remote, &Vec::new());
+    assert_eq!(res.conflicts.len(), 1);
+    assert_eq!(res.conflicts[0].local.as_ref().unwrap().1.text, "local4");
+    assert_eq!(res.conflicts[0].remote.as_ref().unwrap().1.text, "remote4");
+    assert_eq!(res.trash.len(), 1);
+
+    let unresolved = j.merge_with(&remote, &Vec::new());
+    assert!(!j.apply_merge(unresolved, &remote, &None));
+
+    res.resolve(merge::Resolution::Both);
+    assert!(j.apply_merge(res, &remote, &None));
+
+    assert_eq!(j.get_entry(&e1).unwrap().text, "remote1");
+    assert_eq!(j.get_entry(&e1).unwrap().password, "secret");
+    assert_eq!(j.get(&e2), None);
+    assert_eq!(j.get_entry(&e2_new).unwrap().text, "remote2");
+    assert_eq!(j.get(&e3), None);
+    assert_eq!(j.get_entry(&e4).unwrap().text, "local4");
+    assert_eq!(j.get_entry(&String::from("e4 (remote)")).unwrap().text, "remote4");
+    assert_eq!(j.get_entry(&e5).unwrap().text, "data5");
+    assert_eq!(j.len(), 5);
+
+    // Merging again with the same remote version does not change anything
+    let res = j.merge_with(&remote, &Vec::new());
+    assert!(res.is_resolved());
+    assert_eq!(res.merged, j.contents);
+
+    j.undo();
+    assert_eq!(j.get_entry(&e1).unwrap().text, "data1");
+    assert_eq!(j.len(), 3);
+}
+
+#[test]
+pub fn test_merge_entry_ids() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_merge_entry_ids.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let e2 = String::from("e2");
+
+    // Both entries are created within the same second, so they can not be told apart by their creation time
+    j.add(&e1, &String::from("data1"));
+    j.add(&e2, &String::from("data2"));
+    assert_ne!(j.get_entry(&e1).unwrap().id, "");
+    assert_ne!(j.get_entry(&e1).unwrap().id, j.get_entry(&e2).unwrap().id);
+    j.persist(&mut p, "pw").unwrap();
+
+    let mut remote = j.contents.clone();
+    remote.get_mut(&e1).unwrap().text = String::from("remote1");
+    remote.get_mut(&e2).unwrap().text = String::from("remote2");
+
+    let id1 = j.get_entry(&e1).unwrap().id;
+    assert!(j.rename(&e1, &String::from("a")));
+    assert!(j.rename(&e2, &String::from("b")));
+    j.modify(&String::from("a"), &String::from("local1"));
+    assert_eq!(j.get_entry(&String::from("a")).unwrap().id, id1);
+
+    let res = j.merge_with(&remote, &Vec::new());
+    assert_eq!(res.conflicts.len(), 1);
+    assert_eq!(res.merged[&String::from("b")].text, "remote2");
+
+    // Entries of files without ids get the same id whenever they are read
+    let mut legacy = j.get_entry(&String::from("b")).unwrap();
+    legacy.id.clear();
+    let legacy_file = serde_json::to_string(&vec![jots::KvEntry::new(&String::from("b"), &legacy)]).unwrap();
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.from_reader(legacy_file.as_bytes()).unwrap();
+    j3.from_reader(legacy_file.as_bytes()).unwrap();
+    assert_ne!(j2.get_entry(&String::from("b")).unwrap().id, "");
+    assert_eq!(j2.contents, j3.contents);
+
+    // The stored file has not been changed by anybody else
+    assert!(!j.stored_file_changed(&mut p).unwrap());
+
+    let mut other = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    other.retrieve(&mut p, "pw").unwrap();
+    other.modify(&e2, &String::from("other2"));
+    other.persist(&mut p, "pw").unwrap();
+    assert!(j.stored_file_changed(&mut p).unwrap());
+
+    let (contents, trash, hash) = j.retrieve_other(&mut p, "pw").unwrap();
+    let mut res = j.merge_with(&contents, &trash);
+    res.resolve_all(merge::Resolution::Both);
+    assert!(j.apply_merge(res, &contents, &Some(hash)));
+    assert!(!j.stored_file_changed(&mut p).unwrap());
+
+    // Undoing the merge means the changes of the stored version are missing again
+    assert!(j.undo().1);
+    assert!(j.stored_file_changed(&mut p).unwrap());
+    assert!(j.redo().1);
+    assert!(!j.stored_file_changed(&mut p).unwrap());
+
+    let ids: std::collections::HashSet<String> = j.contents.values().map(|e| e.id.clone()).collect();
+    assert_eq!(ids.len(), j.len());
+
+    let _ = remove_file(&file_name);
+}
+
+#[test]
+pub fn test_save_after_password_change() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_save_after_password_change.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.persist(&mut p, "old").unwrap();
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.retrieve(&mut p, "old").unwrap();
+
+    // The check does not need a password, i.e. it works when only the new password is known
+    assert!(j2.set_kdf_params(fcrypt::KdfId::Sha256.default_params()));
+    assert!(!j2.stored_file_changed(&mut p).unwrap());
+    j2.persist(&mut p, "new").unwrap();
+    assert!(!j2.stored_file_changed(&mut p).unwrap());
+
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j3.retrieve(&mut p, "old").is_err());
+    j3.retrieve(&mut p, "new").unwrap();
+    assert_eq!(j3.get(&String::from("e1")), Some(String::from("data1")));
+
+    let _ = remove_file(&file_name);
+}
+
+#[test]
+pub fn test_save_after_rekey() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_save_after_rekey.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.persist(&mut p, "old").unwrap();
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.retrieve(&mut p, "old").unwrap();
+    assert!(j2.rekey(fcrypt::CipherId::ChaCha20Poly1305, fcrypt::KdfId::Sha256, fcrypt::KdfId::Sha256.default_params()));
+    assert!(!j2.stored_file_changed(&mut p).unwrap());
+    j2.persist(&mut p, "new").unwrap();
+    assert!(!j2.stored_file_changed(&mut p).unwrap());
+
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j3.retrieve(&mut p, "new").unwrap();
+    assert_eq!(j3.cipher_id, Some(fcrypt::CipherId::ChaCha20Poly1305));
+    assert_eq!(j3.get(&String::from("e1")), Some(String::from("data1")));
+
+    let _ = remove_file(&file_name);
+}
+
+#[test]
+pub fn test_jots_redo() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let e2 = String::from("e2");
+    let e3 = String::from("e3");
+
+    j.add(&e1, &String::from("data1"));
+    j.mark_as_clean();
+
+    j.add(&e2, &String::from("data2"));
+    j.modify(&e1, &String::from("data1 modified"));
+    assert!(j.rename(&e2, &e3));
+    j.delete(&e1);
+    assert!(!j.can_redo());
+
+    while j.is_dirty() {
+        assert!(j.undo().1);
+    }
+
+    assert_eq!(j.len(), 1);
+    assert_eq!(j.get(&e1).unwrap(), "data1");
+    assert_eq!(j.trash.len(), 0);
+    assert!(j.can_redo());
+
+    let (comment, res) = j.redo();
+    assert!(res);
+    assert_eq!(comment, "Add entry 'e2'");
+    assert!(j.is_dirty());
+
+    while j.can_redo() {
+        assert!(j.redo().1);
+    }
+
+    assert_eq!(j.len(), 1);
+    assert_eq!(j.get(&e3).unwrap(), "data2");
+    assert_eq!(j.trash.len(), 1);
+    assert_eq!(j.trash[0].value.text, "data1 modified");
+
+    j.undo();
+    j.undo();
+    assert_eq!(j.get(&e2).unwrap(), "data2");
+    assert_eq!(j.get(&e1).unwrap(), "data1 modified");
+
+    // A new change discards the changes which could have been redone
+    j.add(&e3, &String::from("data3"));
+    assert!(!j.can_redo());
+    assert_eq!(j.redo(), (String::new(), true));
+    assert_eq!(j.undoer.get_comments().len(), 3);
+}
+
+#[test]
+pub fn test_jots_journal() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let e1 = String::from("e1");
+    let e2 = String::from("e2");
+    let e3 = String::from("e3");
+    let file_name = journal::file_name_for("test_journal.enc");
+    let pw = "this is a test";
+
+    assert_eq!(journal::file_name_for("https://server/dir/pw.enc"), "pw.enc.journal");
+
+    j.add(&e1, &String::from("data1"));
+    j.add(&e2, &String::from("data2"));
+    j.mark_as_clean();
+    let saved = j.contents.clone();
+
+    assert!(j.enable_journal(&file_name, pw).unwrap());
+    j.add(&e3, &String::from("data3"));
+    let first_change = std::fs::read_to_string(&file_name).unwrap();
+    j.modify(&e1, &String::from("data1 modified"));
+    assert!(j.rename(&e3, &String::from("e4")));
+    j.delete(&e2);
+    assert!(journal::exists(&file_name));
+    assert_eq!(j.journal.as_ref().unwrap().ops.len(), 4);
+
+    // Changes are appended to the journal. The header and the changes written before stay as they are.
+    let all_changes = std::fs::read_to_string(&file_name).unwrap();
+    assert!(all_changes.starts_with(&first_change));
+    assert_eq!(all_changes.lines().count(), 5);
+
+    // Simulate a crash by reading the saved version into a new store
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.contents = saved.clone();
+    assert!(j2.replay_journal(&file_name, "wrong password").is_err());
+    assert_eq!(j2.replay_journal(&file_name, pw).unwrap(), 4);
+    assert_eq!(j2.contents, j.contents);
+    assert_eq!(j2.trash, j.trash);
+    assert!(j2.is_dirty());
+
+    j2.undo();
+    assert_eq!(j2.contents, saved);
+
+    // A change which was only partially appended is ignored
+    std::fs::write(&file_name, format!("{}{{\"Nonce\":", all_changes)).unwrap();
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j3.contents = saved.clone();
+    assert_eq!(j3.replay_journal(&file_name, pw).unwrap(), 4);
+    assert_eq!(j3.contents, j.contents);
+
+    // Changes can not be reordered
+    let mut lines: Vec<&str> = all_changes.lines().collect();
+    lines.swap(1, 2);
+    std::fs::write(&file_name, format!("{}\n", lines.join("\n"))).unwrap();
+    assert!(j3.replay_journal(&file_name, pw).is_err());
+
+    // A new password causes the journal to be written again
+    j.set_journal_password("another password").unwrap();
+    assert!(j3.replay_journal(&file_name, pw).is_err());
+    let mut j4 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j4.contents = saved.clone();
+    assert_eq!(j4.replay_journal(&file_name, "another password").unwrap(), 4);
+    assert_eq!(j4.contents, j.contents);
+
+    // Undoing all changes makes the journal obsolete
+    while j.is_dirty() {
+        j.undo();
+    }
+
+    assert!(!journal::exists(&file_name));
+
+    j.add(&e3, &String::from("data3"));
+    assert!(journal::exists(&file_name));
+    j.mark_as_clean();
+    assert!(!journal::exists(&file_name));
+}
+
+#[test]
+pub fn test_kdf_params() {
+    let (d, i) = fcrypt::KdfId::Argon2.to_named_func();
+    let data_raw: Vec<u8> = vec![0; 32];
+    let strong = fcrypt::KdfParams::new(16 * 1024, 3, 1);
+    let mut cipher_json: Vec<u8> = Vec::new();
+
+    assert_eq!(fcrypt::KdfParams::from_str("16384, 3,1"), Some(strong));
+    assert_eq!(fcrypt::KdfParams::from_str("16384,3"), None);
+    assert!(strong.check(fcrypt::KdfId::Argon2).is_none());
+    assert!(fcrypt::KdfParams::new(1024, 3, 1).check(fcrypt::KdfId::Argon2).is_some());
+    assert!(fcrypt::KdfParams::new(40, 8, 2).check(fcrypt::KdfId::Scrypt).is_some());
+
+    let mut ctx = make_aes_gcm_cryptor(d, i);
+    ctx.set_kdf_params(strong);
+    let cipher_text = ctx.encrypt("this is a test", &data_raw).unwrap();
+    ctx.to_dyn_writer(&mut cipher_json, &cipher_text).unwrap();
+
+    // The parameters are read from the header
+    let mut ctx2 = make_aes_gcm_cryptor(d, i);
+    let cipher_raw = ctx2.from_dyn_reader(&mut cipher_json.as_slice()).unwrap();
+    assert_eq!(ctx2.kdf_params(), strong);
+    assert_eq!(ctx2.decrypt("this is a test", &cipher_raw).unwrap(), data_raw);
+
+    // Headers without parameters use the defaults
+    let legacy = String::from_utf8(cipher_json.clone()).unwrap().replace("\"KdfParams\"", "\"Unknown\"");
+    let mut ctx3 = make_aes_gcm_cryptor(d, i);
+    let _ = ctx3.from_dyn_reader(&mut legacy.as_bytes()).unwrap();
+    assert_eq!(ctx3.kdf_params(), fcrypt::KdfId::Argon2.default_params());
+
+    // Parameters which are too weak are rejected
+    let weak = String::from_utf8(cipher_json).unwrap().replace("16384", "1024");
+    let mut ctx4 = make_aes_gcm_cryptor(d, i);
+    assert!(ctx4.from_dyn_reader(&mut weak.as_bytes()).is_err());
+}
+
+#[test]
+pub fn test_self_describing_header() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let (d2, i2) = fcrypt::KdfId::Argon2.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_chacha20_cryptor));
+    let file_name = "test_header.enc";
+    let pw = "this is a test";
+
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.to_enc_file(file_name, pw).unwrap();
+
+    let raw = std::fs::read(file_name).unwrap();
+    assert_eq!(fcrypt::cipher_from_header(&raw), Some(fcrypt::CipherId::ChaCha20Poly1305));
+
+    // Cipher and KDF are taken from the file and are used for the next save
+    let mut j2 = jots::Jots::new_id(d2, i2, Box::new(make_aes_gcm_cryptor));
+    j2.from_enc_file(file_name, pw).unwrap();
+    assert_eq!(j2.contents, j.contents);
+    assert_eq!(j2.cipher_id, Some(fcrypt::CipherId::ChaCha20Poly1305));
+    assert_eq!(j2.kdf_id, fcrypt::KdfId::Sha256);
+
+    j2.to_enc_file(file_name, pw).unwrap();
+    let raw = std::fs::read(file_name).unwrap();
+    assert_eq!(fcrypt::cipher_from_header(&raw), Some(fcrypt::CipherId::ChaCha20Poly1305));
+
+    // Old headers do not name the cipher which then has to be configured
+    let legacy = String::from_utf8(raw).unwrap().replace("\"Cipher\"", "\"Unknown\"");
+    std::fs::write(file_name, legacy).unwrap();
+    assert_eq!(fcrypt::cipher_from_header(&std::fs::read(file_name).unwrap()), None);
+
+    let mut j3 = jots::Jots::new_id(d2, i2, Box::new(make_aes_gcm_cryptor));
+    assert!(j3.from_enc_file(file_name, pw).is_err());
+
+    let mut j4 = jots::Jots::new_id(d2, i2, Box::new(make_chacha20_cryptor));
+    j4.from_enc_file(file_name, pw).unwrap();
+    assert_eq!(j4.contents, j.contents);
+    assert_eq!(j4.kdf_id, fcrypt::KdfId::Sha256);
+
+    let _ = remove_file(file_name);
+}
+
+#[test]
+pub fn test_extended_nonce() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_extended_nonce.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+    let params = fcrypt::KdfId::Sha256.default_params();
+    let nonce_len = |r: &Vec<u8>, field: &str| {
+        let v = serde_json::from_slice::<serde_json::Value>(r).unwrap();
+        BASE64_STANDARD.decode(v[field].as_str().unwrap()).unwrap().len()
+    };
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_xchacha20_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.persist(&mut p, "pw1").unwrap();
+
+    let raw = std::fs::read(&file_name).unwrap();
+    assert_eq!(fcrypt::cipher_from_header(&raw), Some(fcrypt::CipherId::XChaCha20Poly1305));
+    assert_eq!(nonce_len(&raw, "Nonce"), 24);
+
+    // The nonce size is checked before the file is decrypted
+    let other = String::from_utf8(raw.clone()).unwrap().replace("\"xchacha20\"", "\"chacha20\"");
+    let mut c = make_chacha20_cryptor(d, i);
+    let e = c.from_dyn_reader(&mut other.as_bytes()).err().unwrap();
+    assert_eq!(e.to_string(), "Unsupported nonce size");
+
+    // Key slots are wrapped with the cipher of the file
+    j.add_key_slot("second", "pw2", fcrypt::KdfId::Sha256, params).unwrap();
+    j.persist(&mut p, "pw1").unwrap();
+    let raw = std::fs::read(&file_name).unwrap();
+    let v = serde_json::from_slice::<serde_json::Value>(&raw).unwrap();
+    assert_eq!(BASE64_STANDARD.decode(v["KeySlots"][0]["Nonce"].as_str().unwrap()).unwrap().len(), 24);
+
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j3.retrieve(&mut p, "pw2").unwrap();
+    assert_eq!(j3.contents, j.contents);
+    assert_eq!(j3.cipher_id, Some(fcrypt::CipherId::XChaCha20Poly1305));
+
+    assert!(j3.rekey(fcrypt::CipherId::Aes256GcmSiv, fcrypt::KdfId::Sha256, params));
+    j3.persist(&mut p, "pw2").unwrap();
+    let raw = std::fs::read(&file_name).unwrap();
+    assert_eq!(fcrypt::cipher_from_header(&raw), Some(fcrypt::CipherId::Aes256GcmSiv));
+    assert_eq!(nonce_len(&raw, "Nonce"), 12);
+
+    let mut j4 = jots::Jots::new_id(d, i, Box::new(make_xchacha20_cryptor));
+    j4.retrieve(&mut p, "pw1").unwrap();
+    assert_eq!(j4.contents, j.contents);
+
+    let _ = remove_file(&file_name);
+}
+
+#[test]
+pub fn test_binary_container() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_binary_container.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+    let params = fcrypt::KdfId::Sha256.default_params();
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.add(&String::from("e2"), &String::from("a somewhat longer text which makes the difference in size visible"));
+    j.persist(&mut p, "pw1").unwrap();
+    let json_raw = std::fs::read(&file_name).unwrap();
+    assert_eq!(json_raw[0], b'{');
+
+    j.container_format = fcrypt::ContainerFormat::Binary;
+    j.persist(&mut p, "pw1").unwrap();
+    let raw = std::fs::read(&file_name).unwrap();
+    assert!(raw.starts_with(b"RPWM"));
+    assert!(raw.len() < json_raw.len());
+    assert_eq!(fcrypt::cipher_from_header(&raw), Some(fcrypt::CipherId::Aes256Gcm));
+
+    // The format is detected when reading and kept when saving
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_chacha20_cryptor));
+    j2.retrieve(&mut p, "pw1").unwrap();
+    assert_eq!(j2.contents, j.contents);
+    assert_eq!(j2.container_format, fcrypt::ContainerFormat::Binary);
+
+    j2.add_key_slot("second", "pw2", fcrypt::KdfId::Sha256, params).unwrap();
+    j2.persist(&mut p, "pw1").unwrap();
+    let raw = std::fs::read(&file_name).unwrap();
+    assert!(raw.starts_with(b"RPWM"));
+
+    // Replacing the key slots keeps the format and the ciphertext
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j3.retrieve(&mut p, "pw2").unwrap();
+    j3.add_key_slot("third", "pw3", fcrypt::KdfId::Sha256, params).unwrap();
+    let new_raw = fcrypt::replace_key_slots(&raw, j3.key_slots.as_ref().unwrap()).unwrap();
+    assert!(new_raw.starts_with(b"RPWM"));
+    assert!(new_raw.ends_with(&raw[raw.len() - 32..]));
+    std::fs::write(&file_name, &new_raw).unwrap();
+    assert_eq!(fcrypt::key_slot_labels(&new_raw).unwrap().len(), 3);
+
+    let mut j4 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j4.retrieve(&mut p, "pw3").unwrap();
+    assert_eq!(j4.contents, j.contents);
+
+    // Back to JSON
+    j4.container_format = fcrypt::ContainerFormat::Json;
+    j4.persist(&mut p, "pw3").unwrap();
+    let raw = std::fs::read(&file_name).unwrap();
+    assert_eq!(raw[0], b'{');
+    j4.retrieve(&mut p, "pw1").unwrap();
+    assert_eq!(j4.contents, j.contents);
+
+    // Truncated files and unknown encoding versions are rejected
+    let mut c = make_aes_gcm_cryptor(d, i);
+    assert!(c.from_dyn_reader(&mut &new_raw[0..20]).is_err());
+    let mut other = new_raw.clone();
+    other[4] = 2;
+    assert!(c.from_dyn_reader(&mut other.as_slice()).is_err());
+
+    let _ = remove_file(&file_name);
+}
+
+#[test]
+pub fn test_password_length() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let diceware = "correct horse battery staple ".repeat(4);
+    assert!(diceware.len() > 100);
+
+    assert!(fcrypt::check_password(&diceware, fcrypt::KdfId::Argon2).is_none());
+    assert!(fcrypt::check_password(&diceware, fcrypt::KdfId::Scrypt).is_none());
+    assert!(fcrypt::check_password(&diceware, fcrypt::KdfId::Sha256).is_some());
+    assert!(fcrypt::check_password(&diceware[0..50], fcrypt::KdfId::Sha256).is_none());
+    assert!(fcrypt::check_existing_password(&diceware).is_none());
+    assert!(fcrypt::check_existing_password(&"x".repeat(2000)).is_some());
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j.add_key_slot("long", &diceware, fcrypt::KdfId::Sha256, fcrypt::KdfId::Sha256.default_params()).is_err());
+    j.add_key_slot("long", &diceware, fcrypt::KdfId::Argon2, fcrypt::KdfId::Argon2.default_params()).unwrap();
+}
+
+#[test]
+pub fn test_kdf_upgrade() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_kdf_upgrade.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+
+    assert!(fcrypt::KdfId::Sha256.is_deprecated());
+    assert!(!fcrypt::KdfId::Argon2.is_deprecated());
+    assert!(!fcrypt::KdfId::Scrypt.is_deprecated());
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_chacha20_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.persist(&mut p, "pw").unwrap();
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.retrieve(&mut p, "pw").unwrap();
+    assert_eq!(j2.kdf_id, fcrypt::KdfId::Sha256);
+    assert!(!j2.is_dirty());
+
+    assert!(!j2.upgrade_kdf(fcrypt::KdfParams::new(1024, 1, 1)));
+    assert_eq!(j2.kdf_id, fcrypt::KdfId::Sha256);
+    assert!(j2.upgrade_kdf(fcrypt::KdfId::Argon2.default_params()));
+    assert!(j2.is_dirty());
+    j2.persist(&mut p, "pw").unwrap();
+
+    // The cipher is kept
+    let raw = std::fs::read(&file_name).unwrap();
+    assert_eq!(fcrypt::cipher_from_header(&raw), Some(fcrypt::CipherId::ChaCha20Poly1305));
+
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j3.retrieve(&mut p, "pw").unwrap();
+    assert_eq!(j3.contents, j.contents);
+    assert_eq!(j3.kdf_id, fcrypt::KdfId::Argon2);
+
+    let _ = remove_file(&file_name);
+}
+
+#[test]
+pub fn test_jots_rekey() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    let file_name = String::from("test_rekey.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.persist(&mut p, "old password").unwrap();
+
+    let strong = fcrypt::KdfParams::new(16 * 1024, 3, 1);
+    assert!(!j.rekey(fcrypt::CipherId::ChaCha20Poly1305, fcrypt::KdfId::Argon2, fcrypt::KdfParams::new(1024, 1, 1)));
+    assert_eq!(j.kdf_id, fcrypt::KdfId::Sha256);
+    assert!(j.rekey(fcrypt::CipherId::ChaCha20Poly1305, fcrypt::KdfId::Argon2, strong));
+    j.persist(&mut p, "new password").unwrap();
+    assert!(!std::path::Path::new("test_rekey.enc.tmp").exists());
+
+    let raw = std::fs::read(&file_name).unwrap();
+    assert_eq!(fcrypt::cipher_from_header(&raw), Some(fcrypt::CipherId::ChaCha20Poly1305));
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j2.retrieve(&mut p, "old password").is_err());
+    j2.retrieve(&mut p, "new password").unwrap();
+    assert_eq!(j2.contents, j.contents);
+    assert_eq!(j2.kdf_id, fcrypt::KdfId::Argon2);
+    assert_eq!(j2.kdf_params, strong);
+
+    let _ = remove_file(&file_name);
+}
+
+// Written by a version of rustpwman which did not authenticate the header. Contains entry e1 with value data1.
+#[cfg(test)]
+const V1_CONTAINER: &str = r#"{
+  "PbKdf": "sha256",
+  "Cipher": "aes256",
+  "Salt": "mnoWP46EJOiWCVLXyXAmUQ==",
+  "Nonce": "Igs/X1qJeFXdx8nj",
+  "Data": "qiIxv4gXb0YigIYs9UCjPf3IP8axqskTHSDaHWkyXAyweoRSGJwd9A0w+WCHbNZLeHbVF0VGCqpadWrYCuhyEs7XazhvJakHdslxPyntC/ruTtE61NSY1/88ijbIN+GwwIDgpg/CYx/8QRb84CY3Tz907qqqy3CLkIozMKLwHG+/itTe/ZvqPaNGGba+3WdyvE+P+KlMyPA="
+}"#;
+
+#[test]
+pub fn test_authenticated_header() {
+    let (d, i) = fcrypt::KdfId::Argon2.to_named_func();
+    let data_raw: Vec<u8> = vec![0; 32];
+    let pw = "this is a test";
+    let mut cipher_json: Vec<u8> = Vec::new();
+
+    let mut ctx = make_aes_gcm_cryptor(d, i);
+    ctx.set_kdf_params(fcrypt::KdfParams::new(16 * 1024, 3, 1));
+    let cipher_text = ctx.encrypt(pw, &data_raw).unwrap();
+    ctx.to_dyn_writer(&mut cipher_json, &cipher_text).unwrap();
+    let header = String::from_utf8(cipher_json).unwrap();
+    assert!(header.contains("\"Version\": 2"));
+
+    let mut ctx2 = make_aes_gcm_cryptor(d, i);
+    let cipher_raw = ctx2.from_dyn_reader(&mut header.as_bytes()).unwrap();
+    assert_eq!(ctx2.decrypt(pw, &cipher_raw).unwrap(), data_raw);
+
+    // Weakening the KDF parameters or downgrading the version is detected
+    for tampered in [header.replace("16384", "15360"), header.replace("\"Version\": 2", "\"Version\": 1")] {
+        let mut ctx3 = make_aes_gcm_cryptor(d, i);
+        let cipher_raw = ctx3.from_dyn_reader(&mut tampered.as_bytes()).unwrap();
+        assert!(ctx3.decrypt(pw, &cipher_raw).is_err());
+    }
+
+    let unknown_version = header.replace("\"Version\": 2", "\"Version\": 4");
+    let mut ctx4 = make_aes_gcm_cryptor(d, i);
+    assert!(ctx4.from_dyn_reader(&mut unknown_version.as_bytes()).is_err());
+
+    // Old files can still be read and are upgraded when they are saved
+    let file_name = "test_v1.enc";
+    std::fs::write(file_name, V1_CONTAINER).unwrap();
+
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j.from_enc_file(file_name, pw).unwrap();
+    assert_eq!(j.get(&String::from("e1")), Some(String::from("data1")));
+
+    j.to_enc_file(file_name, pw).unwrap();
+    assert!(std::fs::read_to_string(file_name).unwrap().contains("\"Version\": 2"));
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.from_enc_file(file_name, pw).unwrap();
+    assert_eq!(j2.contents, j.contents);
+
+    let _ = remove_file(file_name);
+}
+
+#[test]
+pub fn test_secret() {
+    use crate::secret::{SecretString, SecretKey, LAST_UNLOCK_LEN};
+    use zeroize::Zeroize;
+
+    let pw = SecretString::from("this is a test");
+    assert_eq!(pw, String::from("this is a test"));
+    assert_eq!(pw.len(), 14);
+    assert_eq!(format!("{:?}", pw), "Secret(***)");
+
+    let (d, _) = fcrypt::KdfId::Sha256.to_named_func();
+    let key: SecretKey = d(&vec![0; 16], &pw, &fcrypt::KdfId::Sha256.default_params());
+    assert_eq!(key.len(), 32);
+    assert_eq!(format!("{:?}", key), "SecretKey(***)");
+
+    // The whole key is unlocked when it is dropped. Locking may not be permitted, e.g. because of resource limits.
+    if key.is_locked() {
+        LAST_UNLOCK_LEN.with(|l| l.set(0));
+        drop(key);
+        assert_eq!(LAST_UNLOCK_LEN.with(|l| l.get()), 32);
+    }
+
+    let mut e = jots::Entry::new(&String::from("text"));
+    e.password = String::from("secret");
+    e.custom_fields.push(jots::CustomField::new("PIN", "1234"));
+    e.zeroize();
+    assert!(e.password.is_empty());
+    assert!(e.text.is_empty());
+    assert!(e.custom_fields[0].value.is_empty());
+}
+
+#[test]
+pub fn test_key_file() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let key_file = "test_key_file.key";
+    let other_key_file = "test_key_file2.key";
+    let file_name = "test_key_file.enc";
+    let pw = "this is a test";
+
+    let _ = remove_file(key_file);
+    let _ = remove_file(other_key_file);
+    fcrypt::create_key_file(key_file).unwrap();
+    fcrypt::create_key_file(other_key_file).unwrap();
+    // Existing key files are never overwritten
+    assert!(fcrypt::create_key_file(key_file).is_err());
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.key_file = Some(fcrypt::key_file_hash(key_file).unwrap());
+    j.to_enc_file(file_name, pw).unwrap();
+    assert!(std::fs::read_to_string(file_name).unwrap().contains("\"KeyFile\": true"));
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j2.from_enc_file(file_name, pw).is_err());
+
+    j2.key_file = Some(fcrypt::key_file_hash(other_key_file).unwrap());
+    assert!(j2.from_enc_file(file_name, pw).is_err());
+
+    j2.key_file = Some(fcrypt::key_file_hash(key_file).unwrap());
+    j2.from_enc_file(file_name, pw).unwrap();
+    assert_eq!(j2.contents, j.contents);
+
+    // Only a store which is protected by a password in addition to the key file writes a journal
+    let journal_name = journal::file_name_for(file_name);
+    assert!(!j2.enable_journal(&journal_name, "").unwrap());
+    assert!(j2.enable_journal(&journal_name, pw).unwrap());
+    j2.set_journal_password("").unwrap();
+    assert!(j2.journal.is_none());
+
+    // Removing the requirement from the header is detected
+    let tampered = std::fs::read_to_string(file_name).unwrap().replace("\"KeyFile\": true", "\"KeyFile\": false");
+    std::fs::write(file_name, tampered).unwrap();
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j3.from_enc_file(file_name, pw).is_err());
+
+    let _ = remove_file(file_name);
+    let _ = remove_file(key_file);
+    let _ = remove_file(other_key_file);
+}
+
+#[test]
+pub fn test_key_slots() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_key_slots.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+    let params = fcrypt::KdfId::Sha256.default_params();
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.persist(&mut p, "pw1").unwrap();
+    assert!(j.key_slots.is_none());
+
+    // Converts the file to key slots
+    j.add_key_slot("ops", "pw2", fcrypt::KdfId::Sha256, params).unwrap();
+    assert!(j.add_key_slot("ops", "pw3", fcrypt::KdfId::Sha256, params).is_err());
+    j.persist(&mut p, "pw1").unwrap();
+
+    let raw = std::fs::read(&file_name).unwrap();
+    assert_eq!(fcrypt::key_slot_labels(&raw).unwrap(), vec![String::from("ops"), String::from(jots::DEFAULT_KEY_SLOT)]);
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.retrieve(&mut p, "pw2").unwrap();
+    assert_eq!(j2.contents, j.contents);
+    assert!(j2.retrieve(&mut p, "pw3").is_err());
+
+    // Adding a slot only changes the header
+    j2.add_key_slot("third", "pw3", fcrypt::KdfId::Sha256, params).unwrap();
+    let new_raw = fcrypt::replace_key_slots(&raw, j2.key_slots.as_ref().unwrap()).unwrap();
+    let data_of = |r: &Vec<u8>| serde_json::from_slice::<serde_json::Value>(r).unwrap()["Data"].clone();
+    assert_eq!(data_of(&raw), data_of(&new_raw));
+    std::fs::write(&file_name, &new_raw).unwrap();
+
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j3.retrieve(&mut p, "pw3").unwrap();
+    assert_eq!(j3.contents, j.contents);
+
+    // The slot which was used to open the file can not be revoked
+    assert!(j3.revoke_key_slot("third").is_err());
+    assert!(j3.revoke_key_slot("unknown").is_err());
+    j3.revoke_key_slot("ops").unwrap();
+    // Saving with another password only changes the slot which was unlocked
+    j3.persist(&mut p, "pw4").unwrap();
+
+    let mut j4 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j4.retrieve(&mut p, "pw2").is_err());
+    assert!(j4.retrieve(&mut p, "pw3").is_err());
+    j4.retrieve(&mut p, "pw4").unwrap();
+    j4.retrieve(&mut p, "pw1").unwrap();
+    assert_eq!(j4.contents, j.contents);
+    assert_eq!(j4.key_slot_labels(), vec![String::from(jots::DEFAULT_KEY_SLOT), String::from("third")]);
+
+    let _ = remove_file(&file_name);
+}
+
+#[test]
+pub fn test_recipients() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = "test_recipients.enc";
+    let key_names = ["test_alice.key", "test_bob.key", "test_carol.key"];
+
+    for k in key_names {
+        let _ = remove_file(k);
+        let _ = remove_file(fcrypt::public_key_file_name(k));
+        fcrypt::generate_key_pair(k, k).unwrap();
+    }
+
+    // Existing keys are never overwritten
+    assert!(fcrypt::generate_key_pair(key_names[0], "other").is_err());
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.recipients.push(fcrypt::read_public_key(&fcrypt::public_key_file_name(key_names[0])).unwrap());
+    j.recipients.push(fcrypt::read_public_key(&fcrypt::public_key_file_name(key_names[1])).unwrap());
+    j.to_enc_file(file_name, "").unwrap();
+
+    let raw = std::fs::read(file_name).unwrap();
+    assert_eq!(fcrypt::recipients_from_header(&raw), j.recipients);
+    assert!(String::from_utf8(raw).unwrap().contains("\"Version\": 4"));
+
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j2.from_enc_file(file_name, "").is_err());
+
+    j2.private_key = Some(fcrypt::read_private_key(key_names[2]).unwrap());
+    assert!(j2.from_enc_file(file_name, "").is_err());
+
+    j2.private_key = Some(fcrypt::read_private_key(key_names[1]).unwrap());
+    j2.from_enc_file(file_name, "").unwrap();
+    assert_eq!(j2.contents, j.contents);
+
+    // Saving keeps the recipients
+    j2.add(&String::from("e2"), &String::from("data2"));
+    j2.to_enc_file(file_name, "").unwrap();
+
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j3.private_key = Some(fcrypt::read_private_key(key_names[0]).unwrap());
+    j3.from_enc_file(file_name, "").unwrap();
+    assert_eq!(j3.contents, j2.contents);
+    assert_eq!(j3.recipients, j.recipients);
+
+    // The empty password would be the only secret of a journal, so none is written
+    let journal_name = journal::file_name_for(file_name);
+    let _ = remove_file(&journal_name);
+    assert!(!j3.enable_journal(&journal_name, "").unwrap());
+    j3.add(&String::from("e3"), &String::from("data3"));
+    assert!(!journal::exists(&journal_name));
+    assert!(journal::read(&journal_name, "", &None).is_err());
+
+    // A file which nobody could open is never written
+    let inner = make_aes_gcm_cryptor(d, i);
+    let c: Box<dyn fcrypt::Cryptor> = Box::new(fcrypt::RecipientCryptor::new(inner, j.recipients.clone(), None));
+    let mut out: Vec<u8> = Vec::new();
+    assert!(c.to_dyn_writer(&mut out, &vec![0; 32]).is_err());
+    assert!(out.is_empty());
+
+    let _ = remove_file(file_name);
+    for k in key_names {
+        let _ = remove_file(k);
+        let _ = remove_file(fcrypt::public_key_file_name(k));
+    }
+}
+
+#[test]
+pub fn test_verify() {
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = "test_verify.enc";
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_chacha20_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.add_key_slot("second", "pw2", fcrypt::KdfId::Sha256, fcrypt::KdfId::Sha256.default_params()).unwrap();
+    j.container_format = fcrypt::ContainerFormat::Binary;
+    j.to_enc_file(file_name, "pw1").unwrap();
+    let raw = std::fs::read(file_name).unwrap();
+
+    // The header can be inspected without a password
+    let info = fcrypt::container_info(&raw).unwrap();
+    assert_eq!(info.version, 3);
+    assert_eq!(info.format, fcrypt::ContainerFormat::Binary);
+    assert_eq!(info.cipher_id, Some(fcrypt::CipherId::ChaCha20Poly1305));
+    assert_eq!(info.kdf_id, fcrypt::KdfId::Sha256);
+    assert!(!info.key_file_required);
+    assert_eq!(info.key_slots.len(), 2);
+    assert_eq!(info.recipients.len(), 0);
+
+    assert!(fcrypt::container_info(&raw[0..20].to_vec()).is_err());
+
+    let plain = j.decrypt_other(&raw, "pw2").unwrap();
+    assert_eq!(jots::Jots::check_payload(plain.as_slice()).unwrap(), (1, 0));
+    assert!(j.decrypt_other(&raw, "wrong").is_err());
+
+    // A payload which is correctly encrypted but does not contain entries is detected
+    let mut c = make_chacha20_cryptor(d, i);
+    let enc = c.encrypt("pw1", &b"no entries".to_vec()).unwrap();
+    c.to_file(&enc, file_name).unwrap();
+    let raw = std::fs::read(file_name).unwrap();
+    assert!(fcrypt::container_info(&raw).is_ok());
+    let plain = j.decrypt_other(&raw, "pw1").unwrap();
+    assert!(jots::Jots::check_payload(plain.as_slice()).is_err());
+
+    let _ = remove_file(file_name);
+}
+
+// Cost model which makes the calibration independent of the speed of the machine
+#[cfg(test)]
+fn kdf_cost_model(id: fcrypt::KdfId, p: &fcrypt::KdfParams) -> std::time::Duration {
+    return match id {
+        fcrypt::KdfId::Argon2 => std::time::Duration::from_micros(p.m as u64 * p.t as u64),
+        fcrypt::KdfId::Scrypt => std::time::Duration::from_micros((1u64 << p.m) * p.t as u64 * p.p as u64 / 8),
+        fcrypt::KdfId::Sha256 => std::time::Duration::from_micros(1)
+    };
+}
+
+#[test]
+pub fn test_kdf_calibration() {
+    let target = std::time::Duration::from_secs(1);
+    let argon2_defaults = fcrypt::KdfId::Argon2.default_params();
+
+    // The memory is interpolated between the last two powers of two
+    let c = fcrypt::calibrate_with(fcrypt::KdfId::Argon2, target, 4 * 1024 * 1024, kdf_cost_model);
+    assert_eq!(c.recommended, fcrypt::KdfParams::new(488 * 1024, argon2_defaults.t, argon2_defaults.p));
+    assert!(c.recommended_duration().unwrap() <= target);
+    assert!(kdf_cost_model(fcrypt::KdfId::Argon2, &fcrypt::KdfParams::new(489 * 1024, 2, 1)) > target);
+
+    // When the memory limit is reached the number of passes is raised
+    let c = fcrypt::calibrate_with(fcrypt::KdfId::Argon2, target, 64 * 1024, kdf_cost_model);
+    assert_eq!(c.recommended, fcrypt::KdfParams::new(64 * 1024, 15, argon2_defaults.p));
+    assert!(c.recommended.check(fcrypt::KdfId::Argon2).is_none());
+
+    // Weaker parameters than the defaults are never recommended
+    let c = fcrypt::calibrate_with(fcrypt::KdfId::Argon2, std::time::Duration::from_millis(1), 64 * 1024, kdf_cost_model);
+    assert_eq!(c.recommended, argon2_defaults);
+    assert_eq!(c.measurements.len(), 1);
+    assert!(c.recommended_duration().unwrap() > std::time::Duration::from_millis(1));
+
+    // scrypt closes the gap to the target by raising p
+    let c = fcrypt::calibrate_with(fcrypt::KdfId::Scrypt, target, 4 * 1024 * 1024, kdf_cost_model);
+    assert_eq!(c.recommended, fcrypt::KdfParams::new(18, 8, 3));
+    assert!(c.recommended.check(fcrypt::KdfId::Scrypt).is_none());
+
+    // N = 2^15 with r = 8 needs 32 MiB
+    let c = fcrypt::calibrate_with(fcrypt::KdfId::Scrypt, target, 32 * 1024, kdf_cost_model);
+    assert_eq!(c.recommended.m, 15);
+    assert_eq!(c.recommended.p, 16);
+
+    let c = fcrypt::calibrate_with(fcrypt::KdfId::Sha256, target, 1024, kdf_cost_model);
+    assert_eq!(c.measurements.len(), 1);
+
+    // The real measurement
+    let d = fcrypt::calibrate(fcrypt::KdfId::Sha256, target, 1024).recommended_duration().unwrap();
+    assert!(d < target);
+}
+
+#[test]
+pub fn test_recovery_shares() {
+    let secret = fcrypt::generate_recovery_secret();
+    let shares = fcrypt::split_recovery_secret(&secret, 3, 5).unwrap();
+    assert_eq!(shares.len(), 5);
+    assert!(fcrypt::split_recovery_secret(&secret, 1, 5).is_err());
+    assert!(fcrypt::split_recovery_secret(&secret, 4, 3).is_err());
+
+    // Any three shares reconstruct the secret
+    let some: Vec<String> = vec![shares[4].clone(), shares[0].clone(), shares[2].clone()];
+    assert_eq!(fcrypt::combine_shares(&some).unwrap(), secret);
+    assert_eq!(fcrypt::combine_shares(&shares).unwrap(), secret);
+
+    // Duplicates are only counted once
+    let too_few: Vec<String> = vec![shares[1].clone(), shares[3].clone(), shares[1].clone()];
+    assert!(fcrypt::combine_shares(&too_few).is_err());
+    assert!(fcrypt::combine_shares(&Vec::new()).is_err());
+
+    // Shares of different secrets can not be mixed
+    let other = fcrypt::split_recovery_secret(&fcrypt::generate_recovery_secret(), 3, 5).unwrap();
+    let mixed: Vec<String> = vec![shares[0].clone(), shares[1].clone(), other[2].clone()];
+    assert!(fcrypt::combine_shares(&mixed).is_err());
+
+    // A damaged share is detected
+    let mut parts: Vec<String> = shares[2].split(':').map(String::from).collect();
+    let mut value = BASE64_STANDARD.decode(&parts[3]).unwrap();
+    value[5] ^= 1;
+    parts[3] = BASE64_STANDARD.encode(value);
+    let damaged: Vec<String> = vec![shares[0].clone(), shares[1].clone(), parts.join(":")];
+    assert!(fcrypt::combine_shares(&damaged).is_err());
+
+    assert!(fcrypt::share_to_qr(&shares[0]).unwrap().lines().count() > 10);
+
+    // Coefficients are drawn from the whole field. With a threshold of 2 the share at x = 1 is the secret plus the
+    // coefficient, so a coefficient of 0 shows up as a byte which equals the secret.
+    let mut zero_coefficients = 0;
+    for _ in 0..300 {
+        let pair = fcrypt::split_recovery_secret(&secret, 2, 2).unwrap();
+        let value = BASE64_STANDARD.decode(pair[0].split(':').last().unwrap()).unwrap();
+        assert_eq!(value[0], 1);
+        zero_coefficients += value[1..].iter().zip(secret.iter()).filter(|(y, s)| y == s).count();
+        assert_eq!(fcrypt::combine_shares(&pair).unwrap(), secret);
+    }
+    assert!(zero_coefficients > 0);
+
+    // The recovery slot allows to set a new password
+    let (d, i) = fcrypt::KdfId::Sha256.to_named_func();
+    let file_name = String::from("test_recovery_shares.enc");
+    let mut p = crate::persist::FilePersister::new(&file_name);
+    let params = fcrypt::KdfId::Sha256.default_params();
+
+    let mut j = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j.add(&String::from("e1"), &String::from("data1"));
+    j.set_recovery_secret(&secret, fcrypt::KdfId::Sha256, params).unwrap();
+    j.persist(&mut p, "pw1").unwrap();
+    assert_eq!(j.key_slot_labels(), vec![String::from(jots::DEFAULT_KEY_SLOT), String::from(fcrypt::RECOVERY_KEY_SLOT)]);
+
+    let recovered = fcrypt::combine_shares(&some).unwrap();
+    let mut j2 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j2.retrieve(&mut p, &fcrypt::recovery_password(&recovered)).unwrap();
+    assert_eq!(j2.contents, j.contents);
+
+    j2.replace_key_slot(jots::DEFAULT_KEY_SLOT, "pw2", fcrypt::KdfId::Sha256, params).unwrap();
+    j2.persist(&mut p, &fcrypt::recovery_password(&recovered)).unwrap();
+
+    let mut j3 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    assert!(j3.retrieve(&mut p, "pw1").is_err());
+    j3.retrieve(&mut p, "pw2").unwrap();
+    assert_eq!(j3.contents, j.contents);
+
+    // New shares replace the old ones
+    j3.set_recovery_secret(&fcrypt::generate_recovery_secret(), fcrypt::KdfId::Sha256, params).unwrap();
+    j3.persist(&mut p, "pw2").unwrap();
+    assert!(j3.retrieve(&mut p, &fcrypt::recovery_password(&recovered)).is_err());
+
+    // The shares alone would not open a file which requires a key file
+    let mut j4 = jots::Jots::new_id(d, i, Box::new(make_aes_gcm_cryptor));
+    j4.key_file = Some(crate::secret::SecretBytes::new(vec![1; 32]));
+    assert!(j4.set_recovery_secret(&secret, fcrypt::KdfId::Sha256, params).is_err());
+    assert!(j4.key_slots.is_none());
+
+    let _ = remove_file(&file_name);
+}
@@ -0,0 +1,52 @@
+/* Copyright 2025 Martin Grap
+
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License. */
+
+
+use chrono::{Local, TimeZone};
+
+// Timestamps are stored as seconds since the Unix epoch. The value 0 means that the time is unknown,
+// which is the case for all data that was written before timestamps were recorded.
+pub type Timestamp = i64;
+
+pub const UNKNOWN: Timestamp = 0;
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub fn is_unknown(ts: &Timestamp) -> bool {
+    return *ts == UNKNOWN;
+}
+
+// Returns the number of full days which have passed between from and to
+pub fn days_between(from: Timestamp, to: Timestamp) -> u64 {
+    if to <= from {
+        return 0;
+    }
+
+    return ((to - from) / SECONDS_PER_DAY) as u64;
+}
+
+pub fn now() -> Timestamp {
+    return Local::now().timestamp();
+}
+
+// Formats a timestamp using the local time zone
+pub fn format(ts: Timestamp) -> String {
+    if is_unknown(&ts) {
+        return String::from("Unknown");
+    }
+
+    return match Local.timestamp_opt(ts, 0).single() {
+        Some(t) => t.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => String::from("Unknown")
+    };
+}
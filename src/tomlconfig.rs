@@ -30,10 +30,19 @@ pub struct RustPwManSerialize {
     pub webdav_user: String,
     pub webdav_pw: String,
     pub webdav_server: String,
+    // Number of days after which entries are purged from the recycle bin. 0 means never.
+    #[serde(default)]
+    pub trash_days: u64,
+    // KDF cost parameters of the form "m,t,p" used for new files. An empty string means the default parameters are used.
+    #[serde(default)]
+    pub argon2_params: String,
+    #[serde(default)]
+    pub scrypt_params: String,
 }
 
 impl RustPwManSerialize {
-    pub fn new(seclevel: usize, pbkdf: &str, pwgen: &str, clip_command: &str, copy_command: &str, user: &str, pw: &str, server: &str) -> Self {
+    pub fn new(seclevel: usize, pbkdf: &str, pwgen: &str, clip_command: &str, copy_command: &str, user: &str, pw: &str, server: &str, trash_days: u64,
+               argon2_params: &str, scrypt_params: &str) -> Self {
         return RustPwManSerialize {
             seclevel: seclevel,
             pbkdf: String::from(pbkdf),
@@ -43,6 +52,9 @@ impl RustPwManSerialize {
             webdav_user: String::from(user),
             webdav_pw: String::from(pw),
             webdav_server: String::from(server),
+            trash_days: trash_days,
+            argon2_params: String::from(argon2_params),
+            scrypt_params: String::from(scrypt_params),
         }
     }
 }
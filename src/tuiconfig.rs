@@ -36,6 +36,9 @@ const BITS_SEC_VALUE: &str = "cfgseclevel";
 const SLIDER_SEC_NAME: &str = "cfgslider";
 const EDIT_PASTE_COMMAND: &str = "pastecmd";
 const EDIT_COPY_COMMAND: &str = "copycmd";
+const EDIT_TRASH_DAYS: &str = "trashdays";
+const EDIT_ARGON2_PARAMS: &str = "argon2params";
+const EDIT_SCRYPT_PARAMS: &str = "scryptparams";
 
 pub fn show_yes_no_decision(siv: &mut Cursive, msg: &str) {
     siv.add_layer(
@@ -125,6 +128,48 @@ pub fn save_new_config(s: &mut Cursive, u: &String, p: &String, srv: &String, co
         }
     };
 
+    let trash_days = match s.call_on_name(EDIT_TRASH_DAYS, |view: &mut EditView| { view.get_content() }) {
+        Some(v) => {
+            match v.trim().parse::<u64>() {
+                Ok(d) => d,
+                Err(_) => {
+                    show_message(s, "Number of days for the recycle bin is not valid");
+                    return;
+                }
+            }
+        },
+        None => {
+            show_message(s, "Unable to determine number of days for the recycle bin");
+            return;
+        }
+    };
+
+    let mut kdf_params: Vec<String> = Vec::new();
+
+    for (edit_name, id) in [(EDIT_ARGON2_PARAMS, fcrypt::KdfId::Argon2), (EDIT_SCRYPT_PARAMS, fcrypt::KdfId::Scrypt)] {
+        let params = match s.call_on_name(edit_name, |view: &mut EditView| { view.get_content() }) {
+            Some(v) => v.trim().to_string(),
+            None => {
+                show_message(s, &format!("Unable to determine parameters for {}", id.to_str()));
+                return;
+            }
+        };
+
+        if params != "" {
+            let is_valid = match fcrypt::KdfParams::from_str(&params) {
+                Some(p) => p.check(id).is_none(),
+                None => false
+            };
+
+            if !is_valid {
+                show_message(s, &format!("Parameters for {} are not valid", id.to_str()));
+                return;
+            }
+        }
+
+        kdf_params.push(params);
+    }
+
     #[cfg(feature = "webdav")]
     if let Some(t) = s.call_on_name("webdav_user", |view: &mut EditView| { view.get_content() }) {
         user = t.to_string();
@@ -152,7 +197,8 @@ pub fn save_new_config(s: &mut Cursive, u: &String, p: &String, srv: &String, co
     let strategy = strat.selection();
     let pbkdf = &pbkdf.selection();
 
-    let new_config = RustPwManSerialize::new(rand_bytes, pbkdf.to_str(), strategy.to_str(), clip_command.as_str(), copy_command.as_str(), user.as_str(), pw.as_str(), server.as_str());
+    let new_config = RustPwManSerialize::new(rand_bytes, pbkdf.to_str(), strategy.to_str(), clip_command.as_str(), copy_command.as_str(), user.as_str(), pw.as_str(), server.as_str(), trash_days,
+                                             &kdf_params[0], &kdf_params[1]);
 
     match tomlconfig::save(config_file, new_config) {
         Some(e) => {
@@ -165,7 +211,8 @@ pub fn save_new_config(s: &mut Cursive, u: &String, p: &String, srv: &String, co
 }
 
 pub fn config_main(config_file: std::path::PathBuf, sec_level: usize, pw_gen_strategy: pwgen::GenerationStrategy, pbkdf_id: fcrypt::KdfId,
-                   clp_cmd: &String, cpy_cmd: &String, webdav_user: &String, webdav_pw: &String, webdav_server: &String) {
+                   clp_cmd: &String, cpy_cmd: &String, webdav_user: &String, webdav_pw: &String, webdav_server: &String, trash_days: u64,
+                   argon2_params: &String, scrypt_params: &String) {
     let mut siv = cursive::default();
 
     let mut strategy_group: RadioGroup<pwgen::GenerationStrategy> = RadioGroup::new();
@@ -189,7 +236,8 @@ pub fn config_main(config_file: std::path::PathBuf, sec_level: usize, pw_gen_str
     }
 
     for i in &fcrypt::KdfId::get_known_ids() {
-        let mut b = pbkdf_group.button(*i, i.to_str());
+        let label = if i.is_deprecated() { format!("{} (deprecated)", i.to_str()) } else { i.to_string() };
+        let mut b = pbkdf_group.button(*i, label);
 
         if *i == pbkdf_id {
             b.select();
@@ -226,11 +274,32 @@ pub fn config_main(config_file: std::path::PathBuf, sec_level: usize, pw_gen_str
         .title("Parameters for password generation")
     );
 
+    let argon2_defaults = fcrypt::KdfId::Argon2.default_params().to_string();
+    let scrypt_defaults = fcrypt::KdfId::Scrypt.default_params().to_string();
+
     config_panels.add_child(
         Panel::new(
             PaddedView::new(Margins::lrtb(1,1,1,1),
-                linear_layout_pbkdf)
-            ).title("Default PBKDF")
+            LinearLayout::vertical()
+                .child(linear_layout_pbkdf)
+                .child(TextView::new("\n"))
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new(format!("{:<50}: ", format!("argon2 memory KiB,passes,parallelism (default {})", argon2_defaults))))
+                        .child(EditView::new()
+                            .content(argon2_params.clone())
+                            .with_name(EDIT_ARGON2_PARAMS)
+                            .fixed_width(20))
+                )
+                .child(
+                    LinearLayout::horizontal()
+                        .child(TextView::new(format!("{:<50}: ", format!("scrypt log2(N),r,p (default {})", scrypt_defaults))))
+                        .child(EditView::new()
+                            .content(scrypt_params.clone())
+                            .with_name(EDIT_SCRYPT_PARAMS)
+                            .fixed_width(20))
+                )
+            )).title("Default PBKDF")
     );
 
     config_panels.add_child(
@@ -256,6 +325,19 @@ pub fn config_main(config_file: std::path::PathBuf, sec_level: usize, pw_gen_str
             .title("Clipboard commands")
     );
 
+    config_panels.add_child(
+        Panel::new(
+            PaddedView::new(Margins::lrtb(1,1,1,1),
+                LinearLayout::horizontal()
+                    .child(TextView::new("Purge deleted entries after days (0 = never): "))
+                    .child(EditView::new()
+                        .content(trash_days.to_string())
+                        .with_name(EDIT_TRASH_DAYS)
+                        .fixed_width(10))
+            ))
+            .title("Recycle bin")
+    );
+
     #[cfg(feature = "webdav")]
     {
         config_panels.add_child(
@@ -13,51 +13,82 @@ See the License for the specific language governing permissions and
 limitations under the License. */
 
 
-use std::collections::HashMap;
 use std::collections::VecDeque;
 
 
-pub struct UndoEntry<T, S> 
+pub type UndoFunc<S> = Box<dyn FnMut(&mut S) -> bool + Send + Sync>;
+
+// A recorded operation. undo reverts the operation and redo applies it again. Both functions
+// may be called several times but always alternately.
+pub struct UndoEntry<S> 
 {
     pub comment: String,
-    pub f: Box<dyn FnMut(& mut HashMap<T,S>) -> bool + Send + Sync>,
+    pub undo: UndoFunc<S>,
+    pub redo: UndoFunc<S>,
 }
 
-impl<T, S> UndoEntry<T, S> {
-    pub fn new(c: &String, func: Box<dyn FnMut(&mut HashMap<T, S>) -> bool + Send + Sync>) -> UndoEntry<T, S> {
-        return UndoEntry::<T, S> {
+impl<S> UndoEntry<S> {
+    pub fn new(c: &String, undo_func: UndoFunc<S>, redo_func: UndoFunc<S>) -> UndoEntry<S> {
+        return UndoEntry::<S> {
             comment: c.clone(),
-            f: func,
+            undo: undo_func,
+            redo: redo_func,
         };
     }
 }
 
-pub struct UndoRepo<T, S> {
-    stack: VecDeque<UndoEntry<T, S>>
+pub struct UndoRepo<S> {
+    stack: VecDeque<UndoEntry<S>>,
+    redo_stack: Vec<UndoEntry<S>>
 }
 
-impl<T, S> UndoRepo<T, S> {
-    pub fn new() -> UndoRepo<T, S> {
-        return UndoRepo { stack: VecDeque::new() }
+impl<S> UndoRepo<S> {
+    pub fn new() -> UndoRepo<S> {
+        return UndoRepo { stack: VecDeque::new(), redo_stack: Vec::new() }
     }
 
-    // false means undo has failed
-    pub fn undo_one(&mut self, state: &mut HashMap<T, S>) -> (String, bool) {
-        let mut comment = String::new();
+    // false means undo has failed. An operation which has been undone successfully can be redone.
+    pub fn undo_one(&mut self, state: &mut S) -> (String, bool) {
+        let mut e = match self.stack.pop_back() {
+            Some(e) => e,
+            None => return (String::new(), true)
+        };
+
+        let call_res = (e.undo)(state);
+        let comment = e.comment.clone();
+
+        if call_res {
+            self.redo_stack.push(e);
+        } else {
+            // The state is unknown, so the remaining operations can not be redone
+            self.redo_stack.clear();
+        }
+
+        return (comment, call_res)
+    }
 
-        let call_res = match self.stack.pop_back() {
-            Some(mut e) => {
-                comment = e.comment;
-                (e.f)(state)
-            },
-            _ => true
+    // false means redo has failed. An operation which has been redone successfully can be undone again.
+    pub fn redo_one(&mut self, state: &mut S) -> (String, bool) {
+        let mut e = match self.redo_stack.pop() {
+            Some(e) => e,
+            None => return (String::new(), true)
         };
 
+        let call_res = (e.redo)(state);
+        let comment = e.comment.clone();
+
+        if call_res {
+            self.stack.push_back(e);
+        } else {
+            self.redo_stack.clear();
+        }
+
         return (comment, call_res)
     }
 
     pub fn clear(&mut self) {
         self.stack.clear();
+        self.redo_stack.clear();
     }
 
     pub fn get_comments(&self) -> Vec<String> {
@@ -70,11 +101,22 @@ impl<T, S> UndoRepo<T, S> {
         return res;
     }
 
-    pub fn push(&mut self, c: &String, func: Box<dyn FnMut(&mut HashMap<T, S>) -> bool + Send + Sync>) {
-        self.stack.push_back(UndoEntry::new(c, func));
+    // Returns the comments of all operations which can be redone. The operation which is redone next comes last.
+    pub fn get_redo_comments(&self) -> Vec<String> {
+        return self.redo_stack.iter().map(|e| e.comment.clone()).collect();
+    }
+
+    // Recording a new operation discards all operations which could have been redone
+    pub fn push(&mut self, c: &String, undo_func: UndoFunc<S>, redo_func: UndoFunc<S>) {
+        self.redo_stack.clear();
+        self.stack.push_back(UndoEntry::new(c, undo_func, redo_func));
     }
 
     pub fn is_all_undone(&self) -> bool {
         return self.stack.is_empty();
     }
+
+    pub fn is_all_redone(&self) -> bool {
+        return self.redo_stack.is_empty();
+    }
 }
\ No newline at end of file